@@ -1,9 +1,11 @@
 use super::template;
+use crate::user_config::{UserConfig, VaspBinEntry};
 use glob::glob;
 use home;
 use lazy_static::lazy_static;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::path::{Path, PathBuf};
 
 lazy_static! {
     pub static ref VASP_DIR: String = {
@@ -16,21 +18,44 @@ lazy_static! {
         };
         vasp_dir
     };
+    /// Registry of VASP binaries in the user config, by version and binary name.
+    pub static ref VASP_REGISTRY: BTreeMap<String, BTreeMap<String, VaspBinEntry>> = match UserConfig::load() {
+        Ok(config) => config.vasp,
+        Err(e) => {
+            eprintln!("Warning: {}", e);
+            BTreeMap::new()
+        }
+    };
+    /// Available VASP versions, from the registry and the `$VASP_DIR/<version>/<bin>` layout.
     pub static ref VASP_VERSIONS: Vec<String> = {
-        glob(&format!("{}/*.*.*", &*VASP_DIR))
+        let mut versions: Vec<String> = VASP_REGISTRY.keys().cloned().collect();
+        for version in glob(&format!("{}/*.*.*", &*VASP_DIR))
             .unwrap()
             .map(|path| path.unwrap().to_str().unwrap().to_string())
             .map(|path| path.split('/').last().unwrap().to_string())
-            .collect()
+        {
+            if !versions.contains(&version) {
+                versions.push(version);
+            }
+        }
+        versions
     };
     pub static ref VASP_BINS: HashMap<String, Vec<String>> = {
         let mut vasp_bins: HashMap<String, Vec<String>> = HashMap::new();
         for version in &*VASP_VERSIONS {
+            let mut all_bins: Vec<String> = VASP_REGISTRY
+                .get(version)
+                .map(|bins| bins.keys().cloned().collect())
+                .unwrap_or_default();
             let version_dir = format!("{}/{}", &*VASP_DIR, version);
-            let all_bins = glob(&format!("{}/*", version_dir))
+            for bin in glob(&format!("{}/*", version_dir))
                 .unwrap()
                 .map(|path| path.unwrap().file_name().unwrap().to_str().unwrap().to_string())
-                .collect::<Vec<String>>();
+            {
+                if !all_bins.contains(&bin) {
+                    all_bins.push(bin);
+                }
+            }
             vasp_bins.insert(version.clone(), all_bins);
         }
         vasp_bins
@@ -53,10 +78,7 @@ pub fn check_vasp_version(version: &str) -> Result<String, String> {
 pub fn check_task(task: &str) -> Result<String, String> {
     let available_tasks: Vec<&str> = template::TEMPLATES
         .get_template_names()
-        .filter_map(|name| match name.contains("INCAR") {
-            true => Some(name.split('_').last().unwrap()),
-            false => None,
-        })
+        .filter_map(|name| name.strip_prefix("INCAR_"))
         .collect();
 
     match available_tasks.iter().any(|name| name == &task) {
@@ -69,6 +91,77 @@ pub fn check_task(task: &str) -> Result<String, String> {
     }
 }
 
+/// Minimum VASP version required by tasks using newer functionals and features.
+const MIN_TASK_VERSIONS: [(&str, &str, &str); 5] = [
+    ("scan", "5.4.1", "SCAN"),
+    ("r2scan", "6.2.0", "r2SCAN"),
+    ("mlff_train", "6.4.0", "Machine-learned force field (ML_MODE)"),
+    ("mlff_refit", "6.4.0", "Machine-learned force field (ML_MODE)"),
+    ("mlff_run", "6.4.0", "Machine-learned force field (ML_MODE)"),
+];
+
+/// Parses version like "6.3.1" for comparison.
+fn parse_version(version: &str) -> Vec<u32> {
+    version.split('.').map(|v| v.parse().unwrap_or(0)).collect()
+}
+
+/// Checks if the vasp version and binary support the functional or feature of the task.
+pub fn check_task_support(task: &str, version: &str, bin: &str) -> Result<String, String> {
+    if let Some((_, min_version, functional)) = MIN_TASK_VERSIONS.iter().find(|(t, _, _)| *t == task) {
+        if parse_version(version) < parse_version(min_version) {
+            return Err(format!(
+                "{} requires VASP >= {}, but version {} is used.",
+                functional, min_version, version
+            ));
+        }
+    }
+    if ["hse", "pbe0"].contains(&task) && bin.contains("gpu") && parse_version(version) < parse_version("6.0.0") {
+        return Err(format!(
+            "Hybrid functionals are not supported by the GPU port of VASP {}.",
+            version
+        ));
+    }
+    Ok(task.to_string())
+}
+
+/// Path of the vasp binary of `version` in the `$VASP_DIR/<version>/<bin>` layout.
+pub fn vasp_bin_path(version: &str, bin: &str) -> PathBuf {
+    Path::new(&*VASP_DIR).join(version).join(bin)
+}
+
+/// VASP binary run by job scripts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedVaspBin {
+    /// Absolute path of the binary, or its name on PATH after `load`.
+    pub executable: String,
+    /// Command making the binary available, e.g. `module load vasp/6.4.2`.
+    pub load: Option<String>,
+    /// Where the binary was found: "user config" or "VASP_DIR".
+    pub source: &'static str,
+}
+
+/// Resolves the vasp binary from the registry of the user config, falling back to the path in
+/// the `$VASP_DIR/<version>/<bin>` layout, which may not exist.
+pub fn resolve_vasp_bin(version: &str, bin: &str) -> ResolvedVaspBin {
+    match VASP_REGISTRY.get(version).and_then(|bins| bins.get(bin)) {
+        Some(VaspBinEntry::Path(path)) => ResolvedVaspBin {
+            executable: path.clone(),
+            load: None,
+            source: "user config",
+        },
+        Some(VaspBinEntry::Module { load, executable }) => ResolvedVaspBin {
+            executable: executable.clone().unwrap_or_else(|| bin.to_string()),
+            load: Some(load.clone()),
+            source: "user config",
+        },
+        None => ResolvedVaspBin {
+            executable: vasp_bin_path(version, bin).to_str().unwrap().to_string(),
+            load: None,
+            source: "VASP_DIR",
+        },
+    }
+}
+
 /// Checks if the vasp binary is available.
 pub fn check_vasp_bin(version: &str, task: &str) -> Result<String, String> {
     match VASP_BINS.get(version) {
@@ -89,6 +182,267 @@ pub fn check_vasp_bin(version: &str, task: &str) -> Result<String, String> {
     }
 }
 
+/// Environment variable of the directory containing POTCAR of each potential, e.g. `$HOME/.local/POTCAR_PBE`.
+pub const POTCAR_PATH_ENV: &str = "POTCAR_PATH_PREFIX";
+
+/// Checks if any VASP version is available, from the registry of the user config or `VASP_DIR`.
+pub fn check_vasp_setup() -> Result<String, String> {
+    if VASP_VERSIONS.is_empty() {
+        return Err(format!(
+            "No VASP binary registered in the user config, and no VASP version directory (e.g. 6.3.1) found in {}.",
+            &*VASP_DIR
+        ));
+    }
+    Ok(VASP_VERSIONS.join(", "))
+}
+
+/// Checks if the POTCAR directory is set and not empty.
+pub fn check_potcar_dir() -> Result<String, String> {
+    let potcar_dir = env::var(POTCAR_PATH_ENV).map_err(|_| format!("{} is not set.", POTCAR_PATH_ENV))?;
+    let path = Path::new(&potcar_dir);
+    if !path.is_dir() {
+        return Err(format!("{} = {} is not a directory.", POTCAR_PATH_ENV, potcar_dir));
+    }
+    if path
+        .read_dir()
+        .map(|mut entries| entries.next().is_none())
+        .unwrap_or(true)
+    {
+        return Err(format!("{} = {} is empty.", POTCAR_PATH_ENV, potcar_dir));
+    }
+    Ok(potcar_dir)
+}
+
+/// Result of a single check of `vasp_manager doctor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// A check of the setup, with a hint to fix it if it did not pass.
+#[derive(Debug, Clone)]
+pub struct SetupCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub hint: Option<String>,
+}
+
+impl SetupCheck {
+    pub fn pass(name: &str, detail: String) -> SetupCheck {
+        SetupCheck {
+            name: name.to_string(),
+            status: CheckStatus::Pass,
+            detail,
+            hint: None,
+        }
+    }
+
+    pub fn warn(name: &str, detail: String, hint: String) -> SetupCheck {
+        SetupCheck {
+            name: name.to_string(),
+            status: CheckStatus::Warn,
+            detail,
+            hint: Some(hint),
+        }
+    }
+
+    pub fn fail(name: &str, detail: String, hint: String) -> SetupCheck {
+        SetupCheck {
+            name: name.to_string(),
+            status: CheckStatus::Fail,
+            detail,
+            hint: Some(hint),
+        }
+    }
+}
+
+impl std::fmt::Display for SetupCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let status = match self.status {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Warn => "WARN",
+            CheckStatus::Fail => "FAIL",
+        };
+        writeln!(f, "[{}] {}: {}", status, self.name, self.detail)?;
+        if let Some(hint) = &self.hint {
+            writeln!(f, "       hint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the registry and the VASP binary directory, and whether each binary of each version is executable.
+pub fn check_vasp_binaries() -> Vec<SetupCheck> {
+    let source = match env::var("VASP_DIR") {
+        Ok(_) => "from VASP_DIR",
+        Err(_) => "default, VASP_DIR is not set",
+    };
+    let dir_hint = "Put binaries in <VASP_DIR>/<version>/<bin> and set VASP_DIR, or register them in [vasp] of the \
+                    user config. See `vasp_manager --show-guide`.";
+    let mut checks = vec![];
+    if Path::new(&*VASP_DIR).is_dir() {
+        checks.push(SetupCheck::pass(
+            "VASP directory",
+            format!("{} ({})", &*VASP_DIR, source),
+        ));
+    } else if !VASP_REGISTRY.is_empty() {
+        checks.push(SetupCheck::pass(
+            "VASP directory",
+            format!("{} does not exist, binaries are taken from the user config", &*VASP_DIR),
+        ));
+    }
+    if let Err(e) = check_vasp_setup() {
+        checks.push(SetupCheck::fail("VASP binaries", e, String::from(dir_hint)));
+        return checks;
+    }
+
+    let mut versions = VASP_VERSIONS.clone();
+    versions.sort_by_key(|version| parse_version(version));
+    for version in &versions {
+        let mut bins = VASP_BINS[version].clone();
+        bins.sort();
+        let name = format!("VASP {}", version);
+        if bins.is_empty() {
+            checks.push(SetupCheck::fail(
+                &name,
+                String::from("no binaries"),
+                format!(
+                    "Copy or link VASP binaries (e.g. vasp_std) into {}/{}.",
+                    &*VASP_DIR, version
+                ),
+            ));
+            continue;
+        }
+        let mut available = vec![];
+        let mut not_executable = vec![];
+        for bin in bins {
+            let resolved = resolve_vasp_bin(version, &bin);
+            match (&resolved.load, resolved.source) {
+                (Some(load), _) => available.push(format!("{} (`{}`)", bin, load)),
+                (None, _) if !is_executable(Path::new(&resolved.executable)) => {
+                    not_executable.push(resolved.executable)
+                }
+                (None, "user config") => available.push(format!("{} ({})", bin, resolved.executable)),
+                (None, _) => available.push(bin),
+            }
+        }
+        if !available.is_empty() {
+            checks.push(SetupCheck::pass(&name, available.join(", ")));
+        }
+        if !not_executable.is_empty() {
+            checks.push(SetupCheck::fail(
+                &name,
+                format!("missing or not executable: {}", not_executable.join(", ")),
+                String::from("Run `chmod +x` on them, or fix the paths in the user config."),
+            ));
+        }
+    }
+    checks
+}
+
+/// Checks the POTCAR directory and the potentials found in it against the list of potentials in `potcar.json`.
+pub fn check_potcars() -> Vec<SetupCheck> {
+    let name = "POTCAR directory";
+    let potcar_dir = match check_potcar_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            let hint = match env::var("POTCAR_PREFIX_PATH") {
+                Ok(_) => format!(
+                    "POTCAR_PREFIX_PATH is set, but vasp_manager reads {}. Rename it.",
+                    POTCAR_PATH_ENV
+                ),
+                Err(_) => format!(
+                    "export {}=<directory containing Ac, Ag, ...>, e.g. $HOME/.local/POTCAR_PBE.",
+                    POTCAR_PATH_ENV
+                ),
+            };
+            return vec![SetupCheck::fail(name, e, hint)];
+        }
+    };
+    let mut checks = vec![SetupCheck::pass(
+        name,
+        format!("{} (from {})", potcar_dir, POTCAR_PATH_ENV),
+    )];
+
+    let potcars: Vec<serde_json::Value> = serde_json::from_str(POTCAR_LIST).unwrap();
+    let missing = |recommended_only: bool| -> (usize, Vec<String>) {
+        let selected: Vec<&str> = potcars
+            .iter()
+            .filter(|p| !recommended_only || p["recommended"].as_bool().unwrap_or(false))
+            .filter_map(|p| p["potcar_name"].as_str())
+            .collect();
+        let missing = selected
+            .iter()
+            .filter(|name| !Path::new(&potcar_dir).join(name).join("POTCAR").is_file())
+            .map(|name| name.to_string())
+            .collect();
+        (selected.len(), missing)
+    };
+    let abbreviate = |names: &[String]| match names.len() {
+        0..=10 => names.join(", "),
+        n => format!("{}, ... ({} more)", names[..10].join(", "), n - 10),
+    };
+    let (num_recommended, missing_recommended) = missing(true);
+    let found = format!(
+        "{}/{} found",
+        num_recommended - missing_recommended.len(),
+        num_recommended
+    );
+    checks.push(match missing_recommended.is_empty() {
+        true => SetupCheck::pass("Recommended POTCARs", found),
+        false => SetupCheck::fail(
+            "Recommended POTCARs",
+            format!("{}, missing {}", found, abbreviate(&missing_recommended)),
+            format!("Each potential should be in {}/<name>/POTCAR.", potcar_dir),
+        ),
+    });
+    let (num_all, missing_all) = missing(false);
+    let found = format!("{}/{} found", num_all - missing_all.len(), num_all);
+    checks.push(match missing_all.is_empty() {
+        true => SetupCheck::pass("All POTCARs", found),
+        false => SetupCheck::warn(
+            "All POTCARs",
+            format!("{}, missing {}", found, abbreviate(&missing_all)),
+            String::from("Only needed if selected in [potcar] of Vasp.toml."),
+        ),
+    });
+    checks
+}
+
+/// Checks if the slurm commands are on PATH.
+pub fn check_scheduler() -> Vec<SetupCheck> {
+    ["sbatch", "squeue", "scancel"]
+        .iter()
+        .map(|command| match find_in_path(command) {
+            Some(path) => SetupCheck::pass(command, path.to_str().unwrap().to_string()),
+            None => SetupCheck::fail(
+                command,
+                String::from("not found on PATH"),
+                String::from("Run vasp_manager on a login node of a slurm cluster, or load its slurm module."),
+            ),
+        })
+        .collect()
+}
+
+/// Full path of the command if it is found on PATH.
+pub fn find_in_path(command: &str) -> Option<PathBuf> {
+    let paths = env::var_os("PATH")?;
+    env::split_paths(&paths)
+        .map(|dir| dir.join(command))
+        .find(|path| is_executable(path))
+}
+
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+/// List of POTCARs with their recommendation by VASP.
+const POTCAR_LIST: &str = include_str!("data/potcar.json");
+
 mod tests {
     #[test]
     fn test_vasp_versions() {
@@ -98,4 +452,18 @@ mod tests {
         }
         assert!(VASP_VERSIONS.len() > 0);
     }
+
+    #[test]
+    fn test_check_task_support() {
+        use super::*;
+        assert!(check_task_support("relax", "5.4.4", "vasp_std").is_ok());
+        assert!(check_task_support("scan", "5.4.4", "vasp_std").is_ok());
+        assert!(check_task_support("r2scan", "5.4.4", "vasp_std").is_err());
+        assert!(check_task_support("r2scan", "6.2.0", "vasp_std").is_ok());
+        assert!(check_task_support("mlff_train", "6.3.2", "vasp_std").is_err());
+        assert!(check_task_support("mlff_run", "6.4.1", "vasp_std").is_ok());
+        assert!(check_task_support("hse", "5.4.4", "vasp_gpu").is_err());
+        assert!(check_task_support("hse", "5.4.4", "vasp_std").is_ok());
+        assert!(check_task_support("hse", "6.3.2", "vasp_gpu").is_ok());
+    }
 }
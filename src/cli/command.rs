@@ -32,7 +32,52 @@ pub enum Command {
     /// Continue existing job in new directory.
     #[clap(name = "continue_job")]
     Continue(ContinueJob),
-    /// Show available templates for the INCAR file and their contents.
+    /// Show available templates for INCAR, KPOINTS, job scripts and Vasp.toml, and their contents.
     #[clap(name = "show_incar")]
     ShowIncar(ShowIncar),
+    /// Summarize results of a VASP run from OSZICAR and OUTCAR.
+    #[clap(name = "summary")]
+    Summary(Summary),
+    /// Export structures, energies, forces and other quantities from vasprun.xml.
+    #[clap(name = "export")]
+    Export(Export),
+    /// Check convergence of finished jobs. Exits with non-zero code if not converged.
+    #[clap(name = "check")]
+    Check(Check),
+    /// Generate, submit and report ENCUT and k-points convergence tests.
+    #[clap(name = "converge")]
+    Converge(Converge),
+    /// Create jobs for parameter sweep defined in [sweep] of Vasp.toml.
+    #[clap(name = "sweep")]
+    Sweep(Sweep),
+    /// Report vibrational frequencies, imaginary modes and zero-point energy from OUTCAR.
+    #[clap(name = "freq")]
+    Freq(Freq),
+    /// Write total and projected DOS from DOSCAR as CSV, relative to the Fermi level.
+    #[clap(name = "dos")]
+    Dos(Dos),
+    /// Collect forces of displaced supercells of a phonon job into FORCE_SETS for phonopy.
+    #[clap(name = "force_sets")]
+    ForceSets(ForceSets),
+    /// Fit Birch-Murnaghan equation of state to the energies of an eos job.
+    #[clap(name = "eos")]
+    Eos(Eos),
+    /// Compute elastic constants and bulk and shear moduli from the stresses of an elastic job.
+    #[clap(name = "elastic")]
+    Elastic(Elastic),
+    /// Sum charge densities such as AECCAR0 and AECCAR2 (as chgsum.pl).
+    #[clap(name = "chgsum")]
+    Chgsum(Chgsum),
+    /// Report Bader net charges from ACF.dat and POTCAR valences, and write them as CSV.
+    #[clap(name = "bader")]
+    Bader(Bader),
+    /// Query and update the local database of created and submitted jobs.
+    #[clap(name = "db")]
+    Db(Db),
+    /// Check job directories against vasp_manager.lock written by create_job, reporting drift.
+    #[clap(name = "verify")]
+    Verify(Verify),
+    /// Check VASP binaries, POTCARs, slurm and config files, with hints to fix failed checks.
+    #[clap(name = "doctor")]
+    Doctor(Doctor),
 }
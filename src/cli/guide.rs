@@ -1,10 +1,10 @@
-const GUIDE_STRING: &str = r"======== Setup guide =========
+const GUIDE_STRING: &str = r#"======== Setup guide =========
 The following steps are required to setup vasp_manager:
-1. Set environment variable `POTCAR_PREFIX_PATH`
+1. Set environment variable `POTCAR_PATH_PREFIX`
 2. Setup VASP executable binaries
 
-======== 1. Set environment variable `POTCAR_PREFIX_PATH` =========
-The environment variable `POTCAR_PREFIX_PATH` is used to locate POTCAR files
+======== 1. Set environment variable `POTCAR_PATH_PREFIX` =========
+The environment variable `POTCAR_PATH_PREFIX` is used to locate POTCAR files
 and generating POTCAR automatically with respect to given POSCAR.
 VASP POTCAR files are usually locates as following example:
 
@@ -16,10 +16,10 @@ POTCAR_PBE
 ├── Zr_sv
 └── Zr_sv_GW
 
-Then, the environment variable `POTCAR_PREFIX_PATH` should be set to the
+Then, the environment variable `POTCAR_PATH_PREFIX` should be set to the
 full path of 'POTCAR_PBE' directory, for example, $HOME/.local/POTCAR_PBE.
 In this case, add the following line to your ~/.bashrc or ~/.zshrc:
-    export POTCAR_PREFIX_PATH=$HOME/.local/POTCAR_PBE
+    export POTCAR_PATH_PREFIX=$HOME/.local/POTCAR_PBE
 and restart your shell (or run `source ~/.bashrc`).
 
 ======== 2. Setup VASP executable binaries =========
@@ -40,7 +40,17 @@ $HOME/.local/VASP/bin
 
 So, in order to get vasp_manager working, you need to setup your VASP executable binaries like above.
 If you cannot copy or move binaries because of permission issue, you can just create symbolic link to them.
-";
+To use another directory instead of $HOME/.local/VASP/bin, set the environment variable `VASP_DIR` to it.
+
+Alternatively, register the binaries in ~/.config/vasp_manager/config.toml (or the file given by
+`VASP_MANAGER_CONFIG`), as absolute paths or commands loading them:
+
+[vasp."6.4.2"]
+vasp_std = { load = "module load vasp/6.4.2" }
+vasp_gam = "/opt/vasp/6.4.2/bin/vasp_gam"
+
+Run `vasp_manager doctor` to check the setup.
+"#;
 
 pub fn print_guide() {
     println!("{}", GUIDE_STRING);
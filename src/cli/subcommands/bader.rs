@@ -0,0 +1,31 @@
+use crate::output_parser::Bader as BaderAnalysis;
+use clap::Parser;
+use eyre::Result;
+use std::path::Path;
+
+#[derive(Parser)]
+pub struct Bader {
+    /// The directory of the job with ACF.dat of the `bader` program.
+    #[clap(default_value = ".")]
+    pub dir: String,
+    /// CSV file of per-atom charges, written in the directory.
+    #[clap(short, long, default_value = "bader.csv")]
+    pub output: String,
+    /// Print results as JSON.
+    #[clap(long, action)]
+    pub json: bool,
+}
+
+impl Bader {
+    /// Reports net charges of atoms and elements, and writes them as CSV.
+    pub fn run(&self) -> Result<()> {
+        let bader = BaderAnalysis::from_dir(&self.dir)?;
+        std::fs::write(Path::new(&self.dir).join(&self.output), bader.to_csv())?;
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&bader)?);
+        } else {
+            print!("{}", bader);
+        }
+        Ok(())
+    }
+}
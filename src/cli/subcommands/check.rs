@@ -0,0 +1,37 @@
+use crate::output_parser::ConvergenceCheck;
+use clap::Parser;
+use eyre::Result;
+
+#[derive(Parser)]
+pub struct Check {
+    /// Directories of the jobs to check.
+    #[clap(default_value = ".")]
+    pub dirs: Vec<String>,
+    /// Print results as JSON.
+    #[clap(long, action)]
+    pub json: bool,
+}
+
+impl Check {
+    /// Checks convergence of the jobs.
+    /// Exits with code 1 if any of them is not converged.
+    pub fn run(&self) -> Result<()> {
+        let mut checks = vec![];
+        for dir in &self.dirs {
+            // A directory without output is reported as not converged, without stopping the others.
+            let check = ConvergenceCheck::from_dir(dir).unwrap_or_else(|e| ConvergenceCheck::unreadable(dir, &e));
+            checks.push(check);
+        }
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&checks)?);
+        } else {
+            for check in &checks {
+                print!("{}", check);
+            }
+        }
+        if !checks.iter().all(|check| check.converged()) {
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,26 @@
+use crate::output_parser::ChargeDensity;
+use clap::Parser;
+use eyre::Result;
+
+#[derive(Parser)]
+pub struct Chgsum {
+    /// Charge density files to sum.
+    #[clap(multiple_values = true, default_values = &["AECCAR0", "AECCAR2"])]
+    pub files: Vec<String>,
+    /// Output file.
+    #[clap(short, long, default_value = "CHGCAR_sum")]
+    pub output: String,
+}
+
+impl Chgsum {
+    /// Sums charge densities, e.g. core and valence densities as reference of Bader analysis (as `chgsum.pl`).
+    pub fn run(&self) -> Result<()> {
+        let mut sum = ChargeDensity::from_file(&self.files[0])?;
+        for file in &self.files[1..] {
+            sum = sum.add(&ChargeDensity::from_file(file)?)?;
+        }
+        sum.to_file(&self.output)?;
+        println!("Writing {} = {}", self.output, self.files.join(" + "));
+        Ok(())
+    }
+}
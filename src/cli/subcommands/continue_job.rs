@@ -1,3 +1,6 @@
+use super::record_job_created;
+use crate::check_util::{check_task, check_task_support};
+use crate::config_parser::{carry_over_ml_files, JobConfig};
 use clap::Parser;
 use eyre::Result;
 use std::ffi::OsStr;
@@ -8,6 +11,10 @@ pub struct ContinueJob {
     /// The directory to continue the job in.
     #[clap(short, long)]
     pub dir: String,
+    /// Task of the new job, e.g. mlff_refit after mlff_train. Inputs are generated from Vasp.toml
+    /// in current directory instead of being copied.
+    #[clap(short, long)]
+    pub task: Option<String>,
 }
 
 impl ContinueJob {
@@ -29,10 +36,31 @@ impl ContinueJob {
         }
 
         copy_if_nonempty("CONTCAR", &job_dir.join("POSCAR"))?;
-        let files_to_copy = vec!["INCAR", "POTCAR", "KPOINTS", "job_script.sh", "WAVECAR", "CHGCAR"];
-        for file in files_to_copy {
-            println!("Copying {}", file);
-            copy_if_nonempty(file, &job_dir.join(file))?;
+        for (source, dest) in carry_over_ml_files(".", &self.dir)? {
+            println!("Copying {} to {}", source, dest);
+        }
+        match &self.task {
+            Some(task) => {
+                let mut job_config = JobConfig::from_dir(".")?;
+                check_task(task)?;
+                let vasp_config = &job_config.toml_contents["vasp"];
+                check_task_support(
+                    task,
+                    vasp_config["version"].as_str().unwrap(),
+                    vasp_config["bin"].as_str().unwrap(),
+                )?;
+                job_config.set_incar_base(task);
+                job_config.with_job_dir(&self.dir).write_config()?;
+                JobConfig::from_dir(&self.dir)?.create_job()?;
+                record_job_created(&self.dir);
+            }
+            None => {
+                let files_to_copy = vec!["INCAR", "POTCAR", "KPOINTS", "job_script.sh", "WAVECAR", "CHGCAR"];
+                for file in files_to_copy {
+                    println!("Copying {}", file);
+                    copy_if_nonempty(file, &job_dir.join(file))?;
+                }
+            }
         }
 
         println!("Created continue job in {}", &self.dir);
@@ -0,0 +1,240 @@
+use super::{record_job_created, submit_job_dirs, write_job_array};
+use crate::config_parser::JobConfig;
+use crate::output_parser::RunSummary;
+use clap::Parser;
+use eyre::Result;
+use std::path::Path;
+use toml::Value;
+
+/// Name of the manifest file mapping job directories to parameter values.
+const MANIFEST: &str = "converge.csv";
+
+#[derive(Parser)]
+pub struct Converge {
+    /// Range of ENCUT (eV) as start:stop:step, e.g. 400:700:50.
+    #[clap(long)]
+    pub encut: Option<String>,
+    /// Range of k-points density (1/Angstrom) as start:stop:step, e.g. 2.0:6.0:0.5.
+    #[clap(long)]
+    pub kdensity: Option<String>,
+    /// The directory to create convergence test jobs in.
+    #[clap(short, long, default_value = "converge")]
+    pub dir: String,
+    /// Whether submit jobs to slurm or not.
+    #[clap(short, long, action)]
+    pub submit: bool,
+    /// Submit jobs as a single slurm job array.
+    #[clap(long, action)]
+    pub array: bool,
+    /// Report energies of finished convergence test in `--dir` instead of creating jobs.
+    #[clap(long, action)]
+    pub report: bool,
+    /// Tolerance of energy per atom (meV/atom) for recommending parameters.
+    #[clap(long, default_value = "1.0")]
+    pub tolerance: f64,
+}
+
+/// Parses inclusive range `start:stop:step`. Values are rounded to the decimal places given in the range,
+/// which are returned for formatting the values.
+fn parse_range(range: &str) -> Result<(Vec<f64>, usize)> {
+    let values: Vec<f64> = range
+        .split(':')
+        .map(|v| v.trim().parse::<f64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| eyre::eyre!("Invalid range '{}'. Expected start:stop:step", range))?;
+    let decimals = range
+        .split(':')
+        .map(|v| v.trim().split_once('.').map_or(0, |(_, d)| d.len()))
+        .max()
+        .unwrap_or(0);
+    let scale = 10f64.powi(decimals as i32);
+    let round = |x: f64| (x * scale).round() / scale;
+    match values[..] {
+        [start, stop, step] if step > 0.0 && stop >= start => {
+            let n = ((stop - start) / step + 1e-8).floor() as usize + 1;
+            Ok(((0..n).map(|i| round(start + i as f64 * step)).collect(), decimals))
+        }
+        [value] => Ok((vec![value], decimals)),
+        _ => Err(eyre::eyre!("Invalid range '{}'. Expected start:stop:step", range)),
+    }
+}
+
+impl Converge {
+    pub fn run(&self) -> Result<()> {
+        match self.report {
+            true => self.report(),
+            false => self.create(),
+        }
+    }
+
+    fn create(&self) -> Result<()> {
+        let base_config = JobConfig::from_dir(".")?;
+        let mut jobs: Vec<(String, &str, f64, String)> = vec![];
+        for (range, name, parameter) in [
+            (&self.encut, "encut", "ENCUT"),
+            (&self.kdensity, "kdensity", "KDENSITY"),
+        ] {
+            if let Some(range) = range {
+                let (values, decimals) = parse_range(range)?;
+                for value in values {
+                    let formatted = format!("{:.*}", decimals, value);
+                    jobs.push((format!("{}_{}", name, formatted), parameter, value, formatted));
+                }
+            }
+        }
+        if jobs.is_empty() {
+            return Err(eyre::eyre!("Specify --encut and/or --kdensity"));
+        }
+
+        std::fs::create_dir_all(&self.dir)?;
+        let mut manifest = String::from("directory,parameter,value\n");
+        for (name, parameter, value, formatted) in &jobs {
+            let job_dir = Path::new(&self.dir).join(name);
+            let mut job_config = base_config.with_job_dir(job_dir.to_str().unwrap());
+            job_config.set_incar_base("singlepoint");
+            job_config.set_job_name(&format!("{}_{}", base_config.job_name(), name));
+            match *parameter {
+                "ENCUT" => job_config.set_incar_tag("ENCUT", Value::Float(*value)),
+                _ => job_config.set_kpoints_density(*value),
+            }
+            job_config.write_config()?;
+            job_config.create_job()?;
+            record_job_created(&job_config.job_dir);
+            manifest.push_str(&format!("{},{},{}\n", name, parameter, formatted));
+        }
+        std::fs::write(Path::new(&self.dir).join(MANIFEST), manifest)?;
+        println!("Created {} convergence test jobs in {}", jobs.len(), self.dir);
+
+        let dirs: Vec<String> = jobs.into_iter().map(|(name, _, _, _)| name).collect();
+        // The array runs the steps of the singlepoint jobs.
+        let mut array_config = base_config.with_job_dir(&self.dir);
+        array_config.set_incar_base("singlepoint");
+        if self.array {
+            write_job_array(&array_config, &self.dir, &dirs, None)?;
+        }
+        if self.submit {
+            submit_job_dirs(&array_config, &self.dir, &dirs, self.array)?;
+        }
+        Ok(())
+    }
+
+    fn report(&self) -> Result<()> {
+        let manifest = std::fs::read_to_string(Path::new(&self.dir).join(MANIFEST))
+            .map_err(|_| eyre::eyre!("{} not found in {}", MANIFEST, self.dir))?;
+        let mut rows: Vec<(String, f64, Option<f64>)> = vec![];
+        for line in manifest.lines().skip(1) {
+            let fields: Vec<&str> = line.split(',').collect();
+            if fields.len() != 3 {
+                continue;
+            }
+            let job_dir = Path::new(&self.dir).join(fields[0]);
+            let energy_per_atom = RunSummary::from_dir(job_dir.to_str().unwrap())
+                .ok()
+                .and_then(|summary| Some(summary.energy_sigma0? / summary.num_ions? as f64));
+            rows.push((fields[1].to_string(), fields[2].parse()?, energy_per_atom));
+        }
+
+        for parameter in ["ENCUT", "KDENSITY"] {
+            let mut values: Vec<(f64, Option<f64>)> = rows
+                .iter()
+                .filter(|(p, _, _)| p == parameter)
+                .map(|(_, value, energy)| (*value, *energy))
+                .collect();
+            if values.is_empty() {
+                continue;
+            }
+            values.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            // The most accurate (largest) finished value is the reference.
+            let reference = values
+                .iter()
+                .rev()
+                .find_map(|(value, energy)| Some((*value, (*energy)?)));
+
+            println!("========= {} =========", parameter);
+            println!("{:>10} {:>18} {:>18}", "value", "E0 (eV/atom)", "diff (meV/atom)");
+            for (value, energy) in &values {
+                match (energy, reference) {
+                    (Some(e), Some((_, r))) => println!("{:>10} {:>18.6} {:>18.3}", value, e, (e - r) * 1000.0),
+                    _ => println!("{:>10} {:>18} {:>18}", value, "not finished", "-"),
+                }
+            }
+
+            let largest = values.last().unwrap().0;
+            if let Some((reference_value, _)) = reference.filter(|(value, _)| *value != largest) {
+                println!(
+                    "{} {} has not finished. Differences are relative to {}.",
+                    parameter, largest, reference_value
+                );
+            }
+            match recommend(&values, self.tolerance) {
+                Some(value) => println!(
+                    "Recommended {}: {} (tolerance {} meV/atom)",
+                    parameter, value, self.tolerance
+                ),
+                None => println!("Could not recommend {}: no finished jobs", parameter),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Smallest value within `tolerance` (meV/atom) of the largest finished value,
+/// for which all larger finished values are also within tolerance.
+/// `values` are sorted in ascending order, with energies of finished jobs.
+/// Unfinished values larger than the reference are skipped, and smaller ones stop the search.
+fn recommend(values: &[(f64, Option<f64>)], tolerance: f64) -> Option<f64> {
+    let mut finished = values.iter().rev().skip_while(|(_, energy)| energy.is_none());
+    let (mut recommended, reference) = finished.next().map(|(value, energy)| (*value, energy.unwrap()))?;
+    for (value, energy) in finished {
+        match energy {
+            Some(e) if ((e - reference) * 1000.0).abs() <= tolerance => recommended = *value,
+            _ => break,
+        }
+    }
+    Some(recommended)
+}
+
+mod tests {
+    #[test]
+    fn test_parse_range() {
+        use super::*;
+        assert_eq!(
+            parse_range("400:550:50").unwrap(),
+            (vec![400.0, 450.0, 500.0, 550.0], 0)
+        );
+        let (values, decimals) = parse_range("2.0:3.0:0.1").unwrap();
+        assert_eq!(values.len(), 11);
+        assert_eq!(values[3], 2.3);
+        assert_eq!(format!("{:.*}", decimals, values[3]), "2.3");
+        assert_eq!(parse_range("2.5:3.2:0.25").unwrap(), (vec![2.5, 2.75, 3.0], 2));
+        assert_eq!(parse_range("520").unwrap(), (vec![520.0], 0));
+        assert!(parse_range("700:400:50").is_err());
+        assert!(parse_range("400:700:0").is_err());
+        assert!(parse_range("400:700").is_err());
+    }
+
+    #[test]
+    fn test_recommend() {
+        use super::*;
+        let values = [
+            (400.0, Some(-5.4000)),
+            (450.0, Some(-5.4200)),
+            (500.0, Some(-5.4215)),
+            (550.0, Some(-5.4220)),
+        ];
+        assert_eq!(recommend(&values, 1.0), Some(500.0));
+        assert_eq!(recommend(&values, 0.1), Some(550.0));
+        // The largest value has not finished.
+        let values = [
+            (400.0, Some(-5.4000)),
+            (450.0, Some(-5.4200)),
+            (500.0, Some(-5.4215)),
+            (550.0, None),
+        ];
+        assert_eq!(recommend(&values, 2.0), Some(450.0));
+        // An unfinished value below the reference stops the search.
+        let values = [(400.0, Some(-5.4200)), (450.0, None), (500.0, Some(-5.4215))];
+        assert_eq!(recommend(&values, 2.0), Some(500.0));
+        assert_eq!(recommend(&[(400.0, None), (450.0, None)], 2.0), None);
+    }
+}
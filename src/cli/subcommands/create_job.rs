@@ -1,28 +1,50 @@
-use super::submit_slurm;
+use super::{record_job_created, record_job_submitted, resolve_job_dirs, run_batch, submit_slurm_queued};
 use crate::config_parser;
 use clap::Parser;
 use eyre::Result;
 
 #[derive(Parser)]
 pub struct CreateJob {
-    /// The directory to create the job in.
-    #[clap(short, long, default_value = ".")]
-    pub dir: String,
+    /// The directories (or glob patterns) to create the jobs in.
+    #[clap(short, long = "dir", multiple_values = true, default_value = ".")]
+    pub dirs: Vec<String>,
+    /// Create jobs in all directories containing Vasp.toml under the given directories.
+    #[clap(short = 'R', long, action)]
+    pub recursive: bool,
+    /// Keep going with the remaining directories if a job fails.
+    #[clap(long, action)]
+    pub continue_on_error: bool,
     /// Whether submit job to slurm or not.
     #[clap(short, long, action)]
     pub submit: bool,
+    /// Maximum number of jobs in the slurm queue. Waits before submitting if reached,
+    /// and after a submission rejected for the limit of submitted jobs.
+    #[clap(long, requires = "submit")]
+    pub max_queued: Option<usize>,
+    /// Interval (seconds) to check the slurm queue while waiting.
+    #[clap(long, default_value = "60")]
+    pub wait_interval: u64,
 }
 
 impl CreateJob {
     pub fn run(&self) -> Result<()> {
-        let job_config = config_parser::JobConfig::from_dir(&self.dir)?;
-        job_config.create_job()?;
+        let dirs = resolve_job_dirs(&self.dirs, self.recursive)?;
+        run_batch(&dirs, self.continue_on_error, |dir| {
+            let job_config = config_parser::JobConfig::from_dir(dir)?;
+            job_config.create_job()?;
+            record_job_created(dir);
 
-        if self.submit {
-            let job_name = &job_config.toml_contents["slurm"]["job_name"].as_str().unwrap();
-            std::env::set_current_dir(job_config.job_dir)?;
-            submit_slurm(job_name)?;
-        }
-        Ok(())
+            if self.submit {
+                let job_id = submit_slurm_queued(
+                    dir,
+                    job_config.job_name(),
+                    "job_script.sh",
+                    self.max_queued,
+                    self.wait_interval,
+                )?;
+                record_job_submitted(dir, Some(&job_id));
+            }
+            Ok(())
+        })
     }
 }
@@ -0,0 +1,170 @@
+use super::resolve_job_dirs;
+use crate::job_db::{format_time, parse_date, JobDatabase, JobFilter, JobRecord, JobStatus};
+use clap::{Parser, Subcommand};
+use eyre::Result;
+
+#[derive(Parser)]
+pub struct Db {
+    #[clap(subcommand)]
+    pub command: DbCommand,
+}
+
+#[derive(Subcommand)]
+pub enum DbCommand {
+    /// List recorded jobs matching the filters.
+    #[clap(name = "query")]
+    Query(DbQuery),
+    /// Update states and energies of recorded jobs from their directories and the slurm queue.
+    #[clap(name = "sync")]
+    Sync(DbSync),
+}
+
+#[derive(Parser)]
+pub struct DbQuery {
+    /// Task (base INCAR template) of the jobs, e.g. relax.
+    #[clap(short, long)]
+    pub task: Option<String>,
+    /// Status of the jobs.
+    #[clap(short, long, value_parser = ["created", "submitted", "running", "finished", "unconverged", "failed", "missing"])]
+    pub status: Option<String>,
+    /// Chemical formula of the jobs. Matches the reduced formula, e.g. NaCl matches Na4Cl4.
+    #[clap(short, long)]
+    pub formula: Option<String>,
+    /// Jobs created on or after the date (YYYY-MM-DD, UTC).
+    #[clap(long)]
+    pub since: Option<String>,
+    /// Jobs created before the date (YYYY-MM-DD, UTC).
+    #[clap(long)]
+    pub until: Option<String>,
+    /// Print jobs as JSON.
+    #[clap(long, action)]
+    pub json: bool,
+}
+
+#[derive(Parser)]
+pub struct DbSync {
+    /// Directories to scan for jobs (directories containing Vasp.toml) not yet in the database.
+    #[clap(multiple_values = true)]
+    pub dirs: Vec<String>,
+    /// Remove jobs whose directory no longer exists.
+    #[clap(long, action)]
+    pub prune: bool,
+}
+
+impl Db {
+    pub fn run(&self) -> Result<()> {
+        match &self.command {
+            DbCommand::Query(query) => query.run(),
+            DbCommand::Sync(sync) => sync.run(),
+        }
+    }
+}
+
+impl DbQuery {
+    pub fn run(&self) -> Result<()> {
+        let filter = JobFilter {
+            task: self.task.clone(),
+            status: self.status.as_deref().map(|s| s.parse()).transpose()?,
+            formula: self.formula.clone(),
+            since: self.since.as_deref().map(parse_date).transpose()?,
+            until: self.until.as_deref().map(parse_date).transpose()?,
+        };
+        let db = JobDatabase::load()?;
+        let jobs = db.query(&filter);
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&jobs)?);
+            return Ok(());
+        }
+        if jobs.is_empty() {
+            println!("No jobs found");
+            return Ok(());
+        }
+        println!(
+            "{:<11} {:<12} {:<12} {:>16} {:<12} {:<16} path",
+            "status", "task", "formula", "energy (eV)", "job id", "created"
+        );
+        for job in &jobs {
+            println!("{}", format_job(job));
+        }
+        println!("{} jobs", jobs.len());
+        Ok(())
+    }
+}
+
+impl DbSync {
+    pub fn run(&self) -> Result<()> {
+        let queued = queued_job_ids();
+        if queued.is_none() {
+            eprintln!("Warning: could not read the slurm queue. Jobs which left the queue are not marked as failed.");
+        }
+        let dirs = match self.dirs.is_empty() {
+            true => vec![],
+            false => resolve_job_dirs(&self.dirs, true)?,
+        };
+        let (num_jobs, num_added, num_updated, num_removed) = JobDatabase::update(|db| {
+            let mut num_added = 0;
+            for dir in &dirs {
+                let record = JobRecord::from_dir(dir)?;
+                if db.get_mut(&record.path).is_none() {
+                    db.jobs.push(record);
+                    num_added += 1;
+                }
+            }
+            let num_updated = db
+                .jobs
+                .iter_mut()
+                .map(|job| job.sync(queued.as_deref()))
+                .filter(|&changed| changed)
+                .count();
+            let mut num_removed = 0;
+            if self.prune {
+                let num_jobs = db.jobs.len();
+                db.jobs.retain(|job| job.status != JobStatus::Missing);
+                num_removed = num_jobs - db.jobs.len();
+            }
+            Ok((db.jobs.len(), num_added, num_updated, num_removed))
+        })?;
+        println!(
+            "{} jobs: {} added, {} updated, {} removed",
+            num_jobs, num_added, num_updated, num_removed
+        );
+        Ok(())
+    }
+}
+
+fn format_job(job: &JobRecord) -> String {
+    let or_dash = |s: &str| match s {
+        "" => String::from("-"),
+        s => s.to_string(),
+    };
+    format!(
+        "{:<11} {:<12} {:<12} {:>16} {:<12} {:<16} {}",
+        job.status.to_string(),
+        or_dash(&job.task),
+        or_dash(job.formula.as_deref().unwrap_or_default()),
+        job.energy
+            .map(|e| format!("{:.6}", e))
+            .unwrap_or_else(|| String::from("-")),
+        or_dash(job.job_id.as_deref().unwrap_or_default()),
+        format_time(job.created),
+        job.path
+    )
+}
+
+/// Ids of the jobs of the current user in the slurm queue, or None if squeue could not be run.
+fn queued_job_ids() -> Option<Vec<String>> {
+    let user = std::env::var("USER").ok()?;
+    let output = std::process::Command::new("squeue")
+        .args(["-h", "-u", &user, "-o", "%i"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.trim().to_string())
+            .collect(),
+    )
+}
@@ -0,0 +1,92 @@
+use crate::check_util::{check_potcars, check_scheduler, check_vasp_binaries, CheckStatus, SetupCheck};
+use crate::config_parser::JobConfig;
+use crate::job_db::JobDatabase;
+use crate::user_config::UserConfig;
+use clap::Parser;
+use eyre::Result;
+use std::path::Path;
+
+#[derive(Parser)]
+pub struct Doctor {}
+
+impl Doctor {
+    /// Reports checks of VASP binaries, POTCARs, slurm and config files.
+    /// Exits with code 1 if any check failed.
+    pub fn run(&self) -> Result<()> {
+        let sections = [
+            ("VASP binaries", check_vasp_binaries()),
+            ("POTCAR", check_potcars()),
+            ("Scheduler", check_scheduler()),
+            ("Config files", check_config_files()),
+        ];
+        let mut num_failed = 0;
+        let mut num_warned = 0;
+        for (title, checks) in &sections {
+            println!("========= {} =========", title);
+            for check in checks {
+                print!("{}", check);
+                match check.status {
+                    CheckStatus::Fail => num_failed += 1,
+                    CheckStatus::Warn => num_warned += 1,
+                    CheckStatus::Pass => (),
+                }
+            }
+        }
+        println!("{} failed, {} warnings", num_failed, num_warned);
+        if num_failed > 0 {
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+}
+
+/// Checks Vasp.toml in current directory, the user config and the job database.
+fn check_config_files() -> Vec<SetupCheck> {
+    let mut checks = vec![match Path::new("Vasp.toml").exists() {
+        false => SetupCheck::warn(
+            "Vasp.toml",
+            String::from("not found in current directory"),
+            String::from("Run `vasp_manager init` to create one."),
+        ),
+        true => match JobConfig::from_dir(".") {
+            Ok(_) => SetupCheck::pass("Vasp.toml", String::from("found in current directory")),
+            Err(e) => SetupCheck::fail(
+                "Vasp.toml",
+                e.to_string(),
+                String::from("Fix the TOML syntax, or recreate it with `vasp_manager init`."),
+            ),
+        },
+    }];
+    if let Some(path) = UserConfig::path() {
+        let path_str = path.to_str().unwrap().to_string();
+        checks.push(match (path.exists(), UserConfig::load()) {
+            (false, _) => SetupCheck::warn(
+                "User config",
+                format!("{} (not found)", path_str),
+                String::from("Create it to register VASP binaries by path or module, e.g. [vasp.\"6.4.2\"] vasp_std = { load = \"module load vasp/6.4.2\" }."),
+            ),
+            (true, Ok(config)) => SetupCheck::pass(
+                "User config",
+                format!("{} ({} VASP versions registered)", path_str, config.vasp.len()),
+            ),
+            (true, Err(e)) => SetupCheck::fail("User config", e.to_string(), String::from("Fix the TOML syntax.")),
+        });
+    }
+    if let Ok(path) = JobDatabase::path() {
+        let path_str = path.to_str().unwrap().to_string();
+        checks.push(match (path.exists(), JobDatabase::load()) {
+            (false, _) => SetupCheck::warn(
+                "Job database",
+                format!("{} (not created yet)", path_str),
+                String::from("It is created when a job is created or submitted."),
+            ),
+            (true, Ok(db)) => SetupCheck::pass("Job database", format!("{} ({} jobs)", path_str, db.jobs.len())),
+            (true, Err(e)) => SetupCheck::fail(
+                "Job database",
+                e.to_string(),
+                format!("Move {} away to start a new database.", path_str),
+            ),
+        });
+    }
+    checks
+}
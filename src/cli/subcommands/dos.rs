@@ -0,0 +1,56 @@
+use crate::output_parser::Doscar;
+use crate::structure_util::atom_species;
+use clap::Parser;
+use eyre::Result;
+use std::path::Path;
+use vasp_files::poscar::Poscar;
+
+#[derive(Parser)]
+pub struct Dos {
+    /// The directory of the finished DOS job.
+    #[clap(default_value = ".")]
+    pub dir: String,
+    /// Prefix of the output files, written as `<prefix>_total.csv` and `<prefix>_projected.csv` in the directory.
+    #[clap(short, long, default_value = "dos")]
+    pub output: String,
+}
+
+impl Dos {
+    /// Writes total and projected DOS as CSV, with energies relative to the Fermi level.
+    /// Projected DOS is summed over atoms of each element and over m of each orbital.
+    pub fn run(&self) -> Result<()> {
+        let dir = Path::new(&self.dir);
+        let doscar = Doscar::from_file(dir.join("DOSCAR"))?;
+        let energies = doscar.shifted_energies();
+        println!("Fermi energy: {:.4} eV", doscar.fermi_energy);
+
+        let total_names: Vec<String> = match doscar.is_spin_polarized() {
+            true => vec!["total_up".into(), "total_down".into()],
+            false => vec!["total".into()],
+        };
+        let total_path = dir.join(format!("{}_total.csv", self.output));
+        std::fs::write(&total_path, to_csv(&energies, &total_names, &doscar.total))?;
+        println!("Writing {}", total_path.display());
+
+        if doscar.projected.is_empty() {
+            println!("No projected DOS in DOSCAR. Set LORBIT to write it.");
+            return Ok(());
+        }
+        // CONTCAR and POSCAR have the same species, but CONTCAR may be empty if the job failed early.
+        let poscar = Poscar::from_file(dir.join("CONTCAR")).or_else(|_| Poscar::from_file(dir.join("POSCAR")))?;
+        let (names, columns) = doscar.element_orbital_dos(&atom_species(&poscar))?;
+        let projected_path = dir.join(format!("{}_projected.csv", self.output));
+        std::fs::write(&projected_path, to_csv(&energies, &names, &columns))?;
+        println!("Writing {}", projected_path.display());
+        Ok(())
+    }
+}
+
+fn to_csv(energies: &[f64], names: &[String], columns: &[Vec<f64>]) -> String {
+    let mut csv = format!("energy,{}\n", names.join(","));
+    for (i, energy) in energies.iter().enumerate() {
+        let row: Vec<String> = columns.iter().map(|column| format!("{:.6}", column[i])).collect();
+        csv.push_str(&format!("{:.6},{}\n", energy, row.join(",")));
+    }
+    csv
+}
@@ -0,0 +1,45 @@
+use crate::config_parser::JobConfig;
+use crate::output_parser::{ElasticTensor, Outcar};
+use clap::Parser;
+use eyre::Result;
+use std::path::Path;
+
+#[derive(Parser)]
+pub struct Elastic {
+    /// The directory of the finished elastic job.
+    #[clap(default_value = ".")]
+    pub dir: String,
+    /// Print results as JSON.
+    #[clap(long, action)]
+    pub json: bool,
+}
+
+impl Elastic {
+    /// Computes elastic constants from the stresses of the strained cells.
+    pub fn run(&self) -> Result<()> {
+        let job_config = JobConfig::from_dir(&self.dir)?;
+        if job_config.incar_base() != "elastic" {
+            return Err(eyre::eyre!("{} is not an elastic job", self.dir));
+        }
+        let mut samples = vec![];
+        for strain in job_config.elastic_strains()? {
+            let outcar_path = Path::new(&self.dir).join(&strain.dir).join("OUTCAR");
+            let outcar = Outcar::from_file(&outcar_path)?;
+            let [xx, yy, zz, xy, yz, zx] = outcar
+                .last_step()
+                .and_then(|step| step.stress)
+                .ok_or_else(|| eyre::eyre!("Stress not found in {}", outcar_path.display()))?;
+            // OUTCAR reports stress in kB with the opposite sign, positive under compression.
+            let stress = [xx, yy, zz, yz, zx, xy].map(|s| -0.1 * s);
+            samples.push((strain.component, strain.magnitude, stress));
+        }
+        let tensor = ElasticTensor::fit(&samples)?;
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&tensor)?);
+        } else {
+            print!("{}", tensor);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use crate::config_parser::JobConfig;
+use crate::output_parser::{BirchMurnaghan, Outcar};
+use clap::Parser;
+use eyre::Result;
+use serde_json::json;
+use std::path::Path;
+
+#[derive(Parser)]
+pub struct Eos {
+    /// The directory of the finished eos job.
+    #[clap(default_value = ".")]
+    pub dir: String,
+    /// Print results as JSON.
+    #[clap(long, action)]
+    pub json: bool,
+}
+
+impl Eos {
+    /// Fits Birch-Murnaghan equation of state to the energies of the strained cells.
+    pub fn run(&self) -> Result<()> {
+        let job_config = JobConfig::from_dir(&self.dir)?;
+        if job_config.incar_base() != "eos" {
+            return Err(eyre::eyre!("{} is not an eos job", self.dir));
+        }
+        let mut volumes = vec![];
+        let mut energies = vec![];
+        for (dir, _) in job_config.eos_volumes()? {
+            let outcar_path = Path::new(&self.dir).join(&dir).join("OUTCAR");
+            let outcar = Outcar::from_file(&outcar_path)?;
+            let step = outcar
+                .last_step()
+                .filter(|step| step.volume.is_some())
+                .ok_or_else(|| eyre::eyre!("Energy and volume not found in {}", outcar_path.display()))?;
+            volumes.push(step.volume.unwrap());
+            energies.push(step.energy_sigma0.or(step.free_energy).unwrap());
+        }
+        let fit = BirchMurnaghan::fit(&volumes, &energies)?;
+
+        if self.json {
+            let result = json!({ "volumes": volumes, "energies": energies, "fit": fit });
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        } else {
+            println!("{:>12} {:>14} {:>14}", "V (A^3)", "E (eV)", "E_fit (eV)");
+            for (volume, energy) in volumes.iter().zip(&energies) {
+                println!("{:12.4} {:14.6} {:14.6}", volume, energy, fit.energy(*volume));
+            }
+            println!();
+            print!("{}", fit);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,251 @@
+use crate::output_parser::{VasprunItem, VasprunReader, VasprunStep};
+use crate::structure_util::{atom_species, cartesian_coords, scaled_lattice, volume};
+use clap::Parser;
+use eyre::Result;
+use serde_json::{json, Map, Value};
+use std::io::{BufWriter, Write};
+
+/// Conversion factor from kB to eV/Angstrom^3.
+const KBAR_TO_EV_PER_ANG3: f64 = 0.1 / 160.21766208;
+
+#[derive(Parser)]
+pub struct Export {
+    /// Path to vasprun.xml.
+    #[clap(short, long, default_value = "vasprun.xml")]
+    pub file: String,
+    /// Output format.
+    #[clap(long, default_value = "xyz", value_parser = ["xyz", "json", "csv"])]
+    pub format: String,
+    /// Quantities to export, separated by comma.
+    /// Available: energy, forces, stress, structure, eigenvalues, dos, incar, kpoints.
+    /// eigenvalues, dos, incar and kpoints are only available in JSON format.
+    #[clap(short, long, value_delimiter = ',', default_value = "energy,forces,stress,structure")]
+    pub quantities: Vec<String>,
+    /// Output file. If not given, prints to stdout.
+    #[clap(short, long)]
+    pub output: Option<String>,
+}
+
+const STEP_QUANTITIES: [&str; 4] = ["energy", "forces", "stress", "structure"];
+const JSON_ONLY_QUANTITIES: [&str; 4] = ["eigenvalues", "dos", "incar", "kpoints"];
+
+impl Export {
+    pub fn run(&self) -> Result<()> {
+        for quantity in &self.quantities {
+            if JSON_ONLY_QUANTITIES.contains(&quantity.as_str()) && self.format != "json" {
+                return Err(eyre::eyre!("'{}' can only be exported in JSON format", quantity));
+            } else if !STEP_QUANTITIES.contains(&quantity.as_str())
+                && !JSON_ONLY_QUANTITIES.contains(&quantity.as_str())
+            {
+                return Err(eyre::eyre!("Unknown quantity: {}", quantity));
+            }
+        }
+
+        let mut writer: BufWriter<Box<dyn Write>> = match &self.output {
+            Some(path) => BufWriter::new(Box::new(std::fs::File::create(path)?)),
+            None => BufWriter::new(Box::new(std::io::stdout())),
+        };
+        let reader = VasprunReader::from_file(&self.file)?;
+        match self.format.as_str() {
+            "xyz" => self.write_xyz(reader, &mut writer)?,
+            "json" => self.write_json(reader, &mut writer)?,
+            "csv" => self.write_csv(reader, &mut writer)?,
+            _ => unreachable!(),
+        }
+        writer.flush()?;
+        if let Some(path) = &self.output {
+            eprintln!("Exported {} to {}", self.file, path);
+        }
+        Ok(())
+    }
+
+    fn has(&self, quantity: &str) -> bool {
+        self.quantities.iter().any(|q| q == quantity)
+    }
+
+    /// Writes extended XYZ. Energies, forces and stress follow the ASE convention.
+    fn write_xyz<R: std::io::BufRead>(&self, reader: VasprunReader<R>, writer: &mut impl Write) -> Result<()> {
+        for item in reader {
+            let step = match item? {
+                VasprunItem::Step(step) => step,
+                _ => continue,
+            };
+            let structure = &step.structure;
+            let lattice = scaled_lattice(structure);
+            let species = atom_species(structure);
+            let positions = cartesian_coords(structure);
+            let with_forces = self.has("forces") && step.forces.len() == positions.len();
+
+            let mut comment = vec![format!(
+                "Lattice=\"{}\"",
+                lattice
+                    .iter()
+                    .flatten()
+                    .map(|x| format!("{:.8}", x))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )];
+            let mut properties = String::from("species:S:1:pos:R:3");
+            if with_forces {
+                properties.push_str(":forces:R:3");
+            }
+            comment.push(format!("Properties={}", properties));
+            if self.has("energy") {
+                if let Some(energy) = step.energy_sigma0 {
+                    comment.push(format!("energy={:.8}", energy));
+                }
+                if let Some(free_energy) = step.free_energy {
+                    comment.push(format!("free_energy={:.8}", free_energy));
+                }
+            }
+            if self.has("stress") {
+                if let Some(stress) = step.stress {
+                    let stress: Vec<String> = stress
+                        .iter()
+                        .flatten()
+                        .map(|s| format!("{:.8}", -s * KBAR_TO_EV_PER_ANG3))
+                        .collect();
+                    comment.push(format!("stress=\"{}\"", stress.join(" ")));
+                }
+            }
+            comment.push(String::from("pbc=\"T T T\""));
+
+            writeln!(writer, "{}", positions.len())?;
+            writeln!(writer, "{}", comment.join(" "))?;
+            for (i, (element, pos)) in species.iter().zip(positions.iter()).enumerate() {
+                write!(
+                    writer,
+                    "{:<3} {:>15.8} {:>15.8} {:>15.8}",
+                    element, pos[0], pos[1], pos[2]
+                )?;
+                if with_forces {
+                    let f = step.forces[i];
+                    write!(writer, " {:>15.8} {:>15.8} {:>15.8}", f[0], f[1], f[2])?;
+                }
+                writeln!(writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes JSON. Steps are written as they are parsed, other quantities are appended at the end.
+    fn write_json<R: std::io::BufRead>(&self, reader: VasprunReader<R>, writer: &mut impl Write) -> Result<()> {
+        let mut extra = Map::new();
+        let mut num_steps = 0;
+        write!(writer, "{{\"steps\": [")?;
+        for item in reader {
+            match item? {
+                VasprunItem::Step(step) => {
+                    if num_steps > 0 {
+                        write!(writer, ",")?;
+                    }
+                    serde_json::to_writer(&mut *writer, &self.step_to_json(&step))?;
+                    num_steps += 1;
+                }
+                VasprunItem::Incar(incar) if self.has("incar") => {
+                    extra.insert(String::from("incar"), json!(incar));
+                }
+                VasprunItem::Kpoints(kpoints) if self.has("kpoints") => {
+                    extra.insert(String::from("kpoints"), json!(kpoints));
+                }
+                VasprunItem::Eigenvalues(eigenvalues) if self.has("eigenvalues") => {
+                    extra.insert(String::from("eigenvalues"), json!(eigenvalues));
+                }
+                VasprunItem::Dos(dos) if self.has("dos") => {
+                    extra.insert(String::from("dos"), json!(dos));
+                }
+                _ => (),
+            }
+        }
+        write!(writer, "]")?;
+        for (key, value) in extra {
+            write!(writer, ", {}: ", json!(key))?;
+            serde_json::to_writer(&mut *writer, &value)?;
+        }
+        writeln!(writer, "}}")?;
+        Ok(())
+    }
+
+    fn step_to_json(&self, step: &VasprunStep) -> Value {
+        let mut map = Map::new();
+        map.insert(String::from("step"), json!(step.index));
+        if self.has("energy") {
+            map.insert(String::from("energy"), json!(step.energy_sigma0));
+            map.insert(String::from("free_energy"), json!(step.free_energy));
+        }
+        if self.has("forces") {
+            map.insert(String::from("forces"), json!(step.forces));
+        }
+        if self.has("stress") {
+            map.insert(String::from("stress"), json!(step.stress));
+        }
+        if self.has("structure") {
+            let structure = &step.structure;
+            map.insert(String::from("lattice"), json!(scaled_lattice(structure)));
+            map.insert(String::from("species"), json!(atom_species(structure)));
+            map.insert(String::from("positions"), json!(cartesian_coords(structure)));
+        }
+        Value::Object(map)
+    }
+
+    /// Writes one row of scalar quantities per ionic step.
+    fn write_csv<R: std::io::BufRead>(&self, reader: VasprunReader<R>, writer: &mut impl Write) -> Result<()> {
+        let mut header = vec!["step"];
+        if self.has("energy") {
+            header.extend(["energy", "free_energy"]);
+        }
+        if self.has("forces") {
+            header.push("max_force");
+        }
+        if self.has("stress") {
+            header.extend([
+                "stress_xx",
+                "stress_yy",
+                "stress_zz",
+                "stress_xy",
+                "stress_yz",
+                "stress_zx",
+            ]);
+        }
+        if self.has("structure") {
+            header.push("volume");
+        }
+        writeln!(writer, "{}", header.join(","))?;
+
+        let format_option = |value: Option<f64>| value.map_or(String::new(), |v| format!("{:.8}", v));
+        for item in reader {
+            let step = match item? {
+                VasprunItem::Step(step) => step,
+                _ => continue,
+            };
+            let mut row = vec![step.index.to_string()];
+            if self.has("energy") {
+                row.push(format_option(step.energy_sigma0));
+                row.push(format_option(step.free_energy));
+            }
+            if self.has("forces") {
+                let max_force = step
+                    .forces
+                    .iter()
+                    .map(|f| (f[0] * f[0] + f[1] * f[1] + f[2] * f[2]).sqrt())
+                    .fold(None, |max: Option<f64>, f| Some(max.map_or(f, |m| m.max(f))));
+                row.push(format_option(max_force));
+            }
+            if self.has("stress") {
+                match step.stress {
+                    Some(s) => {
+                        for (i, j) in [(0, 0), (1, 1), (2, 2), (0, 1), (1, 2), (2, 0)] {
+                            row.push(format!("{:.8}", s[i][j]));
+                        }
+                    }
+                    None => row.extend(vec![String::new(); 6]),
+                }
+            }
+            if self.has("structure") {
+                row.push(format!("{:.8}", volume(&scaled_lattice(&step.structure))));
+            }
+            writeln!(writer, "{}", row.join(","))?;
+        }
+        Ok(())
+    }
+}
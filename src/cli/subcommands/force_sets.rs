@@ -0,0 +1,59 @@
+use crate::config_parser::JobConfig;
+use crate::output_parser::{VasprunItem, VasprunReader};
+use clap::Parser;
+use eyre::Result;
+use std::fmt::Write;
+use std::path::Path;
+use vasp_files::poscar::Poscar;
+
+#[derive(Parser)]
+pub struct ForceSets {
+    /// The directory of the finished phonon job.
+    #[clap(default_value = ".")]
+    pub dir: String,
+}
+
+impl ForceSets {
+    /// Collects forces of the displaced supercells into FORCE_SETS of phonopy.
+    /// Displacements are generated again from Vasp.toml and POSCAR of the phonon job.
+    pub fn run(&self) -> Result<()> {
+        let job_config = JobConfig::from_dir(&self.dir)?;
+        if job_config.incar_base() != "phonon" {
+            return Err(eyre::eyre!("{} is not a phonon job", self.dir));
+        }
+        let unitcell = Poscar::from_file(job_config.structure_file())?;
+        let displacements = job_config.phonon_displacements(&unitcell)?;
+        let num_atoms = unitcell.frac_coords.len() * job_config.phonon()?.supercell.iter().product::<usize>();
+
+        let mut force_sets = format!("{}\n{}\n", num_atoms, displacements.len());
+        for (n, displacement) in displacements.iter().enumerate() {
+            let vasprun = Path::new(&self.dir)
+                .join(format!("disp-{:03}", n + 1))
+                .join("vasprun.xml");
+            let mut forces = None;
+            for item in VasprunReader::from_file(&vasprun)? {
+                if let VasprunItem::Step(step) = item? {
+                    forces = Some(step.forces);
+                }
+            }
+            let forces = forces
+                .filter(|forces| forces.len() == num_atoms)
+                .ok_or_else(|| eyre::eyre!("Forces of {} atoms not found in {}", num_atoms, vasprun.display()))?;
+            let [dx, dy, dz] = displacement.vector;
+            write!(
+                force_sets,
+                "\n{}\n{:20.16} {:20.16} {:20.16}\n",
+                displacement.atom + 1,
+                dx,
+                dy,
+                dz
+            )?;
+            for [fx, fy, fz] in forces {
+                writeln!(force_sets, "{:15.10} {:15.10} {:15.10}", fx, fy, fz)?;
+            }
+        }
+        std::fs::write(Path::new(&self.dir).join("FORCE_SETS"), force_sets)?;
+        println!("Writing FORCE_SETS with {} displacements", displacements.len());
+        Ok(())
+    }
+}
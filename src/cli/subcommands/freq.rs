@@ -0,0 +1,25 @@
+use crate::output_parser::Vibrations;
+use clap::Parser;
+use eyre::Result;
+
+#[derive(Parser)]
+pub struct Freq {
+    /// The directory of the finished frequency job.
+    #[clap(default_value = ".")]
+    pub dir: String,
+    /// Print results as JSON.
+    #[clap(long, action)]
+    pub json: bool,
+}
+
+impl Freq {
+    pub fn run(&self) -> Result<()> {
+        let vibrations = Vibrations::from_dir(&self.dir)?;
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&vibrations)?);
+        } else {
+            print!("{}", vibrations);
+        }
+        Ok(())
+    }
+}
@@ -35,7 +35,11 @@ impl InitJob {
             let vasp_bin = &format!("\"{}\"", &check_vasp_bin(&self.vasp_version, &self.vasp_bin)?);
             context.insert("vasp_bin", vasp_bin);
 
-            let incar_base = &format!("\"{}\"", &check_task(&self.task)?);
+            check_task(&self.task)?;
+            let incar_base = &format!(
+                "\"{}\"",
+                &check_task_support(&self.task, &self.vasp_version, &self.vasp_bin)?
+            );
             context.insert("incar_base", incar_base);
 
             template::TEMPLATES.render("Vasp.toml", &context)?
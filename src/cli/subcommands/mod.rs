@@ -1,25 +1,409 @@
 pub use clap::Parser;
+pub mod bader;
+pub mod check;
+pub mod chgsum;
 pub mod continue_job;
+pub mod converge;
 pub mod create_job;
+pub mod db;
+pub mod doctor;
+pub mod dos;
+pub mod elastic;
+pub mod eos;
+pub mod export;
+pub mod force_sets;
+pub mod freq;
 pub mod init_job;
 pub mod show_incar;
 pub mod submit_job;
+pub mod summary;
+pub mod sweep;
+pub mod verify;
 
+pub use bader::*;
+pub use check::*;
+pub use chgsum::*;
 pub use continue_job::*;
+pub use converge::*;
 pub use create_job::*;
+pub use db::*;
+pub use doctor::*;
+pub use dos::*;
+pub use elastic::*;
+pub use eos::*;
+pub use export::*;
+pub use force_sets::*;
+pub use freq::*;
 pub use init_job::*;
 pub use show_incar::*;
 pub use submit_job::*;
+pub use summary::*;
+pub use sweep::*;
+pub use verify::*;
 
+use crate::config_parser::JobConfig;
+use crate::job_db::JobDatabase;
 use eyre::Result;
+use std::path::Path;
+
+/// List of job directories read by `job_array.sh`.
+pub const JOB_DIR_LIST: &str = "job_dirs.txt";
 
 /// Submits slurm job in current directory.
-pub fn submit_slurm(job_name: &str) -> Result<()> {
+pub fn submit_slurm(job_name: &str) -> Result<String> {
+    submit_slurm_script(job_name, "job_script.sh")
+}
+
+/// Submits `script` in current directory to slurm, returning the job id.
+/// Returns an error with the message of sbatch if the job is rejected.
+pub fn submit_slurm_script(job_name: &str, script: &str) -> Result<String> {
     let output = std::process::Command::new("sbatch")
         .arg("-J")
-        .arg(&job_name)
-        .arg("job_script.sh")
+        .arg(job_name)
+        .arg(script)
         .output()?;
-    println!("{}", String::from_utf8_lossy(&output.stdout));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    println!("{}", stdout);
+    parse_sbatch_output(
+        output.status.success(),
+        &stdout,
+        &String::from_utf8_lossy(&output.stderr),
+    )
+}
+
+/// Job id from the output of sbatch, which prints "Submitted batch job <id>".
+fn parse_sbatch_output(success: bool, stdout: &str, stderr: &str) -> Result<String> {
+    let job_id = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Submitted batch job"))
+        .and_then(|rest| rest.split_whitespace().next());
+    match (success, job_id) {
+        (true, Some(job_id)) => Ok(job_id.to_string()),
+        _ => Err(eyre::eyre!("sbatch failed: {}", stderr.trim())),
+    }
+}
+
+/// Whether sbatch rejected the job for the limit of submitted jobs of the user or account.
+fn is_submit_limit(error: &eyre::Report) -> bool {
+    let message = error.to_string();
+    message.contains("MaxSubmitJob") || message.contains("job submit limit")
+}
+
+/// Submits slurm job in `dir`, returning to the current directory afterwards.
+pub fn submit_slurm_in(dir: &str, job_name: &str, script: &str) -> Result<String> {
+    let current_dir = std::env::current_dir()?;
+    std::env::set_current_dir(dir)?;
+    let result = submit_slurm_script(job_name, script);
+    std::env::set_current_dir(current_dir)?;
+    result
+}
+
+/// Submits slurm job in `dir`. If `max_queued` is given, waits until fewer jobs are queued, and
+/// waits again if slurm rejects the job for the limit of submitted jobs, checking every `interval` seconds.
+pub fn submit_slurm_queued(
+    dir: &str,
+    job_name: &str,
+    script: &str,
+    max_queued: Option<usize>,
+    interval: u64,
+) -> Result<String> {
+    let max_queued = match max_queued {
+        Some(max_queued) => max_queued,
+        None => return submit_slurm_in(dir, job_name, script),
+    };
+    loop {
+        wait_for_queue(max_queued, interval)?;
+        match submit_slurm_in(dir, job_name, script) {
+            Err(e) if is_submit_limit(&e) => {
+                println!("Submission limit reached. Retrying in {} s...", interval);
+                std::thread::sleep(std::time::Duration::from_secs(interval));
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Records creation of the job in `dir` in the job database.
+/// Failing to update the database is reported but does not fail the job.
+pub fn record_job_created(dir: &str) {
+    if let Err(e) = JobDatabase::update(|db| db.record_created(dir)) {
+        eprintln!("Warning: could not update job database: {}", e);
+    }
+}
+
+/// Records submission of the job in `dir` in the job database.
+pub fn record_job_submitted(dir: &str, job_id: Option<&str>) {
+    if let Err(e) = JobDatabase::update(|db| db.record_submitted(dir, job_id)) {
+        eprintln!("Warning: could not update job database: {}", e);
+    }
+}
+
+/// Checks that `dirs`, relative to `root`, can run as tasks of a job array of `config`.
+/// `job_array.sh` renders the pre-step and Bader analysis of `config` once for all tasks,
+/// so jobs running other steps are rejected.
+pub fn check_array_dirs(config: &JobConfig, root: &str, dirs: &[String]) -> Result<()> {
+    if dirs.is_empty() {
+        return Err(eyre::eyre!("No job directory to run as a job array"));
+    }
+    let steps = |config: &JobConfig| -> Result<(Option<String>, Option<String>)> {
+        Ok((config.pre_step().map(|p| p.name), config.bader_executable()?))
+    };
+    let array_steps = steps(config)?;
+    for dir in dirs {
+        let job_dir = Path::new(root).join(dir);
+        // Tasks with sub-jobs run VASP only in their sub-directories.
+        if !job_dir.join("INCAR").exists() || !job_dir.join("Vasp.toml").exists() {
+            continue;
+        }
+        if steps(&JobConfig::from_dir(job_dir.to_str().unwrap())?)? != array_steps {
+            return Err(eyre::eyre!(
+                "Job in {} runs a different pre-step or Bader analysis from the job array. Submit it separately.",
+                job_dir.display()
+            ));
+        }
+    }
     Ok(())
 }
+
+/// Writes `job_array.sh` and the list of job directories (relative to `root`) in `root`.
+pub fn write_job_array(config: &JobConfig, root: &str, dirs: &[String], throttle: Option<usize>) -> Result<()> {
+    check_array_dirs(config, root, dirs)?;
+    std::fs::write(Path::new(root).join(JOB_DIR_LIST), dirs.join("\n") + "\n")?;
+    config
+        .with_job_dir(root)
+        .write_array_script(JOB_DIR_LIST, dirs.len(), throttle)
+}
+
+/// Submits jobs in `dirs`, relative to `root`, one by one.
+/// If `array` is set, submits `job_array.sh` in `root` instead.
+pub fn submit_job_dirs(config: &JobConfig, root: &str, dirs: &[String], array: bool) -> Result<()> {
+    if array {
+        let array_id = submit_slurm_in(root, config.job_name(), "job_array.sh")?;
+        record_array_submitted(root, dirs, &array_id);
+        return Ok(());
+    }
+    for dir in dirs {
+        let job_dir = Path::new(root).join(dir);
+        let job_dir = job_dir.to_str().unwrap();
+        let job_config = JobConfig::from_dir(job_dir)?;
+        let job_id = submit_slurm_in(job_dir, job_config.job_name(), "job_script.sh")?;
+        record_job_submitted(job_dir, Some(&job_id));
+    }
+    Ok(())
+}
+
+/// Records submission of the jobs in `dirs`, relative to `root`, as tasks of the job array `array_id`.
+pub fn record_array_submitted(root: &str, dirs: &[String], array_id: &str) {
+    // Array task ids follow the order of the job directory list.
+    for (i, dir) in dirs.iter().enumerate() {
+        let job_id = format!("{}_{}", array_id, i);
+        record_job_submitted(Path::new(root).join(dir).to_str().unwrap(), Some(&job_id));
+    }
+}
+
+/// Expands directories and glob patterns to job directories.
+/// If `recursive` is set, directories containing Vasp.toml under them are collected instead.
+pub fn resolve_job_dirs(patterns: &[String], recursive: bool) -> Result<Vec<String>> {
+    let mut dirs: Vec<String> = vec![];
+    for pattern in patterns {
+        let mut matched: Vec<String> = glob::glob(pattern)?
+            .filter_map(|path| path.ok())
+            .filter(|path| path.is_dir())
+            .map(|path| path.to_str().unwrap().to_string())
+            .collect();
+        if matched.is_empty() {
+            return Err(eyre::eyre!("No directory matches {}", pattern));
+        }
+        matched.sort();
+        for dir in matched {
+            match recursive {
+                true => find_job_dirs(Path::new(&dir), &mut dirs)?,
+                false => dirs.push(dir),
+            }
+        }
+    }
+    let mut unique = vec![];
+    for dir in dirs {
+        if !unique.contains(&dir) {
+            unique.push(dir);
+        }
+    }
+    Ok(unique)
+}
+
+/// Collects directories containing Vasp.toml under `dir`, in sorted order.
+fn find_job_dirs(dir: &Path, dirs: &mut Vec<String>) -> Result<()> {
+    if dir.join("Vasp.toml").exists() {
+        dirs.push(dir.to_str().unwrap().to_string());
+    }
+    let mut entries: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir() && !path.is_symlink())
+        .collect();
+    entries.sort();
+    for entry in entries {
+        find_job_dirs(&entry, dirs)?;
+    }
+    Ok(())
+}
+
+/// Runs `job` for each directory, reporting success or failure of each.
+/// If `continue_on_error` is set, remaining directories are processed after a failure,
+/// and an error is returned at the end.
+pub fn run_batch<F>(dirs: &[String], continue_on_error: bool, mut job: F) -> Result<()>
+where
+    F: FnMut(&str) -> Result<()>,
+{
+    if dirs.len() == 1 {
+        return job(&dirs[0]);
+    }
+    let mut failed = vec![];
+    for dir in dirs {
+        match job(dir) {
+            Ok(()) => println!("[OK] {}", dir),
+            Err(e) if continue_on_error => {
+                eprintln!("[FAILED] {}: {}", dir, e);
+                failed.push(dir.as_str());
+            }
+            Err(e) => {
+                eprintln!("[FAILED] {}: {}", dir, e);
+                return Err(e);
+            }
+        }
+    }
+    println!("{} succeeded, {} failed", dirs.len() - failed.len(), failed.len());
+    if !failed.is_empty() {
+        return Err(eyre::eyre!("Failed in {}", failed.join(", ")));
+    }
+    Ok(())
+}
+
+/// Number of jobs of the current user in the slurm queue.
+pub fn num_queued_jobs() -> Result<usize> {
+    let user = std::env::var("USER").map_err(|_| eyre::eyre!("USER is not set"))?;
+    let output = std::process::Command::new("squeue")
+        .args(["-h", "-u", &user])
+        .output()?;
+    if !output.status.success() {
+        return Err(eyre::eyre!(
+            "squeue failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().count())
+}
+
+/// Waits until the number of queued jobs of the current user is less than `max_queued`,
+/// checking the queue every `interval` seconds.
+pub fn wait_for_queue(max_queued: usize, interval: u64) -> Result<()> {
+    loop {
+        let num_queued = num_queued_jobs()?;
+        if num_queued < max_queued {
+            return Ok(());
+        }
+        println!(
+            "{} jobs in queue (max {}). Retrying in {} s...",
+            num_queued, max_queued, interval
+        );
+        std::thread::sleep(std::time::Duration::from_secs(interval));
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_parse_sbatch_output() {
+        use super::*;
+        assert_eq!(
+            parse_sbatch_output(true, "Submitted batch job 12345\n", "").unwrap(),
+            "12345"
+        );
+        let rejected = parse_sbatch_output(
+            false,
+            "",
+            "sbatch: error: QOSMaxSubmitJobPerUserLimit\nsbatch: error: Batch job submission failed: \
+             Job violates accounting/QOS policy (job submit limit, user's size and/or time limits)\n",
+        )
+        .unwrap_err();
+        assert!(rejected.to_string().contains("QOSMaxSubmitJobPerUserLimit"));
+        assert!(is_submit_limit(&rejected));
+        let invalid_partition = parse_sbatch_output(
+            false,
+            "",
+            "sbatch: error: invalid partition specified: g9\nsbatch: error: Batch job submission failed: \
+             Invalid partition name specified\n",
+        )
+        .unwrap_err();
+        assert!(invalid_partition.to_string().contains("invalid partition"));
+        assert!(!is_submit_limit(&invalid_partition));
+        assert!(parse_sbatch_output(true, "", "").is_err());
+    }
+
+    #[test]
+    fn test_check_array_dirs() {
+        use super::*;
+        let root = std::env::temp_dir().join(format!("vasp_manager_test_array_{}", std::process::id()));
+        let config_with_base = |base: &str| {
+            format!(
+                "[slurm]\njob_name = \"test\"\n\n[vasp]\nversion = \"6.3.1\"\nbin = \"vasp_std\"\n\n\
+                 [vasp.incar]\nbase = \"{}\"\n",
+                base
+            )
+        };
+        for (dir, base) in [
+            ("hse_a", "hse"),
+            ("hse_b", "hse"),
+            ("relax", "relax"),
+            ("phonon", "relax"),
+        ] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            std::fs::write(root.join(dir).join("Vasp.toml"), config_with_base(base)).unwrap();
+        }
+        for dir in ["hse_a", "hse_b", "relax"] {
+            std::fs::write(root.join(dir).join("INCAR"), "").unwrap();
+        }
+        let config = JobConfig {
+            toml_contents: toml::from_str(&config_with_base("hse")).unwrap(),
+            job_dir: String::from("."),
+        };
+        let root_str = root.to_str().unwrap();
+        let dirs = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+
+        assert!(check_array_dirs(&config, root_str, &[]).is_err());
+        assert!(check_array_dirs(&config, root_str, &dirs(&["hse_a", "hse_b"])).is_ok());
+        // The PBE pre-step of hse is not run by an array of relax jobs and vice versa.
+        let mixed = check_array_dirs(&config, root_str, &dirs(&["hse_a", "relax"])).unwrap_err();
+        assert!(mixed.to_string().contains("relax"));
+        // Tasks with sub-jobs run VASP only.
+        assert!(check_array_dirs(&config, root_str, &dirs(&["hse_a", "phonon"])).is_ok());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_run_batch() {
+        use super::*;
+        let dirs: Vec<String> = ["a", "b", "c"].iter().map(|dir| dir.to_string()).collect();
+        let fail_b = |dir: &str| match dir {
+            "b" => Err(eyre::eyre!("failed")),
+            _ => Ok(()),
+        };
+
+        let mut visited = vec![];
+        let result = run_batch(&dirs, false, |dir| {
+            visited.push(dir.to_string());
+            fail_b(dir)
+        });
+        assert!(result.is_err());
+        assert_eq!(visited, ["a", "b"]);
+
+        let mut visited = vec![];
+        let result = run_batch(&dirs, true, |dir| {
+            visited.push(dir.to_string());
+            fail_b(dir)
+        });
+        assert_eq!(result.unwrap_err().to_string(), "Failed in b");
+        assert_eq!(visited, ["a", "b", "c"]);
+
+        assert!(run_batch(&dirs, false, |_| Ok(())).is_ok());
+    }
+}
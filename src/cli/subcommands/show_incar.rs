@@ -1,33 +1,86 @@
+use super::JOB_DIR_LIST;
+use crate::check_util::check_task;
+use crate::config_parser::{format_value, JobConfig};
 use crate::template;
 use clap::Parser;
 use eyre::Result;
 use inquire::error::InquireError;
 use inquire::Select;
+use serde::Serialize;
 use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::path::Path;
+use tera::Context;
 
 #[derive(Parser)]
-pub struct ShowIncar;
+pub struct ShowIncar {
+    /// Template to print: a task (e.g. relax), KPOINTS, job_script.sh, job_array.sh or Vasp.toml.
+    /// Chosen interactively if not given in a terminal, listed otherwise.
+    pub name: Option<String>,
+    /// List available templates with their descriptions.
+    #[clap(short, long, action, conflicts_with = "name")]
+    pub list: bool,
+    /// Print the list as JSON.
+    #[clap(long, action, requires = "list")]
+    pub json: bool,
+    /// Print the template source instead of rendering it with Vasp.toml in current directory.
+    #[clap(long, action)]
+    pub raw: bool,
+}
+
+/// Template in the list of `show_incar --list`.
+#[derive(Debug, Clone, Serialize)]
+struct TemplateInfo {
+    /// Task for INCAR templates, file name for the others.
+    name: String,
+    /// Name of the template, e.g. "INCAR_relax".
+    template: String,
+    description: String,
+}
 
 impl ShowIncar {
     pub fn run(&self) -> Result<()> {
-        let mut all_incar_templates = vec![];
-        let mut all_incar_descriptions = vec![];
-        for key in template::INCAR_TEMPLATES.keys() {
-            let description = format!("{}  [{}]", key, template::INCAR_TEMPLATES[key].1);
-            all_incar_templates.push(key.to_string());
-            all_incar_descriptions.push(description.to_string());
+        if self.list {
+            return self.print_list();
+        }
+        match &self.name {
+            Some(name) => print!("{}", self.contents(name)?),
+            None if std::io::stdin().is_terminal() && std::io::stdout().is_terminal() => self.select()?,
+            None => self.print_list()?,
+        }
+        Ok(())
+    }
+
+    fn print_list(&self) -> Result<()> {
+        let templates = template_list();
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&templates)?);
+            return Ok(());
+        }
+        let width = templates.iter().map(|t| t.name.len()).max().unwrap_or(0);
+        for t in &templates {
+            println!("{:<width$}  {}", t.name, t.description, width = width);
         }
+        Ok(())
+    }
+
+    /// Chooses a template interactively and prints it.
+    fn select(&self) -> Result<()> {
+        let templates = template_list();
+        let descriptions: Vec<String> = templates
+            .iter()
+            .map(|t| format!("{}  [{}]", t.template, t.description))
+            .collect();
         let mapping: HashMap<&String, &String> =
-            HashMap::from_iter(all_incar_descriptions.iter().zip(all_incar_templates.iter()));
+            HashMap::from_iter(descriptions.iter().zip(templates.iter().map(|t| &t.name)));
 
-        let selected_incar: Result<String, InquireError> =
-            Select::new("Choose INCAR template to see.", all_incar_descriptions.clone()).prompt();
-        match selected_incar {
+        let selected: Result<String, InquireError> =
+            Select::new("Choose template to see.", descriptions.clone()).prompt();
+        match selected {
             Ok(desc) => {
-                let key = mapping[&desc];
-                println!("========= {} =========", key);
-                let (template, _) = template::INCAR_TEMPLATES[key];
-                println!("{}", template);
+                let name = mapping[&desc];
+                println!("========= {} =========", name);
+                print!("{}", self.contents(name)?);
             }
             Err(e) => {
                 println!("{}", e);
@@ -35,4 +88,105 @@ impl ShowIncar {
         }
         Ok(())
     }
+
+    /// Template source, or the file rendered from it with Vasp.toml in current directory.
+    fn contents(&self, name: &str) -> Result<String> {
+        let template_name = match name {
+            "KPOINTS" | "Vasp.toml" | "job_script.sh" | "job_array.sh" => name.to_string(),
+            "job_script" | "job_array" => format!("{}.sh", name),
+            task => {
+                let task = check_task(task.strip_prefix("INCAR_").unwrap_or(task)).map_err(|e| {
+                    eyre::eyre!(
+                        "{}\nOther templates: KPOINTS, job_script.sh, job_array.sh, Vasp.toml",
+                        e
+                    )
+                })?;
+                format!("INCAR_{}", task)
+            }
+        };
+        if self.raw {
+            return Ok(template::template_source(&template_name).unwrap().to_string());
+        }
+
+        let job_config = match Path::new("Vasp.toml").exists() {
+            true => Some(JobConfig::from_dir(".")?),
+            false => None,
+        };
+        if template_name == "Vasp.toml" {
+            return render_vasp_toml(job_config.as_ref());
+        }
+        let mut job_config = match job_config {
+            Some(job_config) => job_config,
+            None if template_name.starts_with("INCAR_") => {
+                // Without Vasp.toml, INCAR templates are rendered without additional tags.
+                eprintln!("Vasp.toml not found in current directory. Showing the template without additional tags.");
+                let mut context = Context::new();
+                context.insert("incar_tags", &Vec::<template::IncarTag>::new());
+                context.insert("images", "<images>");
+                return Ok(template::TEMPLATES.render(&template_name, &context)?);
+            }
+            None => {
+                return Err(eyre::eyre!(
+                    "Vasp.toml not found in current directory. Use --raw to show the template source."
+                ))
+            }
+        };
+        match template_name.as_str() {
+            "KPOINTS" => job_config.kpoints_contents(),
+            "job_script.sh" => job_config.job_script_contents(),
+            "job_array.sh" => {
+                let num_jobs = std::fs::read_to_string(JOB_DIR_LIST)
+                    .map(|dirs| dirs.lines().count())
+                    .unwrap_or(1);
+                job_config.array_script_contents(JOB_DIR_LIST, num_jobs, None)
+            }
+            incar => {
+                job_config.set_incar_base(incar.strip_prefix("INCAR_").unwrap());
+                job_config.incar_contents()
+            }
+        }
+    }
+}
+
+/// INCAR templates by task, followed by the other templates.
+fn template_list() -> Vec<TemplateInfo> {
+    let mut templates: Vec<TemplateInfo> = template::INCAR_TEMPLATES
+        .entries()
+        .map(|(name, (_, description))| TemplateInfo {
+            name: name.strip_prefix("INCAR_").unwrap().to_string(),
+            template: name.to_string(),
+            description: description.to_string(),
+        })
+        .collect();
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    templates.extend(
+        template::OTHER_TEMPLATES
+            .iter()
+            .map(|(name, _, description)| TemplateInfo {
+                name: name.to_string(),
+                template: name.to_string(),
+                description: description.to_string(),
+            }),
+    );
+    templates
+}
+
+/// Renders the Vasp.toml template as `init` does, with VASP and task of Vasp.toml in current directory
+/// if it exists, or the defaults of `init`.
+fn render_vasp_toml(job_config: Option<&JobConfig>) -> Result<String> {
+    let value = |keys: &[&str], default: &str| -> String {
+        let mut value = job_config.map(|c| &c.toml_contents);
+        for key in keys {
+            value = value.and_then(|v| v.get(key));
+        }
+        value.map(format_value).unwrap_or_else(|| default.to_string())
+    };
+    let mut context = Context::new();
+    context.insert("vasp_version", &format!("\"{}\"", value(&["vasp", "version"], "6.3.1")));
+    context.insert("vasp_bin", &format!("\"{}\"", value(&["vasp", "bin"], "vasp_std")));
+    context.insert(
+        "incar_base",
+        &format!("\"{}\"", value(&["vasp", "incar", "base"], "relax")),
+    );
+    Ok(template::TEMPLATES.render("Vasp.toml", &context)?)
 }
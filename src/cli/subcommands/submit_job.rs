@@ -1,26 +1,66 @@
-use super::submit_slurm;
+use super::{
+    check_array_dirs, record_array_submitted, record_job_submitted, resolve_job_dirs, run_batch, submit_slurm_queued,
+    write_job_array,
+};
 use crate::config_parser;
 use clap::Parser;
 use eyre::Result;
 use inquire::Confirm;
+use std::path::Path;
 
 #[derive(Parser)]
 pub struct SubmitJob {
+    /// The directories (or glob patterns) of the jobs to submit.
+    #[clap(short, long = "dir", multiple_values = true, default_value = ".")]
+    pub dirs: Vec<String>,
+    /// Submit jobs in all directories containing Vasp.toml under the given directories.
+    #[clap(short = 'R', long, action)]
+    pub recursive: bool,
+    /// Keep going with the remaining directories if a submission fails.
+    #[clap(long, action)]
+    pub continue_on_error: bool,
+    /// Maximum number of jobs in the slurm queue. Waits before submitting if reached,
+    /// and after a submission rejected for the limit of submitted jobs.
+    #[clap(long)]
+    pub max_queued: Option<usize>,
+    /// Interval (seconds) to check the slurm queue while waiting.
+    #[clap(long, default_value = "60")]
+    pub wait_interval: u64,
     /// If set, command does not inquire the user for reviewing the input.
     #[clap(short = 'r', long, action)]
     pub no_review: bool,
+    /// Submit jobs in the given directories (or glob patterns) as a single slurm job array.
+    /// Resources are read from Vasp.toml in current directory.
+    #[clap(long, multiple_values = true, value_name = "DIRS")]
+    pub array: Vec<String>,
+    /// Maximum number of array tasks running at the same time (`%N` of `--array`).
+    #[clap(long, requires = "array")]
+    pub throttle: Option<usize>,
 }
 
 impl SubmitJob {
     pub fn run(&self) -> Result<()> {
-        let job_config = config_parser::JobConfig::from_dir(".")?;
-        let job_name = job_config.toml_contents["slurm"]["job_name"].as_str().unwrap();
+        let dirs = match self.array.is_empty() {
+            true => resolve_job_dirs(&self.dirs, self.recursive)?,
+            false => self.array_dirs()?,
+        };
 
         if !self.no_review {
-            let config_file = std::fs::read_to_string("Vasp.toml")?;
-            println!("======= Vasp.toml =======");
-            println!("{}", config_file);
-            println!("=========================");
+            match (dirs.len(), self.array.is_empty()) {
+                (1, true) => {
+                    let config_file = std::fs::read_to_string(Path::new(&dirs[0]).join("Vasp.toml"))?;
+                    println!("======= Vasp.toml =======");
+                    println!("{}", config_file);
+                    println!("=========================");
+                }
+                _ => {
+                    println!("======= Job directories =======");
+                    for dir in &dirs {
+                        println!("{}", dir);
+                    }
+                    println!("===============================");
+                }
+            }
             let ans = Confirm::new("Are you sure you want to submit this job?")
                 .with_default(false)
                 .with_help_message("Please review your settings.")
@@ -28,7 +68,7 @@ impl SubmitJob {
 
             match ans {
                 Ok(true) => {
-                    submit_slurm(job_name)?;
+                    self.submit(&dirs)?;
                     std::process::exit(0);
                 }
                 Ok(false) => {
@@ -41,7 +81,63 @@ impl SubmitJob {
             }
         }
 
-        submit_slurm(job_name)?;
+        self.submit(&dirs)?;
         Ok(())
     }
+
+    /// Returns the directories of `--array`, checking that their jobs are created.
+    fn array_dirs(&self) -> Result<Vec<String>> {
+        // Resources of the array are read from Vasp.toml in current directory.
+        let job_config = config_parser::JobConfig::from_dir(".")?;
+        let dirs: Vec<String> = resolve_job_dirs(&self.array, self.recursive)?
+            .into_iter()
+            .filter(|dir| Path::new(dir) != Path::new("."))
+            .collect();
+        for dir in &dirs {
+            // Tasks with sub-jobs have INCAR in the sub-directories.
+            let has_incar = |dir: &Path| dir.join("INCAR").exists();
+            let sub_dir_has_incar = || -> Result<bool> {
+                Ok(std::fs::read_dir(dir)?
+                    .filter_map(|entry| entry.ok())
+                    .any(|entry| has_incar(&entry.path())))
+            };
+            if !has_incar(Path::new(dir)) && !sub_dir_has_incar()? {
+                return Err(eyre::eyre!("INCAR not found in {}. Run create_job first.", dir));
+            }
+        }
+        check_array_dirs(&job_config, ".", &dirs)?;
+        if self.throttle == Some(0) {
+            return Err(eyre::eyre!("--throttle should be larger than 0"));
+        }
+        Ok(dirs)
+    }
+
+    fn submit(&self, dirs: &[String]) -> Result<()> {
+        if !self.array.is_empty() {
+            let job_config = config_parser::JobConfig::from_dir(".")?;
+            write_job_array(&job_config, ".", dirs, self.throttle)?;
+            println!("Writing job_array.sh for {} directories", dirs.len());
+            let array_id = submit_slurm_queued(
+                ".",
+                job_config.job_name(),
+                "job_array.sh",
+                self.max_queued,
+                self.wait_interval,
+            )?;
+            record_array_submitted(".", dirs, &array_id);
+            return Ok(());
+        }
+        run_batch(dirs, self.continue_on_error, |dir| {
+            let job_config = config_parser::JobConfig::from_dir(dir)?;
+            let job_id = submit_slurm_queued(
+                dir,
+                job_config.job_name(),
+                "job_script.sh",
+                self.max_queued,
+                self.wait_interval,
+            )?;
+            record_job_submitted(dir, Some(&job_id));
+            Ok(())
+        })
+    }
 }
@@ -0,0 +1,25 @@
+use crate::output_parser::RunSummary;
+use clap::Parser;
+use eyre::Result;
+
+#[derive(Parser)]
+pub struct Summary {
+    /// The directory of the finished (or running) job.
+    #[clap(default_value = ".")]
+    pub dir: String,
+    /// Print summary as JSON.
+    #[clap(long, action)]
+    pub json: bool,
+}
+
+impl Summary {
+    pub fn run(&self) -> Result<()> {
+        let summary = RunSummary::from_dir(&self.dir)?;
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&summary)?);
+        } else {
+            print!("{}", summary);
+        }
+        Ok(())
+    }
+}
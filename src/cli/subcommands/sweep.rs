@@ -0,0 +1,49 @@
+use super::{record_job_created, submit_job_dirs, write_job_array};
+use crate::config_parser::JobConfig;
+use clap::Parser;
+use eyre::Result;
+use std::path::Path;
+
+/// Name of the manifest file mapping job directories to values of sweep variables.
+const MANIFEST: &str = "sweep.csv";
+
+#[derive(Parser)]
+pub struct Sweep {
+    /// Whether submit jobs to slurm or not.
+    #[clap(short, long, action)]
+    pub submit: bool,
+    /// Submit jobs as a single slurm job array.
+    #[clap(long, action)]
+    pub array: bool,
+}
+
+impl Sweep {
+    /// Creates a job for each combination of `[sweep]` in Vasp.toml.
+    pub fn run(&self) -> Result<()> {
+        let base_config = JobConfig::from_dir(".")?;
+        let sweep = base_config
+            .sweep()?
+            .ok_or_else(|| eyre::eyre!("[sweep] section not found in Vasp.toml"))?;
+        let combinations = sweep.combinations()?;
+
+        std::fs::create_dir_all(&sweep.dir)?;
+        let mut dirs = vec![];
+        for combination in &combinations {
+            let job_config = base_config.sweep_job(&sweep, combination);
+            job_config.write_config()?;
+            job_config.create_job()?;
+            record_job_created(&job_config.job_dir);
+            dirs.push(sweep.job_name(combination));
+        }
+        std::fs::write(Path::new(&sweep.dir).join(MANIFEST), sweep.manifest(&combinations))?;
+        println!("Created {} jobs in {}", dirs.len(), sweep.dir);
+
+        if self.array {
+            write_job_array(&base_config, &sweep.dir, &dirs, None)?;
+        }
+        if self.submit {
+            submit_job_dirs(&base_config, &sweep.dir, &dirs, self.array)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+use crate::config_parser::Manifest;
+use clap::Parser;
+use eyre::Result;
+
+#[derive(Parser)]
+pub struct Verify {
+    /// Directories of the jobs to verify.
+    #[clap(default_value = ".")]
+    pub dirs: Vec<String>,
+}
+
+impl Verify {
+    /// Checks the jobs against their `vasp_manager.lock`.
+    /// Exits with code 1 if any of them drifted.
+    pub fn run(&self) -> Result<()> {
+        let mut drifted = false;
+        for dir in &self.dirs {
+            let manifest = Manifest::from_dir(dir)?;
+            let drifts = manifest.verify(dir)?;
+            println!("========= {} =========", dir);
+            println!(
+                "Created {} by vasp_manager {}",
+                manifest.created, manifest.vasp_manager_version
+            );
+            if manifest.vasp_manager_version != env!("CARGO_PKG_VERSION") {
+                println!("Note: current vasp_manager is {}", env!("CARGO_PKG_VERSION"));
+            }
+            if drifts.is_empty() {
+                println!("OK: job matches the manifest");
+            } else {
+                for drift in &drifts {
+                    println!("[DRIFT] {}", drift);
+                }
+                drifted = true;
+            }
+        }
+        if drifted {
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,11 @@
+use super::gen_manifest::MANIFEST_FILE;
+use crate::check_util::check_task_support;
+use crate::structure_io::{read_structure, sort_species, StructureFormat};
 use eyre::Result;
 use std::fs::read_to_string;
 use std::path::Path;
 use toml::Value;
+use vasp_files::poscar::Poscar;
 
 pub fn format_value(value: &Value) -> String {
     let formatted = format!("{}", value);
@@ -24,7 +28,31 @@ impl JobConfig {
     pub fn from_dir(job_dir: &str) -> Result<JobConfig> {
         let config_file = Path::new(job_dir).join("Vasp.toml");
         let toml_str = read_to_string(config_file).map_err(|_| eyre::eyre!("Vasp.toml not found."))?;
-        let toml_contents = toml::from_str(&toml_str).map_err(|_| eyre::eyre!("Could not parse Vasp.toml."))?;
+        let mut toml_contents: Value =
+            toml::from_str(&toml_str).map_err(|_| eyre::eyre!("Could not parse Vasp.toml."))?;
+        if !toml_contents.get("vasp").is_some_and(|v| v.is_table()) {
+            return Err(eyre::eyre!("[vasp] not found in Vasp.toml."));
+        }
+        // Structure files are given relative to the job directory.
+        let resolve = |path: &mut Value| {
+            if let Value::String(path) = path {
+                *path = Path::new(job_dir).join(&path).to_str().unwrap().to_string();
+            }
+        };
+        if let Some(input) = toml_contents.get_mut("vasp").and_then(|v| v.get_mut("input")) {
+            resolve(input);
+        }
+        if let Some(neb) = toml_contents
+            .get_mut("vasp")
+            .and_then(|v| v.get_mut("neb"))
+            .and_then(|n| n.as_table_mut())
+        {
+            for key in ["initial", "final"] {
+                if let Some(path) = neb.get_mut(key) {
+                    resolve(path);
+                }
+            }
+        }
         Ok(JobConfig {
             toml_contents,
             job_dir: job_dir.into(),
@@ -33,23 +61,44 @@ impl JobConfig {
 
     /// Writes input files needed for VASP and SLURM.
     pub fn create_job(&self) -> Result<()> {
+        let vasp_config = &self.toml_contents["vasp"];
+        check_task_support(
+            &self.incar_base(),
+            vasp_config["version"].as_str().unwrap(),
+            vasp_config["bin"].as_str().unwrap(),
+        )
+        .map_err(|e| eyre::eyre!(e))?;
+        match self.incar_base().as_str() {
+            "neb" => self.create_neb_job()?,
+            "phonon" => self.create_phonon_job()?,
+            "eos" | "elastic" => self.create_strain_job()?,
+            _ => self.create_single_job()?,
+        }
+        println!("Writing {}", MANIFEST_FILE);
+        self.write_manifest()
+    }
+
+    /// Writes input files of a job running VASP once in the job directory.
+    fn create_single_job(&self) -> Result<()> {
         println!("Creating job in {}", self.job_dir);
+        std::fs::create_dir_all(&self.job_dir)?;
+        if self.write_poscar()? {
+            println!("Writing POSCAR");
+        }
+        self.write_constraints()?;
         println!("Writing POTCAR");
         self.write_potcar()?;
         println!("Writing KPOINTS");
         self.write_kpoints()?;
 
-        if self.toml_contents["vasp"]["incar"]["base"]
-            .as_str()
-            .unwrap()
-            .to_ascii_lowercase()
-            == "custom"
-        {
-            if std::path::Path::new("INCAR").exists() {
+        if self.incar_base() == "custom" {
+            if Path::new(&self.job_dir).join("INCAR").exists() {
                 println!("Using supplied INCAR");
             } else {
-                eprintln!("INCAR not found. Please create INCAR manually.");
-                std::process::exit(1)
+                return Err(eyre::eyre!(
+                    "INCAR not found in {}. Please create INCAR manually.",
+                    self.job_dir
+                ));
             }
         } else {
             println!("Writing INCAR");
@@ -57,6 +106,128 @@ impl JobConfig {
         }
         println!("Writing Job script");
         self.write_job_script()?;
+        match self.incar_base().as_str() {
+            "freq" => self.write_freq_constraints()?,
+            "dimer" => {
+                println!("Writing MODECAR");
+                self.write_modecar()?;
+            }
+            "mlff_refit" | "mlff_run" => self.check_mlff_input(),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of the configuration which creates the job in `job_dir`.
+    pub fn with_job_dir(&self, job_dir: &str) -> JobConfig {
+        JobConfig {
+            toml_contents: self.toml_contents.clone(),
+            job_dir: job_dir.into(),
+        }
+    }
+
+    /// Name of the job for SLURM.
+    pub fn job_name(&self) -> &str {
+        self.toml_contents["slurm"]["job_name"].as_str().unwrap()
+    }
+
+    pub fn set_job_name(&mut self, job_name: &str) {
+        self.toml_contents["slurm"]["job_name"] = Value::String(job_name.to_string());
+    }
+
+    /// Sets INCAR tag in `[vasp.incar.tags]`, which overrides the tag of the base template.
+    pub fn set_incar_tag(&mut self, name: &str, value: Value) {
+        let incar = self.toml_contents["vasp"]["incar"].as_table_mut().unwrap();
+        let tags = incar
+            .entry("tags")
+            .or_insert_with(|| Value::Table(toml::value::Table::new()));
+        tags.as_table_mut().unwrap().insert(name.to_string(), value);
+    }
+
+    /// Base INCAR template, in lowercase.
+    pub fn incar_base(&self) -> String {
+        self.toml_contents["vasp"]["incar"]["base"]
+            .as_str()
+            .unwrap()
+            .to_ascii_lowercase()
+    }
+
+    /// Sets base INCAR template.
+    pub fn set_incar_base(&mut self, base: &str) {
+        self.toml_contents["vasp"]["incar"]["base"] = Value::String(base.to_string());
+    }
+
+    /// Sets k-points density in reciprocal space, replacing the mesh if given.
+    pub fn set_kpoints_density(&mut self, density: f64) {
+        let kpoints = self.toml_contents["vasp"]["kpoints"].as_table_mut().unwrap();
+        kpoints.remove("mesh");
+        kpoints.insert(String::from("density"), Value::Float(density));
+    }
+
+    /// Writes Vasp.toml of this configuration in the job directory.
+    /// The written configuration uses POSCAR of the job directory as input.
+    pub fn write_config(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.job_dir)?;
+        let mut toml_contents = self.toml_contents.clone();
+        toml_contents["vasp"]["input"] = Value::String(String::from("POSCAR"));
+        let toml_str = toml::to_string(&toml_contents)?;
+        std::fs::write(Path::new(&self.job_dir).join("Vasp.toml"), toml_str)?;
         Ok(())
     }
+
+    /// Structure file of the job, from which POTCAR and KPOINTS are generated.
+    pub fn structure_file(&self) -> String {
+        let structure_file = match self.incar_base().as_str() {
+            "neb" => Path::new(&self.job_dir).join("00").join("POSCAR"),
+            _ => Path::new(&self.job_dir).join("POSCAR"),
+        };
+        structure_file.to_str().unwrap().to_string()
+    }
+
+    /// Writes the input structure to the job directory as POSCAR.
+    /// CIF and (extended) XYZ are converted, and atoms are sorted by element if
+    /// `sort = true` or `species_order` is given in `[vasp]`.
+    /// Returns false if the input structure is already the POSCAR of the job directory.
+    pub fn write_poscar(&self) -> Result<bool> {
+        let vasp_config = &self.toml_contents["vasp"];
+        let input_file = Path::new(vasp_config["input"].as_str().unwrap());
+        let poscar_path = Path::new(&self.job_dir).join("POSCAR");
+        let species_order: Option<Vec<String>> = vasp_config
+            .get("species_order")
+            .and_then(|order| order.as_array())
+            .map(|order| order.iter().filter_map(|e| e.as_str()).map(String::from).collect());
+        let sort = vasp_config.get("sort").and_then(|s| s.as_bool()).unwrap_or(false) || species_order.is_some();
+
+        if StructureFormat::from_path(input_file) == StructureFormat::Poscar && !sort {
+            if poscar_path.exists() && input_file.canonicalize()? == poscar_path.canonicalize()? {
+                return Ok(false);
+            }
+            std::fs::copy(input_file, poscar_path)?;
+            return Ok(true);
+        }
+        let mut structure = read_structure(input_file)?;
+        if sort {
+            structure = sort_species(&structure, species_order.as_deref())?;
+        }
+        structure.to_file(poscar_path)?;
+        Ok(true)
+    }
+
+    /// Writes a job of each structure in its directory, relative to the job directory.
+    /// Job script in the job directory runs them one after another.
+    pub fn write_sub_jobs(&self, structures: &[(String, Poscar)]) -> Result<()> {
+        for (dir, structure) in structures {
+            let job_dir = Path::new(&self.job_dir).join(dir);
+            std::fs::create_dir_all(&job_dir)?;
+            structure.to_file(job_dir.join("POSCAR"))?;
+            let job_config = self.with_job_dir(job_dir.to_str().unwrap());
+            job_config.write_potcar()?;
+            job_config.write_kpoints()?;
+            job_config.write_incar()?;
+            job_config.write_job_script()?;
+        }
+        println!("Writing Job script");
+        let dirs: Vec<String> = structures.iter().map(|(dir, _)| dir.clone()).collect();
+        self.write_sequential_job_script(&dirs)
+    }
 }
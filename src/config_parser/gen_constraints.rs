@@ -0,0 +1,121 @@
+use super::config::JobConfig;
+use crate::structure_util::{parse_index_ranges, AtomSelection};
+use eyre::Result;
+use std::path::Path;
+use toml::Value;
+use vasp_files::poscar::Poscar;
+
+/// Parses atom selection criteria from a table of Vasp.toml:
+/// `indices`, `elements`, `above`, `below`, `fractional`, `bottom_layers` and `layer_tolerance`.
+pub fn parse_atom_selection(table: &Value) -> Result<AtomSelection> {
+    let number = |key: &str| -> Option<f64> {
+        table
+            .get(key)
+            .and_then(|v| v.as_float().or_else(|| v.as_integer().map(|i| i as f64)))
+    };
+    let indices = match table.get("indices") {
+        Some(Value::String(ranges)) => parse_index_ranges(ranges)?,
+        Some(Value::Array(indices)) => indices
+            .iter()
+            .map(|i| match i.as_integer() {
+                Some(i) if i > 0 => Ok(i as usize - 1),
+                _ => Err(eyre::eyre!("Atom indices should be positive integers")),
+            })
+            .collect::<Result<_>>()?,
+        Some(_) => return Err(eyre::eyre!("Atom indices should be a list or ranges like \"1-4, 7\"")),
+        None => vec![],
+    };
+    let elements = match table.get("elements").and_then(|e| e.as_array()) {
+        Some(elements) => elements.iter().filter_map(|e| e.as_str()).map(String::from).collect(),
+        None => vec![],
+    };
+    let bottom_layers = match table.get("bottom_layers").map(|n| n.as_integer()) {
+        Some(Some(n)) if n > 0 => Some(n as usize),
+        Some(_) => return Err(eyre::eyre!("bottom_layers should be a positive integer")),
+        None => None,
+    };
+    Ok(AtomSelection {
+        indices,
+        elements,
+        above: number("above"),
+        below: number("below"),
+        fractional: table.get("fractional").and_then(|f| f.as_bool()).unwrap_or(false),
+        bottom_layers,
+        layer_tolerance: number("layer_tolerance").unwrap_or(0.5),
+    })
+}
+
+impl JobConfig {
+    /// Atoms to fix, from `[vasp.constraints]`. Returns `None` if it does not exist.
+    pub fn constraints(&self) -> Result<Option<AtomSelection>> {
+        match self.toml_contents["vasp"].get("constraints") {
+            Some(constraints) => Ok(Some(parse_atom_selection(constraints)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Writes Selective Dynamics fixing the atoms selected by `[vasp.constraints]` in `poscar`.
+    /// Returns the number of fixed atoms, or `None` if there are no constraints.
+    pub fn apply_constraints(&self, poscar: &mut Poscar) -> Result<Option<usize>> {
+        let constraints = match self.constraints()? {
+            Some(constraints) => constraints,
+            None => return Ok(None),
+        };
+        let fixed = constraints.select(poscar)?;
+        poscar.selective_dynamics = Some(fixed.iter().map(|&f| [!f; 3]).collect());
+        Ok(Some(fixed.iter().filter(|&&f| f).count()))
+    }
+
+    /// Applies `[vasp.constraints]` to POSCAR of the job.
+    pub fn write_constraints(&self) -> Result<()> {
+        if self.constraints()?.is_none() {
+            return Ok(());
+        }
+        let poscar_path = Path::new(&self.job_dir).join("POSCAR");
+        let mut poscar = Poscar::from_file(&poscar_path)?;
+        if let Some(num_fixed) = self.apply_constraints(&mut poscar)? {
+            poscar.to_file(&poscar_path)?;
+            println!("Fixing {} of {} atoms", num_fixed, poscar.frac_coords.len());
+        }
+        Ok(())
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_apply_constraints() {
+        use super::*;
+        let mut poscar = Poscar {
+            comment: String::from("Pt slab"),
+            scale: 1.0,
+            lattice: [[5.0, 0.0, 0.0], [0.0, 5.0, 0.0], [0.0, 0.0, 10.0]],
+            species: vec![String::from("Pt"), String::from("O")],
+            num_atoms: vec![3, 1],
+            frac_coords: vec![[0.0, 0.0, 0.1], [0.5, 0.5, 0.12], [0.0, 0.5, 0.3], [0.0, 0.5, 0.45]],
+            selective_dynamics: None,
+        };
+        let job_config = |constraints: &str| JobConfig {
+            toml_contents: toml::from_str(&format!("[vasp]\n{}", constraints)).unwrap(),
+            job_dir: String::from("."),
+        };
+        assert_eq!(job_config("").apply_constraints(&mut poscar).unwrap(), None);
+        assert!(poscar.selective_dynamics.is_none());
+
+        let num_fixed = job_config("constraints = { bottom_layers = 1, indices = \"4\" }")
+            .apply_constraints(&mut poscar)
+            .unwrap();
+        assert_eq!(num_fixed, Some(3));
+        // Fixed atoms cannot move in any direction.
+        assert_eq!(
+            poscar.selective_dynamics,
+            Some(vec![[false; 3], [false; 3], [true; 3], [false; 3]])
+        );
+
+        assert!(job_config("constraints = { indices = \"3-1\" }")
+            .apply_constraints(&mut poscar)
+            .is_err());
+        assert!(job_config("constraints = { bottom_layers = 0 }")
+            .apply_constraints(&mut poscar)
+            .is_err());
+    }
+}
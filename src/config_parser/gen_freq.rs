@@ -0,0 +1,104 @@
+use super::config::JobConfig;
+use super::gen_constraints::parse_atom_selection;
+use crate::output_parser::Vibrations;
+use eyre::Result;
+use std::path::Path;
+use vasp_files::poscar::Poscar;
+
+impl JobConfig {
+    /// Writes Selective Dynamics in POSCAR of the job, so that only atoms selected in
+    /// `[vasp.freq]` are displaced. Does nothing if `[vasp.freq]` does not exist.
+    pub fn write_freq_constraints(&self) -> Result<()> {
+        let freq_config = match self.toml_contents["vasp"].get("freq") {
+            Some(freq_config) => freq_config,
+            None => return Ok(()),
+        };
+        let poscar_path = Path::new(&self.job_dir).join("POSCAR");
+        let mut poscar = Poscar::from_file(&poscar_path)?;
+        let displaced = parse_atom_selection(freq_config)?.select(&poscar)?;
+        let num_displaced = displaced.iter().filter(|&&d| d).count();
+        if num_displaced == 0 {
+            return Err(eyre::eyre!("No atom is selected to displace in [vasp.freq]"));
+        }
+        poscar.selective_dynamics = Some(displaced.iter().map(|&d| [d; 3]).collect());
+        poscar.to_file(&poscar_path)?;
+        println!("Displacing {} of {} atoms", num_displaced, displaced.len());
+        Ok(())
+    }
+
+    /// Writes MODECAR of the dimer method from `[vasp.dimer]`: either copied from `modecar`,
+    /// or the most imaginary mode of a frequency run in `freq_dir`.
+    pub fn write_modecar(&self) -> Result<()> {
+        let dimer_config = self.toml_contents["vasp"]
+            .get("dimer")
+            .ok_or_else(|| eyre::eyre!("[vasp.dimer] section is required for dimer job"))?;
+        let modecar_path = Path::new(&self.job_dir).join("MODECAR");
+        let path = |key: &str| dimer_config.get(key).and_then(|p| p.as_str());
+        if let Some(modecar) = path("modecar") {
+            std::fs::copy(Path::new(&self.job_dir).join(modecar), modecar_path)?;
+            return Ok(());
+        }
+        let freq_dir = path("freq_dir").ok_or_else(|| eyre::eyre!("Specify modecar or freq_dir in [vasp.dimer]"))?;
+        let freq_dir = Path::new(&self.job_dir).join(freq_dir);
+        let vibrations = Vibrations::from_dir(freq_dir.to_str().unwrap())?;
+        let mode = vibrations
+            .most_imaginary_mode()
+            .ok_or_else(|| eyre::eyre!("No imaginary mode found in {}", freq_dir.display()))?;
+        println!(
+            "Using imaginary mode of {:.3}i cm-1 as dimer direction",
+            mode.frequency_cm
+        );
+        let modecar: String = mode
+            .eigenvector
+            .iter()
+            .map(|v| format!("{:>14.8} {:>14.8} {:>14.8}\n", v[0], v[1], v[2]))
+            .collect();
+        std::fs::write(modecar_path, modecar)?;
+        Ok(())
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_write_modecar() {
+        use super::*;
+        let job_dir = std::env::temp_dir().join(format!("vasp_manager_test_modecar_{}", std::process::id()));
+        std::fs::create_dir_all(job_dir.join("freq")).unwrap();
+        let outcar = " Eigenvectors and eigenvalues of the dynamical matrix
+ ----------------------------------------------------
+
+   1 f  =   10.000000 THz    62.831853 2PiTHz  333.564090 cm-1    41.356725 meV
+             X         Y         Z           dx          dy          dz
+      0.000000  0.000000  0.000000     0.707107    0.000000    0.000000
+      0.000000  0.000000  1.150000    -0.707107    0.000000    0.000000
+
+   2 f/i=    2.000000 THz    12.566371 2PiTHz   66.712818 cm-1     8.271345 meV
+             X         Y         Z           dx          dy          dz
+      0.000000  0.000000  0.000000     0.000000    0.707107    0.000000
+      0.000000  0.000000  1.150000     0.000000    0.707107    0.000000
+
+   3 f/i=   15.000000 THz    94.247780 2PiTHz  500.346135 cm-1    62.035087 meV
+             X         Y         Z           dx          dy          dz
+      0.000000  0.000000  0.000000     0.000000    0.600000    0.000000
+      0.000000  0.000000  1.150000     0.000000   -0.800000    0.000000
+
+";
+        std::fs::write(job_dir.join("freq").join("OUTCAR"), outcar).unwrap();
+        let job_config = |dimer: &str| JobConfig {
+            toml_contents: toml::from_str(&format!("[vasp.dimer]\n{}", dimer)).unwrap(),
+            job_dir: job_dir.to_str().unwrap().to_string(),
+        };
+        job_config("freq_dir = \"freq\"").write_modecar().unwrap();
+        let modecar = std::fs::read_to_string(job_dir.join("MODECAR")).unwrap();
+        let vectors: Vec<Vec<f64>> = modecar
+            .lines()
+            .map(|line| line.split_whitespace().map(|v| v.parse().unwrap()).collect())
+            .collect();
+        assert_eq!(vectors, vec![vec![0.0, 0.6, 0.0], vec![0.0, -0.8, 0.0]]);
+
+        std::fs::write(job_dir.join("freq").join("OUTCAR"), outcar.replace("f/i=", "f  =")).unwrap();
+        assert!(job_config("freq_dir = \"freq\"").write_modecar().is_err());
+        assert!(job_config("").write_modecar().is_err());
+        std::fs::remove_dir_all(&job_dir).unwrap();
+    }
+}
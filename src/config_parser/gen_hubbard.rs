@@ -0,0 +1,159 @@
+use super::config::JobConfig;
+use crate::template::IncarTag;
+use eyre::Result;
+use phf::phf_map;
+use std::collections::HashMap;
+use vasp_files::poscar::Poscar;
+
+/// U values (eV) on d orbitals of Materials Project (MP2020 compatibility),
+/// applied to oxides and fluorides.
+pub static MATERIALS_PROJECT_U: phf::Map<&'static str, f64> = phf_map! {
+    "Co" => 3.32,
+    "Cr" => 3.7,
+    "Fe" => 5.3,
+    "Mn" => 3.9,
+    "Mo" => 4.38,
+    "Ni" => 6.2,
+    "V" => 3.25,
+    "W" => 6.2,
+};
+
+/// On-site Coulomb interaction of an element.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hubbard {
+    /// Angular momentum of the orbitals (2 for d, 3 for f).
+    pub l: i64,
+    pub u: f64,
+    pub j: f64,
+}
+
+impl JobConfig {
+    /// Hubbard parameters of each element from `[vasp.hubbard]`.
+    /// Elements given explicitly override the preset.
+    pub fn hubbard(&self, species: &[String]) -> Result<Option<HashMap<String, Hubbard>>> {
+        let hubbard_config = match self.toml_contents["vasp"].get("hubbard").and_then(|h| h.as_table()) {
+            Some(hubbard_config) => hubbard_config,
+            None => return Ok(None),
+        };
+        let mut hubbard = HashMap::new();
+        match hubbard_config.get("preset").and_then(|p| p.as_str()) {
+            // Materials Project applies U only if O or F is present.
+            Some("materials_project") if species.iter().any(|s| s == "O" || s == "F") => {
+                for (element, &u) in MATERIALS_PROJECT_U.entries() {
+                    hubbard.insert(element.to_string(), Hubbard { l: 2, u, j: 0.0 });
+                }
+            }
+            Some("materials_project") => (),
+            Some(preset) => {
+                return Err(eyre::eyre!(
+                    "Unknown Hubbard U preset '{}'. Available presets: materials_project",
+                    preset
+                ))
+            }
+            None => (),
+        }
+        let number =
+            |value: Option<&toml::Value>| value.and_then(|v| v.as_float().or_else(|| v.as_integer().map(|i| i as f64)));
+        for (element, parameters) in hubbard_config.iter().filter(|(key, _)| *key != "preset") {
+            if !species.contains(element) {
+                return Err(eyre::eyre!("Element {} in [vasp.hubbard] is not in POSCAR", element));
+            }
+            let u = number(parameters.get("u")).ok_or_else(|| {
+                eyre::eyre!(
+                    "Specify u of {} in [vasp.hubbard], e.g. {} = {{ l = 2, u = 5.3 }}",
+                    element,
+                    element
+                )
+            })?;
+            let l = match parameters.get("l").and_then(|l| l.as_integer()) {
+                Some(l) if (0..=3).contains(&l) => l,
+                None => 2,
+                Some(l) => return Err(eyre::eyre!("Invalid l = {} of {} in [vasp.hubbard]", l, element)),
+            };
+            let j = number(parameters.get("j")).unwrap_or(0.0);
+            hubbard.insert(element.clone(), Hubbard { l, u, j });
+        }
+        Ok(Some(hubbard))
+    }
+
+    /// LDAU tags from `[vasp.hubbard]`, ordered as the species of POSCAR.
+    pub fn hubbard_tags(&self) -> Result<Vec<IncarTag>> {
+        if self.toml_contents["vasp"].get("hubbard").is_none() {
+            return Ok(vec![]);
+        }
+        let poscar = Poscar::from_file(self.structure_file())?;
+        let hubbard = match self.hubbard(&poscar.species)? {
+            Some(hubbard) => hubbard,
+            None => return Ok(vec![]),
+        };
+        let parameters: Vec<Option<&Hubbard>> = poscar.species.iter().map(|s| hubbard.get(s)).collect();
+        if parameters.iter().all(|p| p.is_none()) {
+            return Ok(vec![]);
+        }
+
+        let join = |f: &dyn Fn(Option<&Hubbard>) -> String| -> String {
+            parameters.iter().map(|&p| f(p)).collect::<Vec<String>>().join(" ")
+        };
+        let max_l = parameters.iter().flatten().map(|p| p.l).max().unwrap_or(0);
+        let tag = |name: &str, value: String| IncarTag {
+            name: name.to_string(),
+            value,
+        };
+        let mut tags = vec![
+            tag("LDAU", String::from(".TRUE.")),
+            tag("LDAUTYPE", String::from("2")),
+            tag("LDAUL", join(&|p| p.map_or(-1, |p| p.l).to_string())),
+            tag("LDAUU", join(&|p| format!("{:?}", p.map_or(0.0, |p| p.u)))),
+            tag("LDAUJ", join(&|p| format!("{:?}", p.map_or(0.0, |p| p.j)))),
+            tag("LDAUPRINT", String::from("1")),
+        ];
+        // Charge density mixing should include l = 4 for d and l = 6 for f electrons.
+        match max_l {
+            2 => tags.push(tag("LMAXMIX", String::from("4"))),
+            3 => tags.push(tag("LMAXMIX", String::from("6"))),
+            _ => (),
+        }
+        Ok(tags)
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_hubbard_tags() {
+        use super::*;
+        let job_dir = std::env::temp_dir().join(format!("vasp_manager_test_hubbard_{}", std::process::id()));
+        std::fs::create_dir_all(&job_dir).unwrap();
+        let poscar = "FeGdO
+1.0
+ 4.0 0.0 0.0
+ 0.0 4.0 0.0
+ 0.0 0.0 4.0
+O Fe Gd
+1 1 1
+Direct
+ 0.5 0.0 0.0
+ 0.0 0.0 0.0
+ 0.5 0.5 0.5
+";
+        std::fs::write(job_dir.join("POSCAR"), poscar).unwrap();
+        let job_config = |hubbard: &str| JobConfig {
+            toml_contents: toml::from_str(&format!("[vasp.incar]\nbase = \"relax\"\n[vasp.hubbard]\n{}", hubbard))
+                .unwrap(),
+            job_dir: job_dir.to_str().unwrap().to_string(),
+        };
+
+        let tags = job_config("preset = \"materials_project\"\nGd = { l = 3, u = 6.7, j = 0.7 }")
+            .hubbard_tags()
+            .unwrap();
+        let value = |name: &str| tags.iter().find(|t| t.name == name).unwrap().value.clone();
+        assert_eq!(value("LDAUL"), "-1 2 3");
+        assert_eq!(value("LDAUU"), "0.0 5.3 6.7");
+        assert_eq!(value("LDAUJ"), "0.0 0.0 0.7");
+        assert_eq!(value("LMAXMIX"), "6");
+
+        let tags = job_config("Fe = { u = 4 }").hubbard_tags().unwrap();
+        assert_eq!(tags.iter().find(|t| t.name == "LMAXMIX").unwrap().value, "4");
+        assert!(job_config("Ni = { u = 6.2 }").hubbard_tags().is_err());
+        std::fs::remove_dir_all(&job_dir).unwrap();
+    }
+}
@@ -1,18 +1,42 @@
 use super::config;
 use super::config::format_value;
+use super::gen_workflow::PreStep;
 use crate::template::{IncarTag, TEMPLATES};
 use eyre::Result;
 use tera::Context;
 
 impl config::JobConfig {
-    /// Write the INCAR file from pre-defined templates and additional tags.
-    pub fn write_incar(&self) -> Result<()> {
-        let incar_config = &self.toml_contents["vasp"]["incar"];
-        let base_incar = {
-            let base = incar_config["base"].as_str().unwrap();
-            format!("INCAR_{}", base)
-        };
-        let additional_tags: Vec<IncarTag> = match incar_config.get("tags") {
+    /// Tags added to the INCAR template. Tags generated from other sections of Vasp.toml
+    /// (e.g. `[vasp.magnetism]`) precede the additional tags, so that additional tags override them.
+    fn incar_tags(&self) -> Result<Vec<IncarTag>> {
+        let mut incar_tags: Vec<IncarTag> = self.magnetism_tags()?;
+        incar_tags.extend(self.hubbard_tags()?);
+        incar_tags.extend(self.parallel_tags(None)?);
+        incar_tags.extend(self.functional_tags()?);
+        incar_tags.extend(self.additional_tags());
+        Ok(incar_tags)
+    }
+
+    /// Tags added to the INCAR template of the pre-step. The pre-step runs its own functional, so the
+    /// functional tags of the task and the excluded additional tags are left out.
+    /// Parallelization follows the k-points of the pre-step.
+    fn pre_step_incar_tags(&self, pre_step: &PreStep) -> Result<Vec<IncarTag>> {
+        let mut incar_tags: Vec<IncarTag> = self.magnetism_tags()?;
+        incar_tags.extend(self.hubbard_tags()?);
+        incar_tags.extend(self.parallel_tags(Some(pre_step))?);
+        incar_tags.extend(self.additional_tags().into_iter().filter(|tag| {
+            !pre_step
+                .excluded_tags
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(&tag.name))
+        }));
+        incar_tags.extend(pre_step.incar_tags.clone());
+        Ok(incar_tags)
+    }
+
+    /// Tags of `tags` in `[vasp.incar]`.
+    fn additional_tags(&self) -> Vec<IncarTag> {
+        match self.toml_contents["vasp"]["incar"].get("tags") {
             Some(tags) => tags
                 .as_table()
                 .unwrap()
@@ -23,14 +47,175 @@ impl config::JobConfig {
                 })
                 .collect(),
             None => vec![],
-        };
-        let mut context = Context::new();
-        context.insert("incar_tags", &additional_tags);
-        let rendered = TEMPLATES
-            .render(&base_incar, &context)
-            .unwrap_or_else(|_| panic!("Template {} not found.", base_incar));
-        let incar_path = format!("{}/INCAR", self.job_dir);
-        std::fs::write(incar_path, rendered)?;
+        }
+    }
+
+    /// Contents of INCAR of the job, without writing it.
+    pub fn incar_contents(&self) -> Result<String> {
+        self.render_incar(&self.incar_base(), &self.incar_tags()?)
+    }
+
+    /// Write the INCAR file from pre-defined templates and additional tags.
+    /// INCAR of the pre-step of the task is also written, if any.
+    pub fn write_incar(&self) -> Result<()> {
+        let incar = self.render_incar(
+            self.toml_contents["vasp"]["incar"]["base"].as_str().unwrap(),
+            &self.incar_tags()?,
+        )?;
+        std::fs::write(format!("{}/INCAR", self.job_dir), incar)?;
+
+        if let Some(pre_step) = self.pre_step() {
+            let incar = self.render_incar(&pre_step.incar_base, &self.pre_step_incar_tags(&pre_step)?)?;
+            std::fs::write(format!("{}/INCAR.{}", self.job_dir, pre_step.name), incar)?;
+        }
         Ok(())
     }
+
+    /// Renders INCAR template `INCAR_<base>` with tags.
+    fn render_incar(&self, base: &str, incar_tags: &[IncarTag]) -> Result<String> {
+        let base_incar = format!("INCAR_{}", base);
+        let mut context = Context::new();
+        context.insert("incar_tags", &incar_tags);
+        match self.toml_contents["vasp"].get("neb").and_then(|neb| neb.get("images")) {
+            Some(images) => context.insert("images", &format_value(images)),
+            None => context.insert("images", "<images>"),
+        }
+        let rendered = TEMPLATES.render(&base_incar, &context)?;
+        Ok(remove_overridden_tags(&rendered))
+    }
+}
+
+/// Removes tags defined more than once, keeping the last definition.
+/// Additional tags are rendered after the template, so that they override the template.
+/// Lines may define several tags separated by `;`, of which only the overridden ones are removed.
+fn remove_overridden_tags(incar: &str) -> String {
+    let tag_name = |statement: &str| -> Option<String> {
+        let (name, _) = statement.split_once('=')?;
+        let name = name.trim();
+        match !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            true => Some(name.to_ascii_uppercase()),
+            false => None,
+        }
+    };
+    // Statements of each line, and the comment after `!` or `#`.
+    let lines: Vec<(Vec<&str>, &str)> = incar
+        .lines()
+        .map(|line| {
+            let (code, comment) = line.split_at(line.find(['!', '#']).unwrap_or(line.len()));
+            (code.split(';').collect(), comment)
+        })
+        .collect();
+    let names: Vec<Option<String>> = lines
+        .iter()
+        .flat_map(|(statements, _)| statements.iter().map(|s| tag_name(s)))
+        .collect();
+    let mut kept = vec![];
+    let mut index = 0;
+    for (line, (statements, comment)) in incar.lines().zip(&lines) {
+        let remaining: Vec<&str> = statements
+            .iter()
+            .enumerate()
+            .filter(|(k, _)| match &names[index + k] {
+                Some(name) => !names[index + k + 1..].contains(&Some(name.clone())),
+                None => true,
+            })
+            .map(|(_, statement)| *statement)
+            .collect();
+        index += statements.len();
+        if remaining.len() == statements.len() {
+            kept.push(line.to_string());
+        } else if remaining.iter().any(|s| !s.trim().is_empty()) {
+            let code: Vec<&str> = remaining.iter().map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
+            kept.push(format!("{} {}", code.join("; "), comment).trim_end().to_string());
+        }
+    }
+    let mut result = kept.join("\n");
+    result.push('\n');
+    result
+}
+
+mod tests {
+    #[test]
+    fn test_remove_overridden_tags() {
+        use super::*;
+        let incar = "Global Parameters
+ISPIN  =  2
+ENCUT  =  520        (Cut-off energy)
+ISMEAR = 0; SIGMA = 0.05 ! Gaussian smearing
+
+encut = 600
+SIGMA = 0.01
+";
+        assert_eq!(
+            remove_overridden_tags(incar),
+            "Global Parameters
+ISPIN  =  2
+ISMEAR = 0 ! Gaussian smearing
+
+encut = 600
+SIGMA = 0.01
+"
+        );
+        let unchanged = "ISMEAR = 0; SIGMA = 0.05\nNSW = 0\n";
+        assert_eq!(remove_overridden_tags(unchanged), unchanged);
+        assert_eq!(remove_overridden_tags("A = 1; B = 2; A = 3\n"), "B = 2; A = 3\n");
+    }
+
+    #[test]
+    fn test_write_incar_pre_step() {
+        use super::*;
+        let job_dir = std::env::temp_dir().join(format!("vasp_manager_test_pre_step_{}", std::process::id()));
+        std::fs::create_dir_all(&job_dir).unwrap();
+        let job_config = config::JobConfig {
+            toml_contents: toml::from_str(
+                r#"
+                [slurm]
+                num_nodes = 1
+                num_tasks = 64
+
+                [vasp]
+                bin = "vasp_std"
+                parallel = "auto"
+                kpoints = { scheme = "Gamma", mesh = [4, 4, 4] }
+
+                [vasp.incar]
+                base = "hse"
+                [vasp.incar.tags]
+                AEXX = 0.3
+                ALGO = "Damped"
+                ENCUT = 600
+                "#,
+            )
+            .unwrap(),
+            job_dir: job_dir.to_str().unwrap().to_string(),
+        };
+        job_config.write_incar().unwrap();
+        let incar = std::fs::read_to_string(job_dir.join("INCAR")).unwrap();
+        let pre_step_incar = std::fs::read_to_string(job_dir.join("INCAR.pbe")).unwrap();
+        std::fs::remove_dir_all(&job_dir).unwrap();
+
+        let tag_value = |incar: &str, name: &str| -> Option<String> {
+            incar
+                .lines()
+                .filter_map(|line| line.split_once('='))
+                .rev()
+                .find(|(tag, _)| tag.trim() == name)
+                .map(|(_, value)| value.split_whitespace().next().unwrap_or("").to_string())
+        };
+        // The pre-step gives WAVECAR on the same irreducible k-points.
+        assert_eq!(tag_value(&incar, "ISYM").as_deref(), Some("0"));
+        assert_eq!(tag_value(&pre_step_incar, "ISYM"), tag_value(&incar, "ISYM"));
+        assert_eq!(tag_value(&pre_step_incar, "LWAVE").as_deref(), Some(".TRUE."));
+        // Hybrid functional tags only apply to the main calculation.
+        assert_eq!(tag_value(&incar, "AEXX").as_deref(), Some("0.3"));
+        assert_eq!(tag_value(&incar, "ALGO").as_deref(), Some("Damped"));
+        assert_eq!(tag_value(&incar, "NKRED").as_deref(), Some("2"));
+        for name in ["LHFCALC", "HFSCREEN", "AEXX", "NKRED", "METAGGA", "ALGO"] {
+            assert_eq!(tag_value(&pre_step_incar, name), None);
+        }
+        assert_eq!(tag_value(&pre_step_incar, "ENCUT").as_deref(), Some("600"));
+        // 36 irreducible k-points without symmetry.
+        assert_eq!(tag_value(&incar, "KPAR").as_deref(), Some("8"));
+        assert_eq!(tag_value(&pre_step_incar, "KPAR").as_deref(), Some("8"));
+    }
 }
@@ -1,26 +1,150 @@
 use super::super::template::TEMPLATES;
 use super::config;
 use super::config::format_value;
+use crate::check_util::resolve_vasp_bin;
 use eyre::Result;
 use tera::Context;
 
 impl config::JobConfig {
-    pub fn write_job_script(&self) -> Result<()> {
+    /// Template context of SLURM resources and VASP binary, resolved from the user config or `VASP_DIR`.
+    fn job_script_context(&self) -> Result<Context> {
         let vasp_config = &self.toml_contents["vasp"];
         let slurm_config = &self.toml_contents["slurm"];
 
-        let rendered_job_script = {
-            let mut context = Context::new();
-            context.insert("partition", &format_value(&slurm_config["partition"]));
-            context.insert("num_nodes", &format_value(&slurm_config["num_nodes"]));
-            context.insert("num_tasks", &format_value(&slurm_config["num_tasks"]));
-            context.insert("vasp_version", &format_value(&vasp_config["version"]));
-            context.insert("bin", &format_value(&vasp_config["bin"]));
+        let mut context = Context::new();
+        context.insert("partition", &format_value(&slurm_config["partition"]));
+        context.insert("num_nodes", &format_value(&slurm_config["num_nodes"]));
+        context.insert("num_tasks", &format_value(&slurm_config["num_tasks"]));
+        context.insert("vasp_version", &format_value(&vasp_config["version"]));
+        context.insert("bin", &format_value(&vasp_config["bin"]));
+        let vasp_bin = resolve_vasp_bin(
+            &format_value(&vasp_config["version"]),
+            &format_value(&vasp_config["bin"]),
+        );
+        context.insert("vasp_executable", &vasp_bin.executable);
+        context.insert("vasp_load", &vasp_bin.load);
+        if let Some(pre_step) = self.pre_step() {
+            context.insert("pre_step", &pre_step);
+        }
+        if let Some(bader) = self.bader_executable()? {
+            context.insert("bader", &bader);
+            context.insert("vasp_manager", &vasp_manager_path());
+        }
+        Ok(context)
+    }
 
-            TEMPLATES.render("job_script.sh", &context)?
+    /// `bader` executable if `run = true` in `[vasp.bader]`, to run Bader analysis after VASP.
+    /// The executable is given by `executable`, "bader" by default.
+    /// The analysis needs AECCAR0 and AECCAR2, written by the `bader` task or with LAECHG in the additional tags.
+    pub fn bader_executable(&self) -> Result<Option<String>> {
+        let bader_config = match self.toml_contents["vasp"].get("bader") {
+            Some(bader_config) => bader_config,
+            None => return Ok(None),
         };
+        if !bader_config.get("run").and_then(|r| r.as_bool()).unwrap_or(false) {
+            return Ok(None);
+        }
+        let laechg = self.toml_contents["vasp"]["incar"]
+            .get("tags")
+            .and_then(|tags| tags.as_table())
+            .and_then(|tags| tags.iter().find(|(name, _)| name.eq_ignore_ascii_case("LAECHG")))
+            .is_some_and(|(_, value)| {
+                value.as_bool().unwrap_or(false)
+                    || [".TRUE.", "T", ".T."].contains(&format_value(value).to_ascii_uppercase().as_str())
+            });
+        if self.incar_base() != "bader" && !laechg {
+            return Err(eyre::eyre!(
+                "Bader analysis (run = true in [vasp.bader]) needs AECCAR0 and AECCAR2. \
+                 Use base = \"bader\" or set LAECHG = true in [vasp.incar.tags]"
+            ));
+        }
+        let executable = bader_config
+            .get("executable")
+            .and_then(|e| e.as_str())
+            .unwrap_or("bader");
+        Ok(Some(executable.to_string()))
+    }
+
+    /// Contents of `job_script.sh` of the job, without writing it.
+    pub fn job_script_contents(&self) -> Result<String> {
+        Ok(TEMPLATES.render("job_script.sh", &self.job_script_context()?)?)
+    }
 
+    pub fn write_job_script(&self) -> Result<()> {
+        std::fs::write(format!("{}/job_script.sh", self.job_dir), self.job_script_contents()?)?;
+        Ok(())
+    }
+
+    /// Writes `job_script.sh` in the job directory, which runs the jobs in `dirs` (relative to the job directory)
+    /// one after another.
+    pub fn write_sequential_job_script(&self, dirs: &[String]) -> Result<()> {
+        let rendered_job_script = {
+            let mut context = self.job_script_context()?;
+            context.insert("sub_dirs", &dirs.join(" "));
+            TEMPLATES.render("job_script.sh", &context)?
+        };
         std::fs::write(format!("{}/job_script.sh", self.job_dir), rendered_job_script)?;
         Ok(())
     }
+
+    /// Writes `job_array.sh` in the job directory, which runs the jobs in the directories listed in
+    /// `dir_list` (one per line, relative to the job directory) as a SLURM job array.
+    /// At most `throttle` array tasks run at the same time, if given.
+    pub fn write_array_script(&self, dir_list: &str, num_jobs: usize, throttle: Option<usize>) -> Result<()> {
+        let rendered_job_script = self.array_script_contents(dir_list, num_jobs, throttle)?;
+        std::fs::write(format!("{}/job_array.sh", self.job_dir), rendered_job_script)?;
+        Ok(())
+    }
+
+    /// Contents of `job_array.sh` of the job, without writing it.
+    pub fn array_script_contents(&self, dir_list: &str, num_jobs: usize, throttle: Option<usize>) -> Result<String> {
+        let mut context = self.job_script_context()?;
+        context.insert("dir_list", dir_list);
+        context.insert("last_index", &(num_jobs.max(1) - 1));
+        context.insert("throttle", &throttle);
+        Ok(TEMPLATES.render("job_array.sh", &context)?)
+    }
+}
+
+/// Path of the running vasp_manager, to call its subcommands from job scripts.
+fn vasp_manager_path() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|path| path.to_str().map(String::from))
+        .unwrap_or_else(|| String::from("vasp_manager"))
+}
+
+mod tests {
+    #[test]
+    fn test_array_script_contents() {
+        use super::*;
+        let config = config::JobConfig {
+            toml_contents: toml::from_str(
+                r#"
+                [slurm]
+                job_name = "test"
+                partition = "g1"
+                num_nodes = 1
+                num_tasks = 16
+
+                [vasp]
+                version = "6.3.1"
+                bin = "vasp_std"
+
+                [vasp.incar]
+                base = "hse"
+                "#,
+            )
+            .unwrap(),
+            job_dir: String::from("."),
+        };
+        let script = config.array_script_contents("job_dirs.txt", 3, Some(2)).unwrap();
+        assert!(script.contains("#SBATCH --array=0-2%2\n"));
+        assert!(script.contains("sed -n \"$((SLURM_ARRAY_TASK_ID + 1))p\" job_dirs.txt"));
+        assert!(script.contains("cp INCAR.pbe INCAR\n"));
+        assert!(!script.contains("bader"));
+
+        let script = config.array_script_contents("job_dirs.txt", 3, None).unwrap();
+        assert!(script.contains("#SBATCH --array=0-2\n"));
+    }
 }
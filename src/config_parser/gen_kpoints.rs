@@ -1,14 +1,41 @@
 use super::config;
+use super::gen_workflow::PreStep;
 use eyre::Result;
 use vasp_files::kpoints::{Kpoints, KpointsScheme};
 use vasp_files::poscar::Poscar;
 
 impl config::JobConfig {
-    /// Generates KPOINTS file from POSCAR.
+    /// Generates KPOINTS file from POSCAR of the job.
     /// Available schemes are "Gamma" and "Monkhorst-Pack".
     /// User can either specify the mesh or density in reciprocal space.
     pub fn write_kpoints(&self) -> Result<()> {
-        let kpoints_config = &self.toml_contents["vasp"]["kpoints"];
+        if let Some(pre_step) = self.pre_step().filter(|pre_step| pre_step.kpoints) {
+            let kpoints = self.step_kpoints_contents(Some(&pre_step))?;
+            std::fs::write(format!("{}/KPOINTS.{}", self.job_dir, pre_step.name), kpoints)?;
+        }
+        std::fs::write(format!("{}/KPOINTS", self.job_dir), self.kpoints_contents()?)?;
+        Ok(())
+    }
+
+    /// Contents of KPOINTS of the job, without writing it.
+    pub fn kpoints_contents(&self) -> Result<String> {
+        match self.incar_base().as_str() {
+            "dos" => self.render_kpoints(&self.dos_kpoints_config()?),
+            _ => self.render_kpoints(&self.toml_contents["vasp"]["kpoints"]),
+        }
+    }
+
+    /// Contents of KPOINTS used by `pre_step`, or by the job if `None`.
+    /// The pre-step with its own KPOINTS (SCF of DOS) runs on the mesh of `[vasp.kpoints]`.
+    pub fn step_kpoints_contents(&self, pre_step: Option<&PreStep>) -> Result<String> {
+        match pre_step {
+            Some(pre_step) if pre_step.kpoints => self.render_kpoints(&self.toml_contents["vasp"]["kpoints"]),
+            _ => self.kpoints_contents(),
+        }
+    }
+
+    /// Renders KPOINTS from `kpoints_config`.
+    fn render_kpoints(&self, kpoints_config: &toml::Value) -> Result<String> {
         let scheme: KpointsScheme = kpoints_config["scheme"]
             .as_str()
             .unwrap()
@@ -28,14 +55,28 @@ impl config::JobConfig {
                 let density = kpoints_config["density"]
                     .as_float()
                     .expect("Please specify mesh or k-points density");
-                let lattice = Poscar::from_file("POSCAR")?.lattice;
+                let lattice = Poscar::from_file(self.structure_file())?.lattice;
                 Kpoints::from_density(scheme, density, lattice)
             }
         };
 
-        let kpoints_str = kpoints.to_string();
-        let kpoints_path = format!("{}/KPOINTS", self.job_dir);
-        std::fs::write(kpoints_path, kpoints_str)?;
-        Ok(())
+        Ok(kpoints.to_string())
+    }
+}
+
+/// Mesh of automatic KPOINTS. Returns `None` for explicit k-points.
+pub fn kpoints_mesh(kpoints: &str) -> Option<[usize; 3]> {
+    let lines: Vec<&str> = kpoints.lines().collect();
+    if lines.get(1)?.trim() != "0" {
+        return None;
+    }
+    let mesh: Vec<usize> = lines
+        .get(3)?
+        .split_whitespace()
+        .map(|n| n.parse().ok())
+        .collect::<Option<_>>()?;
+    match mesh[..] {
+        [n1, n2, n3] => Some([n1, n2, n3]),
+        _ => None,
     }
 }
@@ -0,0 +1,245 @@
+use super::config::JobConfig;
+use crate::structure_util::{atom_species, cartesian_coords, layer_indices};
+use crate::template::IncarTag;
+use eyre::Result;
+use toml::Value;
+use vasp_files::poscar::Poscar;
+
+/// Magnetic moment of an atom: collinear, or a 3-vector for noncollinear calculations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Moment {
+    Collinear(f64),
+    Noncollinear([f64; 3]),
+}
+
+impl Moment {
+    fn from_value(value: &Value) -> Result<Moment> {
+        let number = |v: &Value| v.as_float().or_else(|| v.as_integer().map(|i| i as f64));
+        if let Some(m) = number(value) {
+            return Ok(Moment::Collinear(m));
+        }
+        match value
+            .as_array()
+            .map(|v| v.iter().map(number).collect::<Option<Vec<f64>>>())
+        {
+            Some(Some(v)) if v.len() == 3 => Ok(Moment::Noncollinear([v[0], v[1], v[2]])),
+            _ => Err(eyre::eyre!(
+                "Magnetic moment should be a number or a 3-vector: {}",
+                value
+            )),
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        match self {
+            Moment::Collinear(m) => *m == 0.0,
+            Moment::Noncollinear(m) => m.iter().all(|&x| x == 0.0),
+        }
+    }
+
+    fn flipped(&self) -> Moment {
+        match self {
+            Moment::Collinear(m) => Moment::Collinear(-m),
+            Moment::Noncollinear(m) => Moment::Noncollinear(m.map(|x| -x)),
+        }
+    }
+
+    fn components(&self) -> Vec<f64> {
+        match self {
+            Moment::Collinear(m) => vec![*m],
+            Moment::Noncollinear(m) => m.to_vec(),
+        }
+    }
+}
+
+/// Compresses repeated values like VASP does, e.g. `4*5.0 2*-5.0 8*0.6`.
+pub fn compress_values(values: &[f64]) -> String {
+    let mut groups: Vec<(usize, f64)> = vec![];
+    for &value in values {
+        match groups.last_mut() {
+            Some((count, last)) if *last == value => *count += 1,
+            _ => groups.push((1, value)),
+        }
+    }
+    groups
+        .iter()
+        .map(|(count, value)| match count {
+            1 => format!("{:?}", value),
+            _ => format!("{}*{:?}", count, value),
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+impl JobConfig {
+    /// Magnetic moment of each atom of `poscar` from `[vasp.magnetism]`.
+    /// Returns `None` if `[vasp.magnetism]` does not exist.
+    ///
+    /// - `moments`: moment of each element, e.g. `{ Fe = 5.0, O = 0.6 }`
+    /// - `default`: moment of elements not in `moments` (default 0)
+    /// - `sites`: moment of each atom (1-based index), overriding `moments` and `order`
+    /// - `order`: "ferro" (default) or "afm-layer", which flips moments of alternating layers
+    ///   of magnetic atoms along z
+    pub fn magnetic_moments(&self, poscar: &Poscar) -> Result<Option<Vec<Moment>>> {
+        let magnetism = match self.toml_contents["vasp"].get("magnetism") {
+            Some(magnetism) => magnetism,
+            None => return Ok(None),
+        };
+        let species = atom_species(poscar);
+        let default = match magnetism.get("default") {
+            Some(default) => Moment::from_value(default)?,
+            None => Moment::Collinear(0.0),
+        };
+        let mut moments = vec![default; species.len()];
+        if let Some(element_moments) = magnetism.get("moments").and_then(|m| m.as_table()) {
+            for (element, moment) in element_moments {
+                if !species.contains(element) {
+                    return Err(eyre::eyre!("Element {} in [vasp.magnetism] is not in POSCAR", element));
+                }
+                let moment = Moment::from_value(moment)?;
+                for (m, s) in moments.iter_mut().zip(&species) {
+                    if s == element {
+                        *m = moment;
+                    }
+                }
+            }
+        }
+        // Moments of sites are given explicitly, so they are set after the magnetic order.
+        let mut site_moments = vec![];
+        if let Some(sites) = magnetism.get("sites").and_then(|s| s.as_table()) {
+            for (index, moment) in sites {
+                let i = match index.parse::<usize>() {
+                    Ok(i) if i >= 1 && i <= species.len() => i - 1,
+                    _ => {
+                        return Err(eyre::eyre!(
+                            "Invalid site index {} in [vasp.magnetism]. POSCAR has {} atoms",
+                            index,
+                            species.len()
+                        ))
+                    }
+                };
+                site_moments.push((i, Moment::from_value(moment)?));
+            }
+        }
+
+        match magnetism.get("order").and_then(|o| o.as_str()).unwrap_or("ferro") {
+            "ferro" => (),
+            "afm-layer" => {
+                let tolerance = magnetism
+                    .get("layer_tolerance")
+                    .and_then(|t| t.as_float())
+                    .unwrap_or(0.5);
+                let magnetic: Vec<usize> = (0..moments.len()).filter(|&i| !moments[i].is_zero()).collect();
+                let positions = cartesian_coords(poscar);
+                let magnetic_positions: Vec<[f64; 3]> = magnetic.iter().map(|&i| positions[i]).collect();
+                let layers = layer_indices(&magnetic_positions, tolerance);
+                for (&i, layer) in magnetic.iter().zip(layers) {
+                    if layer % 2 == 1 {
+                        moments[i] = moments[i].flipped();
+                    }
+                }
+            }
+            order => {
+                return Err(eyre::eyre!(
+                    "Unknown magnetic order '{}'. Use 'ferro' or 'afm-layer'",
+                    order
+                ))
+            }
+        }
+
+        for (i, moment) in site_moments {
+            moments[i] = moment;
+        }
+
+        let noncollinear = moments.iter().any(|m| matches!(m, Moment::Noncollinear(_)));
+        if noncollinear {
+            // Collinear moments are along z in noncollinear calculations.
+            for moment in moments.iter_mut() {
+                if let Moment::Collinear(m) = moment {
+                    *moment = Moment::Noncollinear([0.0, 0.0, *m]);
+                }
+            }
+        }
+        Ok(Some(moments))
+    }
+
+    /// MAGMOM (and LNONCOLLINEAR for noncollinear moments) from `[vasp.magnetism]`.
+    pub fn magnetism_tags(&self) -> Result<Vec<IncarTag>> {
+        if self.toml_contents["vasp"].get("magnetism").is_none() {
+            return Ok(vec![]);
+        }
+        let poscar = Poscar::from_file(self.structure_file())?;
+        let moments = match self.magnetic_moments(&poscar)? {
+            Some(moments) => moments,
+            None => return Ok(vec![]),
+        };
+        let values: Vec<f64> = moments.iter().flat_map(|m| m.components()).collect();
+        let mut tags = vec![IncarTag {
+            name: String::from("MAGMOM"),
+            value: compress_values(&values),
+        }];
+        if matches!(moments.first(), Some(Moment::Noncollinear(_))) {
+            if !self.toml_contents["vasp"]["bin"].as_str().unwrap().contains("ncl") {
+                eprintln!("Warning: noncollinear MAGMOM requires noncollinear binary, e.g. vasp_ncl.");
+            }
+            tags.push(IncarTag {
+                name: String::from("LNONCOLLINEAR"),
+                value: String::from(".TRUE."),
+            });
+        }
+        Ok(tags)
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_compress_values() {
+        use super::*;
+        assert_eq!(compress_values(&[5.0, 5.0, -5.0, 0.6, 0.6, 0.6]), "2*5.0 -5.0 3*0.6");
+        assert_eq!(compress_values(&[1.0, 0.0, 1.0]), "1.0 0.0 1.0");
+        assert_eq!(compress_values(&[]), "");
+    }
+
+    #[test]
+    fn test_afm_layer() {
+        use super::*;
+        // Fe layers at z = 0, 2, 4, 6 with O in between.
+        let poscar = Poscar {
+            comment: String::from("FeO"),
+            scale: 1.0,
+            lattice: [[3.0, 0.0, 0.0], [0.0, 3.0, 0.0], [0.0, 0.0, 8.0]],
+            species: vec![String::from("Fe"), String::from("O")],
+            num_atoms: vec![4, 1],
+            frac_coords: vec![
+                [0.0, 0.0, 0.0],
+                [0.0, 0.0, 0.25],
+                [0.0, 0.0, 0.5],
+                [0.0, 0.0, 0.75],
+                [0.5, 0.5, 0.125],
+            ],
+            selective_dynamics: None,
+        };
+        let job_config = |magnetism: &str| JobConfig {
+            toml_contents: toml::from_str(&format!("[vasp.magnetism]\n{}", magnetism)).unwrap(),
+            job_dir: String::from("."),
+        };
+        let collinear = |moments: Vec<Moment>| -> Vec<f64> { moments.iter().flat_map(|m| m.components()).collect() };
+
+        let afm = job_config("moments = { Fe = 4.0 }\norder = \"afm-layer\"");
+        let moments = afm.magnetic_moments(&poscar).unwrap().unwrap();
+        assert_eq!(collinear(moments), vec![4.0, -4.0, 4.0, -4.0, 0.0]);
+
+        // Moments of sites are kept as given, also on flipped layers.
+        let sites = job_config("moments = { Fe = 4.0 }\norder = \"afm-layer\"\nsites = { 2 = 3.0, 5 = 0.5 }");
+        let moments = sites.magnetic_moments(&poscar).unwrap().unwrap();
+        assert_eq!(collinear(moments), vec![4.0, 3.0, 4.0, -4.0, 0.5]);
+
+        let noncollinear = job_config("moments = { Fe = [1.0, 0.0, 0.0], O = 0.0 }\norder = \"afm-layer\"");
+        let moments = noncollinear.magnetic_moments(&poscar).unwrap().unwrap();
+        assert_eq!(moments[1], Moment::Noncollinear([-1.0, 0.0, 0.0]));
+        assert_eq!(moments[2], Moment::Noncollinear([1.0, 0.0, 0.0]));
+        assert_eq!(moments[4], Moment::Noncollinear([0.0, 0.0, 0.0]));
+
+        assert!(job_config("sites = { 6 = 1.0 }").magnetic_moments(&poscar).is_err());
+    }
+}
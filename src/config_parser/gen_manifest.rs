@@ -0,0 +1,419 @@
+use super::config::{format_value, JobConfig};
+use crate::check_util::resolve_vasp_bin;
+use crate::job_db::{format_time, now};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
+use toml::Value;
+
+/// Manifest written by `create_job`, recording how the job directory was produced.
+pub const MANIFEST_FILE: &str = "vasp_manager.lock";
+
+/// Input files recorded in the manifest, in the job directory and in directories of its sub-jobs.
+const MANIFEST_FILES: [&str; 8] = [
+    "POSCAR",
+    "INCAR",
+    "KPOINTS",
+    "POTCAR",
+    "job_script.sh",
+    "SPOSCAR",
+    "MODECAR",
+    "ICONST",
+];
+
+/// VASP binary run by the job script.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VaspBinary {
+    pub version: String,
+    pub bin: String,
+    /// Absolute path of the binary, or its name on PATH after `load`.
+    pub path: String,
+    /// Command making the binary available, e.g. `module load vasp/6.4.2`.
+    pub load: Option<String>,
+    /// SHA-256 of the binary, unset if it was not found or is loaded as a module.
+    pub sha256: Option<String>,
+}
+
+/// Contents of `vasp_manager.lock`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Version of vasp_manager which created the job.
+    pub vasp_manager_version: String,
+    /// Creation time in UTC.
+    pub created: String,
+    /// TITEL of each potential in POTCAR.
+    pub potcar_titels: Vec<String>,
+    pub vasp: VaspBinary,
+    /// SHA-256 of the input files, by path relative to the job directory.
+    pub files: BTreeMap<String, String>,
+    /// Vasp.toml as used to create the job, with the defaults of omitted settings filled in.
+    pub config: Value,
+}
+
+impl JobConfig {
+    /// Builds the manifest of the job from the files in the job directory.
+    pub fn manifest(&self) -> Result<Manifest> {
+        let job_dir = Path::new(&self.job_dir);
+        let mut names: Vec<String> = MANIFEST_FILES.iter().map(|f| f.to_string()).collect();
+        if let Some(pre_step) = self.pre_step() {
+            names.push(format!("INCAR.{}", pre_step.name));
+            names.push(format!("KPOINTS.{}", pre_step.name));
+        }
+        // Sub-jobs (displacements, strains, NEB images) are directories with POSCAR but without Vasp.toml.
+        let mut dirs: Vec<String> = std::fs::read_dir(job_dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir() && path.join("POSCAR").exists() && !path.join("Vasp.toml").exists())
+            .map(|path| path.file_name().unwrap().to_str().unwrap().to_string())
+            .collect();
+        dirs.sort();
+
+        let mut files = BTreeMap::new();
+        for file in names.iter().cloned().chain(
+            dirs.iter()
+                .flat_map(|dir| MANIFEST_FILES.iter().map(move |f| format!("{}/{}", dir, f))),
+        ) {
+            let path = job_dir.join(&file);
+            if path.is_file() {
+                files.insert(file, sha256_file(&path)?);
+            }
+        }
+        let potcar = std::iter::once(job_dir.join("POTCAR"))
+            .chain(dirs.iter().map(|dir| job_dir.join(dir).join("POTCAR")))
+            .find(|path| path.exists());
+        let potcar_titels = match potcar {
+            Some(path) => potcar_titels(&path)?,
+            None => vec![],
+        };
+
+        Ok(Manifest {
+            vasp_manager_version: env!("CARGO_PKG_VERSION").to_string(),
+            created: format_time(now()),
+            potcar_titels,
+            vasp: self.vasp_binary()?,
+            files,
+            config: self.effective_config()?,
+        })
+    }
+
+    /// Writes `vasp_manager.lock` in the job directory.
+    pub fn write_manifest(&self) -> Result<()> {
+        let manifest = self.manifest()?;
+        let contents = format!(
+            "# Written by vasp_manager create_job. Check the job directory against it with `vasp_manager verify`.\n{}",
+            toml::to_string(&manifest)?
+        );
+        std::fs::write(Path::new(&self.job_dir).join(MANIFEST_FILE), contents)?;
+        Ok(())
+    }
+
+    /// VASP binary of `version` and `bin` in `[vasp]`, with its hash if it is a file.
+    pub fn vasp_binary(&self) -> Result<VaspBinary> {
+        let vasp_config = &self.toml_contents["vasp"];
+        let version = format_value(&vasp_config["version"]);
+        let bin = format_value(&vasp_config["bin"]);
+        let resolved = resolve_vasp_bin(&version, &bin);
+        let path = Path::new(&resolved.executable);
+        let sha256 = match resolved.load.is_none() && path.is_file() {
+            true => Some(sha256_file(path)?),
+            false => None,
+        };
+        Ok(VaspBinary {
+            version,
+            bin,
+            path: resolved.executable,
+            load: resolved.load,
+            sha256,
+        })
+    }
+
+    /// Configuration with the defaults applied in code filled in for the settings of the task,
+    /// so that the manifest records the values actually used.
+    fn effective_config(&self) -> Result<Value> {
+        let mut config = self.unresolved_config();
+        let vasp_config = &self.toml_contents["vasp"];
+        let pre_converge = vasp_config["incar"]
+            .get("pre_converge")
+            .and_then(|p| p.as_bool())
+            .unwrap_or(true);
+        insert_default(
+            &mut config,
+            &["vasp", "incar"],
+            "pre_converge",
+            Value::Boolean(pre_converge),
+        );
+
+        let floats = |values: &[f64]| Value::Array(values.iter().map(|&v| Value::Float(v)).collect());
+        match self.incar_base().as_str() {
+            "eos" => {
+                let eos = self.eos()?;
+                insert_default(&mut config, &["vasp", "eos"], "volume_range", floats(&eos.volume_range));
+                insert_default(
+                    &mut config,
+                    &["vasp", "eos"],
+                    "num_points",
+                    Value::Integer(eos.num_points as i64),
+                );
+            }
+            "elastic" => {
+                let elastic = self.elastic()?;
+                insert_default(
+                    &mut config,
+                    &["vasp", "elastic"],
+                    "magnitudes",
+                    floats(&elastic.magnitudes),
+                );
+            }
+            "phonon" => {
+                let phonon = self.phonon()?;
+                insert_default(
+                    &mut config,
+                    &["vasp", "phonon"],
+                    "displacement",
+                    Value::Float(phonon.displacement),
+                );
+                insert_default(
+                    &mut config,
+                    &["vasp", "phonon"],
+                    "symprec",
+                    Value::Float(phonon.symprec),
+                );
+            }
+            "dos" if vasp_config.get("dos").and_then(|d| d.get("kpoints")).is_none() => {
+                // Validates kpoints_scale, which is 2 if omitted.
+                self.dos_kpoints_config()?;
+                insert_default(&mut config, &["vasp", "dos"], "kpoints_scale", Value::Integer(2));
+            }
+            _ => (),
+        }
+        if vasp_config.get("neb").is_some() {
+            insert_default(
+                &mut config,
+                &["vasp", "neb"],
+                "interpolation",
+                Value::String(String::from("idpp")),
+            );
+        }
+        if vasp_config.get("magnetism").is_some() {
+            insert_default(&mut config, &["vasp", "magnetism"], "default", Value::Float(0.0));
+            insert_default(
+                &mut config,
+                &["vasp", "magnetism"],
+                "order",
+                Value::String(String::from("ferro")),
+            );
+        }
+        Ok(config)
+    }
+
+    /// Configuration with structure files relative to the job directory again, as in Vasp.toml.
+    fn unresolved_config(&self) -> Value {
+        let mut toml_contents = self.toml_contents.clone();
+        let unresolve = |path: &mut Value| {
+            if let Value::String(path) = path {
+                if let Ok(relative) = Path::new(path.as_str()).strip_prefix(&self.job_dir) {
+                    *path = relative.to_str().unwrap().to_string();
+                }
+            }
+        };
+        if let Some(input) = toml_contents["vasp"].get_mut("input") {
+            unresolve(input);
+        }
+        if let Some(neb) = toml_contents["vasp"].get_mut("neb").and_then(|n| n.as_table_mut()) {
+            for key in ["initial", "final"] {
+                if let Some(path) = neb.get_mut(key) {
+                    unresolve(path);
+                }
+            }
+        }
+        toml_contents
+    }
+}
+
+impl Manifest {
+    /// Reads `vasp_manager.lock` in `dir`.
+    pub fn from_dir(dir: &str) -> Result<Manifest> {
+        let path = Path::new(dir).join(MANIFEST_FILE);
+        let contents = std::fs::read_to_string(&path).map_err(|_| {
+            eyre::eyre!(
+                "{} not found in {}. Was the job created by create_job?",
+                MANIFEST_FILE,
+                dir
+            )
+        })?;
+        toml::from_str(&contents).map_err(|e| eyre::eyre!("Could not parse {}: {}", path.display(), e))
+    }
+
+    /// Compares the job in `dir` with the manifest. Returns the differences, empty if nothing drifted.
+    pub fn verify(&self, dir: &str) -> Result<Vec<String>> {
+        let mut drifts = vec![];
+        let job_config = JobConfig::from_dir(dir)?;
+        diff_values("", &self.config, &job_config.effective_config()?, &mut drifts);
+
+        for (file, hash) in &self.files {
+            let path = Path::new(dir).join(file);
+            if !path.is_file() {
+                drifts.push(format!("{}: missing", file));
+            } else if &sha256_file(&path)? != hash {
+                drifts.push(format!("{}: modified", file));
+            }
+        }
+
+        let potcar = Path::new(dir).join("POTCAR");
+        if potcar.exists() {
+            let titels = potcar_titels(&potcar)?;
+            if titels != self.potcar_titels {
+                drifts.push(format!(
+                    "POTCAR: [{}] -> [{}]",
+                    self.potcar_titels.join(", "),
+                    titels.join(", ")
+                ));
+            }
+        }
+
+        let vasp = job_config.vasp_binary()?;
+        if vasp.path != self.vasp.path {
+            drifts.push(format!("VASP binary: {} -> {}", self.vasp.path, vasp.path));
+        } else if vasp.load != self.vasp.load {
+            let describe = |load: &Option<String>| load.clone().unwrap_or_else(|| String::from("(none)"));
+            drifts.push(format!(
+                "VASP binary loaded by: {} -> {}",
+                describe(&self.vasp.load),
+                describe(&vasp.load)
+            ));
+        } else if vasp.sha256 != self.vasp.sha256 {
+            match vasp.sha256 {
+                Some(_) => drifts.push(format!("VASP binary: {} was modified", vasp.path)),
+                None => drifts.push(format!("VASP binary: {} not found", vasp.path)),
+            }
+        }
+        Ok(drifts)
+    }
+}
+
+/// Inserts `key = value` in the table at `path` of `config` if the key is not set, creating the tables.
+fn insert_default(config: &mut Value, path: &[&str], key: &str, value: Value) {
+    let mut table = config;
+    for name in path {
+        table = match table {
+            Value::Table(t) => t
+                .entry(name.to_string())
+                .or_insert_with(|| Value::Table(toml::value::Table::new())),
+            _ => return,
+        };
+    }
+    if let Value::Table(t) = table {
+        t.entry(key.to_string()).or_insert(value);
+    }
+}
+
+/// Collects differences between two TOML values as `key: old -> new`.
+fn diff_values(key: &str, old: &Value, new: &Value, drifts: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Table(old), Value::Table(new)) => {
+            for (k, old_value) in old {
+                let sub_key = if key.is_empty() {
+                    k.clone()
+                } else {
+                    format!("{}.{}", key, k)
+                };
+                match new.get(k) {
+                    Some(new_value) => diff_values(&sub_key, old_value, new_value, drifts),
+                    None => drifts.push(format!("{}: {} -> (removed)", sub_key, old_value)),
+                }
+            }
+            for (k, new_value) in new {
+                if !old.contains_key(k) {
+                    let sub_key = if key.is_empty() {
+                        k.clone()
+                    } else {
+                        format!("{}.{}", key, k)
+                    };
+                    drifts.push(format!("{}: (added) -> {}", sub_key, new_value));
+                }
+            }
+        }
+        (old, new) if old != new => drifts.push(format!("{}: {} -> {}", key, old, new)),
+        _ => (),
+    }
+}
+
+/// Hex-encoded SHA-256 of the file.
+pub fn sha256_file<P: AsRef<Path>>(path: P) -> Result<String> {
+    let mut file = std::fs::File::open(&path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// TITEL of each potential in POTCAR, e.g. "PAW_PBE Fe 06Sep2000".
+pub fn potcar_titels<P: AsRef<Path>>(path: P) -> Result<Vec<String>> {
+    let contents = std::fs::read_to_string(&path)?;
+    Ok(contents
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("TITEL"))
+        .map(|rest| rest.trim_start().trim_start_matches('=').trim().to_string())
+        .collect())
+}
+
+mod tests {
+    #[test]
+    fn test_diff_values() {
+        use super::*;
+        let old: Value = toml::from_str(
+            "[vasp]\nversion = \"6.4.2\"\nbin = \"vasp_std\"\n[vasp.incar]\nbase = \"relax\"\n[vasp.incar.tags]\nENCUT = 520",
+        )
+        .unwrap();
+        let mut drifts = vec![];
+        diff_values("", &old, &old, &mut drifts);
+        assert!(drifts.is_empty());
+
+        let new: Value = toml::from_str(
+            "[vasp]\nversion = \"6.4.2\"\n[vasp.incar]\nbase = \"relax\"\n[vasp.incar.tags]\nENCUT = 600\nISPIN = 2",
+        )
+        .unwrap();
+        diff_values("", &old, &new, &mut drifts);
+        assert_eq!(
+            drifts,
+            vec![
+                "vasp.bin: \"vasp_std\" -> (removed)",
+                "vasp.incar.tags.ENCUT: 520 -> 600",
+                "vasp.incar.tags.ISPIN: (added) -> 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_insert_default() {
+        use super::*;
+        let mut config: Value = toml::from_str("[vasp.eos]\nnum_points = 9").unwrap();
+        insert_default(&mut config, &["vasp", "eos"], "num_points", Value::Integer(7));
+        insert_default(&mut config, &["vasp", "phonon"], "symprec", Value::Float(1e-3));
+        assert_eq!(config["vasp"]["eos"]["num_points"].as_integer(), Some(9));
+        assert_eq!(config["vasp"]["phonon"]["symprec"].as_float(), Some(1e-3));
+    }
+
+    #[test]
+    fn test_potcar_titels() {
+        use super::*;
+        let contents = "  PAW_PBE Na_pv 19Sep2006
+ 5.00000000000000000
+ parameters from PSCTR are:
+   VRHFIN =Na: 2p3s
+   TITEL  = PAW_PBE Na_pv 19Sep2006
+   POMASS =   22.990; ZVAL   =    7.000    mass and valenz
+ End of Dataset
+  PAW_PBE Cl 06Sep2000
+ 7.00000000000000000
+   TITEL  = PAW_PBE Cl 06Sep2000
+ End of Dataset
+";
+        let path = std::env::temp_dir().join(format!("vasp_manager_test_potcar_{}", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        let titels = potcar_titels(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(titels, vec!["PAW_PBE Na_pv 19Sep2006", "PAW_PBE Cl 06Sep2000"]);
+    }
+}
@@ -0,0 +1,43 @@
+use super::config::JobConfig;
+use eyre::Result;
+use std::path::Path;
+
+/// Input file of each ML force field stage, if any.
+const MLFF_INPUTS: [(&str, &str); 2] = [("mlff_refit", "ML_AB"), ("mlff_run", "ML_FF")];
+
+/// Files carried over to the next ML force field stage, with the files they are copied from in order of preference.
+/// Training writes ML_ABN and ML_FFN, and refitting writes ML_FFN.
+const MLFF_CARRY_OVER: [(&str, [&str; 2]); 2] = [("ML_AB", ["ML_ABN", "ML_AB"]), ("ML_FF", ["ML_FFN", "ML_FF"])];
+
+impl JobConfig {
+    /// Warns if the input of the ML force field stage is missing in the job directory.
+    pub fn check_mlff_input(&self) {
+        let base = self.incar_base();
+        if let Some((_, input)) = MLFF_INPUTS.iter().find(|(task, _)| *task == base) {
+            if !Path::new(&self.job_dir).join(input).exists() {
+                eprintln!(
+                    "Warning: {} requires {} in {}. Use `continue_job` from the previous stage to copy it.",
+                    base, input, self.job_dir
+                );
+            }
+        }
+    }
+}
+
+/// Copies ML_ABN (or ML_AB) and ML_FFN (or ML_FF) in `src_dir` to ML_AB and ML_FF in `dest_dir`,
+/// so that the next stage of ML force field continues from them. Returns the copied files and their destinations.
+pub fn carry_over_ml_files(src_dir: &str, dest_dir: &str) -> Result<Vec<(String, String)>> {
+    let mut copied = vec![];
+    for (dest, sources) in MLFF_CARRY_OVER {
+        let source = sources.iter().map(|s| Path::new(src_dir).join(s)).find(|s| {
+            // Empty files are left by stages not writing them.
+            std::fs::metadata(s).is_ok_and(|m| m.len() > 0)
+        });
+        if let Some(source) = source {
+            let dest = Path::new(dest_dir).join(dest);
+            std::fs::copy(&source, &dest)?;
+            copied.push((source.display().to_string(), dest.display().to_string()));
+        }
+    }
+    Ok(copied)
+}
@@ -0,0 +1,124 @@
+use super::config::JobConfig;
+use crate::structure_io::read_structure;
+use crate::structure_util::{check_atom_ordering, interpolate_idpp, interpolate_linear};
+use eyre::Result;
+use std::path::Path;
+
+/// Method to interpolate NEB images between the endpoints.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    Idpp,
+}
+
+/// NEB settings in `[vasp.neb]` section of Vasp.toml.
+#[derive(Debug, Clone)]
+pub struct NebConfig {
+    pub initial: String,
+    pub final_: String,
+    /// Number of intermediate images.
+    pub images: usize,
+    pub interpolation: Interpolation,
+}
+
+impl JobConfig {
+    /// Parses `[vasp.neb]` section. Returns `None` if it does not exist.
+    pub fn neb(&self) -> Result<Option<NebConfig>> {
+        let neb_config = match self.toml_contents["vasp"].get("neb") {
+            Some(neb_config) => neb_config,
+            None => return Ok(None),
+        };
+        let path = |key: &str| -> Result<String> {
+            neb_config
+                .get(key)
+                .and_then(|p| p.as_str())
+                .map(String::from)
+                .ok_or_else(|| eyre::eyre!("Specify {} structure of NEB in [vasp.neb]", key))
+        };
+        let images = match neb_config.get("images").and_then(|n| n.as_integer()) {
+            Some(n) if n > 0 => n as usize,
+            _ => return Err(eyre::eyre!("Specify positive number of images in [vasp.neb]")),
+        };
+        let interpolation = match neb_config.get("interpolation").and_then(|i| i.as_str()) {
+            None | Some("idpp") => Interpolation::Idpp,
+            Some("linear") => Interpolation::Linear,
+            Some(i) => return Err(eyre::eyre!("Unknown interpolation '{}'. Use 'linear' or 'idpp'", i)),
+        };
+        Ok(Some(NebConfig {
+            initial: path("initial")?,
+            final_: path("final")?,
+            images,
+            interpolation,
+        }))
+    }
+
+    /// Writes NEB job: endpoints and interpolated images in `00`, `01`, ... directories,
+    /// and POTCAR and KPOINTS from the initial structure.
+    /// Endpoints may be given in any structure format supported as input.
+    pub fn create_neb_job(&self) -> Result<()> {
+        let neb = self
+            .neb()?
+            .ok_or_else(|| eyre::eyre!("[vasp.neb] section is required for NEB job"))?;
+        let num_tasks = match self.toml_contents.get("slurm").and_then(|s| s.get("num_tasks")) {
+            Some(n) => n
+                .as_integer()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| eyre::eyre!("num_tasks in [slurm] should be a positive integer"))?
+                as usize,
+            None => return Err(eyre::eyre!("Specify num_tasks in [slurm] for NEB job")),
+        };
+        if !num_tasks.is_multiple_of(neb.images) {
+            return Err(eyre::eyre!(
+                "num_tasks ({}) should be a multiple of the number of images ({})",
+                num_tasks,
+                neb.images
+            ));
+        }
+        if !self.toml_contents["vasp"]["bin"].as_str().unwrap().contains("vtst") {
+            eprintln!("Warning: INCAR_neb uses IOPT of VTST. Consider using VTST binary, e.g. vasp_vtst_std.");
+        }
+
+        let mut initial = read_structure(&neb.initial)?;
+        let mut final_ = read_structure(&neb.final_)?;
+        if let Some(num_fixed) = self.apply_constraints(&mut initial)? {
+            self.apply_constraints(&mut final_)?;
+            println!("Fixing {} of {} atoms", num_fixed, initial.frac_coords.len());
+        }
+        check_atom_ordering(&initial, &final_)?;
+        let images = match neb.interpolation {
+            Interpolation::Linear => interpolate_linear(&initial, &final_, neb.images),
+            Interpolation::Idpp => interpolate_idpp(&initial, &final_, neb.images),
+        };
+
+        println!("Creating NEB job in {} with {} images", self.job_dir, neb.images);
+        std::fs::create_dir_all(&self.job_dir)?;
+        println!("Writing images");
+        let structures = std::iter::once(&initial).chain(&images).chain(std::iter::once(&final_));
+        for (n, structure) in structures.enumerate() {
+            let image_dir = Path::new(&self.job_dir).join(format!("{:02}", n));
+            std::fs::create_dir_all(&image_dir)?;
+            structure.to_file(image_dir.join("POSCAR"))?;
+        }
+        println!("Writing POTCAR");
+        self.write_potcar()?;
+        println!("Writing KPOINTS");
+        self.write_kpoints()?;
+        println!("Writing INCAR");
+        self.write_incar()?;
+        println!("Writing Job script");
+        self.write_job_script()?;
+
+        // Endpoint OUTCARs are used by VTST scripts such as nebbarrier.pl.
+        let endpoints = [(&neb.initial, 0), (&neb.final_, neb.images + 1)];
+        for (structure_file, n) in endpoints {
+            let outcar = Path::new(structure_file).with_file_name("OUTCAR");
+            if outcar.exists() {
+                std::fs::copy(
+                    outcar,
+                    Path::new(&self.job_dir).join(format!("{:02}", n)).join("OUTCAR"),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
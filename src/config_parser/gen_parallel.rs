@@ -0,0 +1,289 @@
+use super::config::{format_value, JobConfig};
+use super::gen_kpoints::kpoints_mesh;
+use super::gen_workflow::PreStep;
+use crate::template::{template_source, IncarTag};
+use eyre::Result;
+use vasp_files::poscar::Poscar;
+
+/// Minimum number of MPI ranks working on a k-point group.
+const MIN_RANKS_PER_KPOINT_GROUP: usize = 8;
+
+/// Largest order of a point group including time reversal, that of the cubic lattice.
+const MAX_POINT_GROUP_ORDER: usize = 48;
+
+/// Estimates the number of irreducible k-points from KPOINTS and ISYM of INCAR.
+/// Without symmetry (ISYM = 0) only time-reversal symmetry reduces automatic meshes, which gives the exact count.
+/// With symmetry the count depends on the point group of the structure, of which `point_group_order` is an upper
+/// bound (e.g. that of the lattice). A lower bound is returned to keep KPAR from exceeding the number of k-points.
+pub fn num_irreducible_kpoints(kpoints: &str, isym: i64, point_group_order: usize) -> Result<usize> {
+    let lines: Vec<&str> = kpoints.lines().collect();
+    let invalid = || eyre::eyre!("Could not read KPOINTS");
+    let num_kpoints: usize = lines.get(1).and_then(|l| l.trim().parse().ok()).ok_or_else(invalid)?;
+    if num_kpoints > 0 {
+        // Explicit list of k-points.
+        return Ok(num_kpoints);
+    }
+    let gamma_centered = lines
+        .get(2)
+        .ok_or_else(invalid)?
+        .trim()
+        .to_ascii_lowercase()
+        .starts_with('g');
+    let mesh = kpoints_mesh(kpoints).ok_or_else(invalid)?;
+    let total: usize = mesh.iter().product();
+    if isym < 0 {
+        return Ok(total);
+    }
+    // k-points equal to their own inverse (0 or 1/2 along each direction) are not paired by time reversal.
+    let self_inverse: usize = mesh
+        .iter()
+        .map(|&n| match (n % 2 == 0, gamma_centered) {
+            (true, true) => 2,
+            (true, false) => 0,
+            (false, _) => 1,
+        })
+        .product();
+    let time_reversal = (total + self_inverse) / 2;
+    match isym {
+        0 => Ok(time_reversal),
+        // Time reversal plays the role of inversion, which every lattice point group contains.
+        _ => Ok(time_reversal.div_ceil((point_group_order / 2).max(1))),
+    }
+}
+
+/// Order of the point group of the lattice, an upper bound of that of the structure.
+/// Rotations are searched among integer matrices with entries of -1, 0 and 1, which covers reduced cells.
+pub fn lattice_point_group_order(lattice: [[f64; 3]; 3]) -> usize {
+    let metric: Vec<Vec<f64>> = (0..3)
+        .map(|i| {
+            (0..3)
+                .map(|j| (0..3).map(|k| lattice[i][k] * lattice[j][k]).sum())
+                .collect()
+        })
+        .collect();
+    let tolerance = 1e-3 * (0..3).map(|i| metric[i][i]).fold(0.0, f64::max);
+    let mut order = 0;
+    for index in 0..3usize.pow(9) {
+        let rotation: Vec<f64> = (0..9).map(|k| (index / 3usize.pow(k)) as f64 % 3.0 - 1.0).collect();
+        let r = |i: usize, j: usize| rotation[3 * i + j];
+        let det = r(0, 0) * (r(1, 1) * r(2, 2) - r(1, 2) * r(2, 1)) - r(0, 1) * (r(1, 0) * r(2, 2) - r(1, 2) * r(2, 0))
+            + r(0, 2) * (r(1, 0) * r(2, 1) - r(1, 1) * r(2, 0));
+        if det.abs() != 1.0 {
+            continue;
+        }
+        // The rotated lattice vectors keep their lengths and angles.
+        let preserved = (0..3).all(|i| {
+            (0..3).all(|j| {
+                let rotated: f64 = (0..3)
+                    .flat_map(|k| (0..3).map(move |l| (k, l)))
+                    .map(|(k, l)| r(i, k) * metric[k][l] * r(j, l))
+                    .sum();
+                (rotated - metric[i][j]).abs() < tolerance
+            })
+        });
+        if preserved {
+            order += 1;
+        }
+    }
+    order
+}
+
+fn divisors(n: usize) -> Vec<usize> {
+    (1..=n).filter(|d| n.is_multiple_of(*d)).collect()
+}
+
+/// Chooses KPAR and NCORE for `num_tasks` MPI ranks with `cores_per_node` cores on each node.
+/// KPAR is the largest divisor of `num_tasks` not exceeding the number of k-points, keeping enough ranks in
+/// each k-point group. NCORE is the divisor of the group size and cores per node closest to sqrt(cores per node).
+pub fn choose_parallelization(num_tasks: usize, cores_per_node: usize, num_kpoints: usize) -> (usize, usize) {
+    let kpar = divisors(num_tasks)
+        .into_iter()
+        .filter(|&k| k <= num_kpoints && (k == 1 || num_tasks / k >= MIN_RANKS_PER_KPOINT_GROUP))
+        .max()
+        .unwrap_or(1);
+    let group_size = num_tasks / kpar;
+    let target = (cores_per_node as f64).sqrt();
+    let ncore = divisors(group_size)
+        .into_iter()
+        .filter(|&n| cores_per_node.is_multiple_of(n))
+        .min_by(|&a, &b| {
+            let distance = |n: usize| (n as f64 - target).abs();
+            distance(a).partial_cmp(&distance(b)).unwrap().then(b.cmp(&a))
+        })
+        .unwrap_or(1);
+    (kpar, ncore)
+}
+
+impl JobConfig {
+    /// KPAR and NCORE if `parallel = "auto"` in `[vasp]`, for the k-points of `pre_step`, or of the job if `None`.
+    pub fn parallel_tags(&self, pre_step: Option<&PreStep>) -> Result<Vec<IncarTag>> {
+        match self.toml_contents["vasp"].get("parallel").and_then(|p| p.as_str()) {
+            Some("auto") => (),
+            Some(p) => return Err(eyre::eyre!("Unknown parallel option '{}'. Use 'auto'", p)),
+            None => return Ok(vec![]),
+        }
+        let slurm_config = &self.toml_contents["slurm"];
+        let num_nodes = slurm_config["num_nodes"].as_integer().unwrap_or(1).max(1) as usize;
+        let mut num_tasks = slurm_config["num_tasks"].as_integer().unwrap_or(1).max(1) as usize;
+        if !num_tasks.is_multiple_of(num_nodes) {
+            eprintln!(
+                "Warning: num_tasks ({}) is not divisible by num_nodes ({}). NCORE may span nodes.",
+                num_tasks, num_nodes
+            );
+        }
+        let cores_per_node = (num_tasks / num_nodes).max(1);
+        // Images of NEB run in parallel, each with its share of ranks.
+        if let Some(neb) = self.neb()?.filter(|_| self.incar_base() == "neb") {
+            num_tasks /= neb.images;
+        }
+
+        let bin = self.toml_contents["vasp"]["bin"].as_str().unwrap();
+        let isym = self.isym();
+        let num_kpoints = match bin.contains("gam") {
+            true => 1,
+            false => {
+                // Without a readable structure, any point group is assumed.
+                let point_group_order = match isym > 0 {
+                    true => Poscar::from_file(self.structure_file())
+                        .map(|poscar| lattice_point_group_order(poscar.lattice))
+                        .unwrap_or(MAX_POINT_GROUP_ORDER),
+                    false => MAX_POINT_GROUP_ORDER,
+                };
+                num_irreducible_kpoints(&self.step_kpoints_contents(pre_step)?, isym, point_group_order)?
+            }
+        };
+        let (kpar, ncore) = choose_parallelization(num_tasks, cores_per_node, num_kpoints);
+        if ncore == 1 && cores_per_node > 1 {
+            eprintln!(
+                "Warning: No suitable NCORE for {} ranks per k-point group. Consider num_tasks with more divisors.",
+                num_tasks / kpar
+            );
+        }
+        println!(
+            "Parallelization{}: KPAR = {}, NCORE = {} ({} ranks, {}{} irreducible k-points)",
+            pre_step.map(|p| format!(" of {} step", p.name)).unwrap_or_default(),
+            kpar,
+            ncore,
+            num_tasks,
+            if isym > 0 && !bin.contains("gam") {
+                "at least "
+            } else {
+                ""
+            },
+            num_kpoints
+        );
+        let tag = |name: &str, value: usize| IncarTag {
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+        Ok(vec![tag("KPAR", kpar), tag("NCORE", ncore)])
+    }
+
+    /// ISYM of the INCAR, from the additional tags or the template. VASP uses symmetry (ISYM = 2) if unset.
+    pub fn isym(&self) -> i64 {
+        let additional = self.toml_contents["vasp"]["incar"]
+            .get("tags")
+            .and_then(|tags| tags.as_table())
+            .and_then(|tags| tags.iter().find(|(name, _)| name.eq_ignore_ascii_case("ISYM")))
+            .map(|(_, value)| format_value(value));
+        let template = || {
+            template_source(&format!("INCAR_{}", self.incar_base()))?
+                .lines()
+                .filter_map(|line| line.split_once('='))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("ISYM"))
+                .and_then(|(_, value)| value.split_whitespace().next())
+                .map(|value| value.to_string())
+        };
+        additional
+            .or_else(template)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(2)
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_num_irreducible_kpoints() {
+        use super::*;
+        let gamma = "Automatic mesh\n0\nGamma\n4 4 3\n0 0 0\n";
+        let monkhorst = "Automatic mesh\n0\nMonkhorst-Pack\n4 4 4\n0 0 0\n";
+        // 48 k-points, of which 2 * 2 * 1 are their own inverse.
+        assert_eq!(num_irreducible_kpoints(gamma, 0, 48).unwrap(), 26);
+        assert_eq!(num_irreducible_kpoints(monkhorst, 0, 48).unwrap(), 32);
+        assert_eq!(num_irreducible_kpoints(gamma, -1, 48).unwrap(), 48);
+        assert_eq!(num_irreducible_kpoints(gamma, 2, 48).unwrap(), 2);
+        assert_eq!(num_irreducible_kpoints(gamma, 2, 8).unwrap(), 7);
+        assert_eq!(num_irreducible_kpoints(gamma, 2, 2).unwrap(), 26);
+        assert_eq!(num_irreducible_kpoints(monkhorst, 2, 16).unwrap(), 4);
+        let explicit = "Explicit\n3\nReciprocal\n0 0 0 1\n0.5 0 0 1\n0.5 0.5 0 1\n";
+        assert_eq!(num_irreducible_kpoints(explicit, 2, 48).unwrap(), 3);
+        assert!(num_irreducible_kpoints("Automatic mesh\n", 0, 48).is_err());
+    }
+
+    #[test]
+    fn test_lattice_point_group_order() {
+        use super::*;
+        let cubic = [[5.43, 0.0, 0.0], [0.0, 5.43, 0.0], [0.0, 0.0, 5.43]];
+        assert_eq!(lattice_point_group_order(cubic), 48);
+        let fcc = [[0.0, 2.715, 2.715], [2.715, 0.0, 2.715], [2.715, 2.715, 0.0]];
+        assert_eq!(lattice_point_group_order(fcc), 48);
+        let hexagonal = [[3.2, 0.0, 0.0], [-1.6, 2.771281, 0.0], [0.0, 0.0, 5.2]];
+        assert_eq!(lattice_point_group_order(hexagonal), 24);
+        let tetragonal = [[4.0, 0.0, 0.0], [0.0, 4.0, 0.0], [0.0, 0.0, 6.0]];
+        assert_eq!(lattice_point_group_order(tetragonal), 16);
+        let orthorhombic = [[4.0, 0.0, 0.0], [0.0, 5.0, 0.0], [0.0, 0.0, 6.0]];
+        assert_eq!(lattice_point_group_order(orthorhombic), 8);
+        let triclinic = [[4.0, 0.0, 0.0], [0.7, 5.0, 0.0], [0.9, 1.1, 6.0]];
+        assert_eq!(lattice_point_group_order(triclinic), 2);
+    }
+
+    #[test]
+    fn test_choose_parallelization() {
+        use super::*;
+        // KPAR up to the number of k-points, with at least 8 ranks per group.
+        assert_eq!(choose_parallelization(128, 64, 26), (16, 8));
+        assert_eq!(choose_parallelization(128, 64, 10), (8, 8));
+        assert_eq!(choose_parallelization(64, 64, 1), (1, 8));
+        assert_eq!(choose_parallelization(24, 24, 100), (3, 4));
+        // No divisor of a prime number of ranks fits.
+        assert_eq!(choose_parallelization(7, 7, 4), (1, 1));
+    }
+
+    #[test]
+    fn test_parallel_tags() {
+        use super::*;
+        let job_dir = std::env::temp_dir().join(format!("vasp_manager_test_parallel_{}", std::process::id()));
+        std::fs::create_dir_all(&job_dir).unwrap();
+        std::fs::write(
+            job_dir.join("POSCAR"),
+            "orthorhombic\n1.0\n4.0 0.0 0.0\n0.0 5.0 0.0\n0.0 0.0 6.0\nSi\n1\nDirect\n0.0 0.0 0.0\n",
+        )
+        .unwrap();
+        let job_config = JobConfig {
+            toml_contents: toml::from_str(
+                r#"
+                [slurm]
+                num_nodes = 1
+                num_tasks = 64
+
+                [vasp]
+                bin = "vasp_std"
+                parallel = "auto"
+                kpoints = { scheme = "Gamma", mesh = [2, 2, 2] }
+
+                [vasp.incar]
+                base = "dos"
+                "#,
+            )
+            .unwrap(),
+            job_dir: job_dir.to_str().unwrap().to_string(),
+        };
+        let kpar = |tags: Vec<IncarTag>| tags.into_iter().find(|t| t.name == "KPAR").unwrap().value;
+        // Without KPOINTS written, the non-SCF 4x4x4 mesh has at least 36 / 4 irreducible k-points,
+        // and the 2x2x2 mesh of the SCF step at least 8 / 4.
+        assert_eq!(kpar(job_config.parallel_tags(None).unwrap()), "8");
+        let pre_step = job_config.pre_step().unwrap();
+        assert_eq!(kpar(job_config.parallel_tags(Some(&pre_step)).unwrap()), "2");
+        std::fs::remove_dir_all(&job_dir).unwrap();
+    }
+}
@@ -0,0 +1,215 @@
+use super::config::JobConfig;
+use crate::structure_util::{
+    cart_to_frac, frac_to_cart, make_supercell, scaled_lattice, symmetry_image_indices, symmetry_operations,
+};
+use eyre::Result;
+use std::path::Path;
+use vasp_files::poscar::Poscar;
+
+/// Perfect supercell written in the phonon job directory, named as by phonopy.
+pub const PHONON_SUPERCELL: &str = "SPOSCAR";
+
+/// Phonon settings in `[vasp.phonon]` section of Vasp.toml.
+#[derive(Debug, Clone)]
+pub struct PhononConfig {
+    /// Diagonal of the supercell matrix, `DIM` of phonopy.
+    pub supercell: [usize; 3],
+    /// Displacement length (Angstrom).
+    pub displacement: f64,
+    /// Tolerance (Angstrom) to find symmetry.
+    pub symprec: f64,
+}
+
+/// Displacement of a single atom in the supercell.
+#[derive(Debug, Clone)]
+pub struct Displacement {
+    /// 0-based index of the atom in the supercell.
+    pub atom: usize,
+    /// Cartesian displacement (Angstrom).
+    pub vector: [f64; 3],
+}
+
+impl JobConfig {
+    /// Parses `[vasp.phonon]` section.
+    pub fn phonon(&self) -> Result<PhononConfig> {
+        let phonon_config = self.toml_contents["vasp"]
+            .get("phonon")
+            .ok_or_else(|| eyre::eyre!("[vasp.phonon] section is required for phonon job"))?;
+        let supercell = phonon_config
+            .get("supercell")
+            .and_then(|s| s.as_array())
+            .map(|s| {
+                s.iter()
+                    .filter_map(|n| n.as_integer())
+                    .filter(|&n| n > 0)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|s| s.len() == 3)
+            .ok_or_else(|| eyre::eyre!("Specify supercell = [n1, n2, n3] of positive integers in [vasp.phonon]"))?;
+        let float = |key: &str, default: f64| -> f64 {
+            match phonon_config.get(key) {
+                Some(toml::Value::Integer(x)) => *x as f64,
+                Some(x) => x.as_float().unwrap_or(default),
+                None => default,
+            }
+        };
+        Ok(PhononConfig {
+            supercell: [supercell[0] as usize, supercell[1] as usize, supercell[2] as usize],
+            displacement: float("displacement", 0.01),
+            symprec: float("symprec", 1e-3),
+        })
+    }
+
+    /// Symmetry-reduced displacements of the supercell of `unitcell`, following phonopy.
+    /// Each symmetrically independent atom is displaced along the supercell lattice vectors until the images of the
+    /// displacements under its site symmetry span the space. A displacement in the opposite direction is added
+    /// if it is not equivalent by symmetry.
+    pub fn phonon_displacements(&self, unitcell: &Poscar) -> Result<Vec<Displacement>> {
+        let phonon = self.phonon()?;
+        let m = phonon.supercell;
+        let num_lattice_points = m.iter().product::<usize>();
+        // Operations of the unit cell which are also symmetries of the supercell, M^-1 R M being integer.
+        let operations: Vec<_> = symmetry_operations(unitcell, phonon.symprec)
+            .into_iter()
+            .filter(|op| (0..3).all(|i| (0..3).all(|j| (op.rotation[i][j] * m[j] as i32) % m[i] as i32 == 0)))
+            .collect();
+        let images: Vec<Vec<usize>> = operations
+            .iter()
+            .map(|op| symmetry_image_indices(unitcell, op, phonon.symprec).unwrap())
+            .collect();
+        let supercell_rotation = |rotation: &[[i32; 3]; 3]| -> [[f64; 3]; 3] {
+            [0, 1, 2].map(|i| [0, 1, 2].map(|j| (rotation[i][j] * m[j] as i32 / m[i] as i32) as f64))
+        };
+        let lattice = scaled_lattice(&make_supercell(unitcell, m));
+
+        let mut displacements = vec![];
+        let mut equivalent = vec![false; unitcell.frac_coords.len()];
+        for atom in 0..unitcell.frac_coords.len() {
+            if equivalent[atom] {
+                continue;
+            }
+            for image in &images {
+                equivalent[image[atom]] = true;
+            }
+            let site_rotations: Vec<[[f64; 3]; 3]> = operations
+                .iter()
+                .zip(&images)
+                .filter(|(_, image)| image[atom] == atom)
+                .map(|(op, _)| supercell_rotation(&op.rotation))
+                .collect();
+            let rotate = |r: &[[f64; 3]; 3], d: &[f64; 3]| [0, 1, 2].map(|i| (0..3).map(|j| r[i][j] * d[j]).sum());
+
+            let mut spanned: Vec<[f64; 3]> = vec![];
+            for direction in [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]] {
+                let rotated: Vec<[f64; 3]> = site_rotations.iter().map(|r| rotate(r, &direction)).collect();
+                let mut trial = spanned.clone();
+                trial.extend(&rotated);
+                if rank(&trial) == rank(&spanned) {
+                    continue;
+                }
+                spanned = trial;
+                let opposite = direction.map(|x| -x);
+                let mut directions = vec![direction];
+                if !rotated.iter().any(|d| d == &opposite) {
+                    directions.push(opposite);
+                }
+                for direction in directions {
+                    let cart = frac_to_cart(&direction, &lattice);
+                    let norm = cart.iter().map(|x| x * x).sum::<f64>().sqrt();
+                    displacements.push(Displacement {
+                        atom: atom * num_lattice_points,
+                        vector: cart.map(|x| x / norm * phonon.displacement),
+                    });
+                }
+                if rank(&spanned) == 3 {
+                    break;
+                }
+            }
+        }
+        Ok(displacements)
+    }
+
+    /// Writes phonon job: the perfect supercell and a singlepoint job of each displaced supercell in
+    /// `disp-001`, `disp-002`, ... directories. Job script in the job directory runs them one after another.
+    pub fn create_phonon_job(&self) -> Result<()> {
+        let phonon = self.phonon()?;
+        println!("Creating phonon job in {}", self.job_dir);
+        std::fs::create_dir_all(&self.job_dir)?;
+        if self.write_poscar()? {
+            println!("Writing POSCAR");
+        }
+        let unitcell = Poscar::from_file(self.structure_file())?;
+        let supercell = make_supercell(&unitcell, phonon.supercell);
+        supercell.to_file(Path::new(&self.job_dir).join(PHONON_SUPERCELL))?;
+
+        let displacements = self.phonon_displacements(&unitcell)?;
+        println!(
+            "Writing {} displaced supercells of {} atoms",
+            displacements.len(),
+            supercell.frac_coords.len()
+        );
+        let lattice = scaled_lattice(&supercell);
+        let structures: Vec<(String, Poscar)> = displacements
+            .iter()
+            .enumerate()
+            .map(|(n, displacement)| {
+                let dir = format!("disp-{:03}", n + 1);
+                let mut displaced = supercell.clone();
+                displaced.comment = format!("{} ({})", supercell.comment.trim(), dir);
+                let d = cart_to_frac(&displacement.vector, &lattice);
+                for (x, dx) in displaced.frac_coords[displacement.atom].iter_mut().zip(d) {
+                    *x += dx;
+                }
+                (dir, displaced)
+            })
+            .collect();
+        self.write_sub_jobs(&structures)
+    }
+}
+
+/// Rank of the set of vectors.
+fn rank(vectors: &[[f64; 3]]) -> usize {
+    let mut basis: Vec<[f64; 3]> = vec![];
+    for v in vectors {
+        let mut v = *v;
+        for b in &basis {
+            let projection: f64 = (0..3).map(|k| v[k] * b[k]).sum();
+            for k in 0..3 {
+                v[k] -= projection * b[k];
+            }
+        }
+        let norm = v.iter().map(|x| x * x).sum::<f64>().sqrt();
+        if norm > 1e-6 {
+            basis.push(v.map(|x| x / norm));
+        }
+    }
+    basis.len()
+}
+
+mod tests {
+    #[test]
+    fn test_phonon_displacements() {
+        use super::*;
+        let poscar = Poscar {
+            comment: String::from("Si"),
+            scale: 5.43,
+            lattice: [[0.0, 0.5, 0.5], [0.5, 0.0, 0.5], [0.5, 0.5, 0.0]],
+            species: vec![String::from("Si")],
+            num_atoms: vec![2],
+            frac_coords: vec![[0.0, 0.0, 0.0], [0.25, 0.25, 0.25]],
+            selective_dynamics: None,
+        };
+        let job_config = JobConfig {
+            toml_contents: toml::from_str("[vasp.phonon]\nsupercell = [2, 2, 2]").unwrap(),
+            job_dir: String::from("."),
+        };
+        // phonopy displaces only the first atom of diamond, along the first lattice vector.
+        let displacements = job_config.phonon_displacements(&poscar).unwrap();
+        assert_eq!(displacements.len(), 1);
+        assert_eq!(displacements[0].atom, 0);
+        let expected = 0.01 / 2f64.sqrt();
+        for (x, e) in displacements[0].vector.iter().zip([0.0, expected, expected]) {
+            assert!((x - e).abs() < 1e-12);
+        }
+    }
+}
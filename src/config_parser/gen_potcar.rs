@@ -4,7 +4,7 @@ use std::collections::HashMap;
 use vasp_files::potcar::{generate_potcar, PotcarMode};
 
 impl config::JobConfig {
-    /// Generates POTCAR file from POSCAR.
+    /// Generates POTCAR file from POSCAR of the job.
     pub fn write_potcar(&self) -> Result<()> {
         let potcar_mode = match &self.toml_contents["vasp"]["potcar"].as_str() {
             Some(s) if s == &"recommended" => PotcarMode::Recommended,
@@ -21,7 +21,33 @@ impl config::JobConfig {
                 PotcarMode::Custom(potcar_map)
             }
         };
-        let input_file = &self.toml_contents["vasp"]["input"].as_str().unwrap();
-        generate_potcar(&self.job_dir, input_file, potcar_mode)
+        // An absolute path, read the same whether or not it is taken relative to the job directory.
+        let structure_file = std::fs::canonicalize(self.structure_file())
+            .map_err(|_| eyre::eyre!("{} not found", self.structure_file()))?;
+        generate_potcar(&self.job_dir, structure_file.to_str().unwrap(), potcar_mode)
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_write_potcar_in_sub_dir() {
+        use super::*;
+        let root = std::env::temp_dir().join(format!("vasp_manager_test_potcar_{}", std::process::id()));
+        let job_dir = root.join("encut_400");
+        std::fs::create_dir_all(&job_dir).unwrap();
+        std::fs::write(
+            job_dir.join("POSCAR"),
+            "MgO\n1.0\n4.2 0.0 0.0\n0.0 4.2 0.0\n0.0 0.0 4.2\nMg O\n1 1\nDirect\n0.0 0.0 0.0\n0.5 0.5 0.5\n",
+        )
+        .unwrap();
+        let job_config = config::JobConfig {
+            toml_contents: toml::from_str("[vasp]\npotcar = \"recommended\"\n[vasp.incar]\nbase = \"singlepoint\"")
+                .unwrap(),
+            job_dir: job_dir.to_str().unwrap().to_string(),
+        };
+        job_config.write_potcar().unwrap();
+        let potcar = std::fs::read_to_string(job_dir.join("POTCAR")).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(potcar.matches("End of Dataset").count(), 2);
     }
 }
@@ -0,0 +1,159 @@
+use super::config::JobConfig;
+use crate::structure_util::apply_strain;
+use eyre::Result;
+use vasp_files::poscar::Poscar;
+
+/// Equation of state settings in `[vasp.eos]` section of Vasp.toml.
+#[derive(Debug, Clone)]
+pub struct EosConfig {
+    /// Smallest and largest volumes, relative to the input structure.
+    pub volume_range: [f64; 2],
+    pub num_points: usize,
+}
+
+/// Elastic constant settings in `[vasp.elastic]` section of Vasp.toml.
+#[derive(Debug, Clone)]
+pub struct ElasticConfig {
+    /// Magnitudes of strains, each applied in positive and negative directions.
+    pub magnitudes: Vec<f64>,
+}
+
+/// Strain of a job of the `elastic` task.
+#[derive(Debug, Clone)]
+pub struct ElasticStrain {
+    pub dir: String,
+    /// 0-based Voigt index of the strained component (xx, yy, zz, yz, xz, xy).
+    pub component: usize,
+    pub magnitude: f64,
+}
+
+impl ElasticStrain {
+    /// Strain in Voigt notation.
+    pub fn voigt(&self) -> [f64; 6] {
+        let mut strain = [0.0; 6];
+        strain[self.component] = self.magnitude;
+        strain
+    }
+}
+
+impl JobConfig {
+    /// Parses `[vasp.eos]` section. Volumes from 0.94 to 1.06 times the input at 7 points by default.
+    pub fn eos(&self) -> Result<EosConfig> {
+        let eos_config = self.toml_contents["vasp"].get("eos");
+        let volume_range = match eos_config.and_then(|e| e.get("volume_range")) {
+            Some(range) => {
+                let range: Vec<f64> = range
+                    .as_array()
+                    .map(|r| r.iter().filter_map(|v| v.as_float()).collect())
+                    .unwrap_or_default();
+                match range[..] {
+                    [min, max] if 0.0 < min && min < max => [min, max],
+                    _ => {
+                        return Err(eyre::eyre!(
+                            "volume_range in [vasp.eos] should be [min, max], e.g. [0.94, 1.06]"
+                        ))
+                    }
+                }
+            }
+            None => [0.94, 1.06],
+        };
+        let num_points = match eos_config.and_then(|e| e.get("num_points")) {
+            Some(n) => n.as_integer().filter(|&n| n >= 4).ok_or_else(|| {
+                eyre::eyre!("num_points in [vasp.eos] should be at least 4 to fit the equation of state")
+            })? as usize,
+            None => 7,
+        };
+        Ok(EosConfig {
+            volume_range,
+            num_points,
+        })
+    }
+
+    /// Parses `[vasp.elastic]` section. Strains of 0.5% and 1% by default.
+    pub fn elastic(&self) -> Result<ElasticConfig> {
+        let magnitudes = match self.toml_contents["vasp"]
+            .get("elastic")
+            .and_then(|e| e.get("magnitudes"))
+        {
+            Some(magnitudes) => magnitudes
+                .as_array()
+                .map(|m| {
+                    m.iter()
+                        .filter_map(|v| v.as_float())
+                        .filter(|&v| v > 0.0)
+                        .collect::<Vec<_>>()
+                })
+                .filter(|m| !m.is_empty())
+                .ok_or_else(|| {
+                    eyre::eyre!("magnitudes in [vasp.elastic] should be positive strains, e.g. [0.005, 0.01]")
+                })?,
+            None => vec![0.005, 0.01],
+        };
+        Ok(ElasticConfig { magnitudes })
+    }
+
+    /// Directories and volumes (relative to the input structure) of the `eos` task.
+    pub fn eos_volumes(&self) -> Result<Vec<(String, f64)>> {
+        let eos = self.eos()?;
+        let [min, max] = eos.volume_range;
+        Ok((0..eos.num_points)
+            .map(|n| {
+                let volume = min + (max - min) * n as f64 / (eos.num_points - 1) as f64;
+                (format!("eos-{:02}", n + 1), volume)
+            })
+            .collect())
+    }
+
+    /// Strains of the `elastic` task: each of the six independent components in positive and negative directions.
+    pub fn elastic_strains(&self) -> Result<Vec<ElasticStrain>> {
+        let elastic = self.elastic()?;
+        let mut strains = vec![];
+        for component in 0..6 {
+            for &magnitude in &elastic.magnitudes {
+                for magnitude in [-magnitude, magnitude] {
+                    strains.push(ElasticStrain {
+                        dir: format!("e{}_{:+.4}", component + 1, magnitude),
+                        component,
+                        magnitude,
+                    });
+                }
+            }
+        }
+        Ok(strains)
+    }
+
+    /// Writes a job of each strained structure of `eos` or `elastic` task in its own directory.
+    pub fn create_strain_job(&self) -> Result<()> {
+        println!("Creating {} job in {}", self.incar_base(), self.job_dir);
+        std::fs::create_dir_all(&self.job_dir)?;
+        if self.write_poscar()? {
+            println!("Writing POSCAR");
+        }
+        let poscar = Poscar::from_file(self.structure_file())?;
+        let strained = |dir: String, strain: [f64; 6]| {
+            let mut structure = apply_strain(&poscar, &strain);
+            structure.comment = format!("{} ({})", poscar.comment.trim(), dir);
+            (dir, structure)
+        };
+        let structures: Vec<(String, Poscar)> = match self.incar_base().as_str() {
+            "eos" => self
+                .eos_volumes()?
+                .into_iter()
+                .map(|(dir, volume)| {
+                    let e = volume.cbrt() - 1.0;
+                    strained(dir, [e, e, e, 0.0, 0.0, 0.0])
+                })
+                .collect(),
+            _ => self
+                .elastic_strains()?
+                .into_iter()
+                .map(|strain| {
+                    let voigt = strain.voigt();
+                    strained(strain.dir, voigt)
+                })
+                .collect(),
+        };
+        println!("Writing {} strained structures", structures.len());
+        self.write_sub_jobs(&structures)
+    }
+}
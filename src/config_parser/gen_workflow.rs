@@ -0,0 +1,186 @@
+use super::config::JobConfig;
+use super::gen_kpoints::kpoints_mesh;
+use crate::template::IncarTag;
+use eyre::Result;
+use serde::Serialize;
+
+/// Tasks using hybrid functionals.
+pub const HYBRID_TASKS: [&str; 2] = ["hse", "pbe0"];
+/// Tasks using meta-GGA functionals.
+pub const META_GGA_TASKS: [&str; 2] = ["scan", "r2scan"];
+/// Tags selecting hybrid and meta-GGA functionals, which the PBE pre-step does not take from `[vasp.incar.tags]`.
+pub const FUNCTIONAL_TAG_NAMES: [&str; 6] = ["LHFCALC", "HFSCREEN", "AEXX", "NKRED", "METAGGA", "ALGO"];
+
+/// Calculation run in the same job before the main calculation, e.g. PBE pre-convergence of WAVECAR
+/// or the SCF step of DOS.
+/// Its INCAR (and KPOINTS) are written as `INCAR.<name>` (`KPOINTS.<name>`), and its outputs are
+/// kept as `OUTCAR.<name>`, `OSZICAR.<name>` and `vasprun.xml.<name>`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PreStep {
+    pub name: String,
+    pub description: String,
+    /// Base INCAR template of the step.
+    pub incar_base: String,
+    /// Tags of the step, overriding the tags of the main calculation.
+    pub incar_tags: Vec<IncarTag>,
+    /// Additional tags of the main calculation not used in the step.
+    pub excluded_tags: Vec<String>,
+    /// Whether the step uses its own KPOINTS.
+    pub kpoints: bool,
+}
+
+impl JobConfig {
+    /// Pre-step of the task. Can be disabled with `pre_converge = false` in `[vasp.incar]`.
+    pub fn pre_step(&self) -> Option<PreStep> {
+        let pre_converge = self.toml_contents["vasp"]["incar"]
+            .get("pre_converge")
+            .and_then(|p| p.as_bool())
+            .unwrap_or(true);
+        if !pre_converge {
+            return None;
+        }
+        let base = self.incar_base();
+        if HYBRID_TASKS.contains(&base.as_str()) || META_GGA_TASKS.contains(&base.as_str()) {
+            return Some(PreStep {
+                name: String::from("pbe"),
+                description: String::from("Pre-converge WAVECAR with PBE"),
+                incar_base: String::from("singlepoint"),
+                // WAVECAR can only be read with the same irreducible k-points, i.e. the same ISYM.
+                incar_tags: vec![
+                    IncarTag {
+                        name: String::from("LWAVE"),
+                        value: String::from(".TRUE."),
+                    },
+                    IncarTag {
+                        name: String::from("ISYM"),
+                        value: self.isym().to_string(),
+                    },
+                ],
+                excluded_tags: FUNCTIONAL_TAG_NAMES.iter().map(|name| name.to_string()).collect(),
+                kpoints: false,
+            });
+        }
+        if base == "dos" {
+            return Some(PreStep {
+                name: String::from("scf"),
+                description: String::from("SCF charge density for the non-SCF DOS step"),
+                incar_base: String::from("singlepoint"),
+                incar_tags: vec![IncarTag {
+                    name: String::from("LCHARG"),
+                    value: String::from(".TRUE."),
+                }],
+                excluded_tags: vec![],
+                kpoints: true,
+            });
+        }
+        None
+    }
+
+    /// K-points of the non-SCF step of DOS. Either `kpoints` in `[vasp.dos]`, or the mesh (or density)
+    /// of `[vasp.kpoints]` multiplied by `kpoints_scale` in `[vasp.dos]` (2 by default).
+    pub fn dos_kpoints_config(&self) -> Result<toml::Value> {
+        let dos_config = self.toml_contents["vasp"].get("dos");
+        if let Some(kpoints_config) = dos_config.and_then(|d| d.get("kpoints")) {
+            return Ok(kpoints_config.clone());
+        }
+        let scale = match dos_config.and_then(|d| d.get("kpoints_scale")) {
+            Some(s) => s
+                .as_integer()
+                .filter(|&s| s > 0)
+                .ok_or_else(|| eyre::eyre!("kpoints_scale in [vasp.dos] should be a positive integer"))?,
+            None => 2,
+        };
+        let mut kpoints_config = self.toml_contents["vasp"]["kpoints"].clone();
+        let table = kpoints_config
+            .as_table_mut()
+            .ok_or_else(|| eyre::eyre!("Invalid kpoints in [vasp]"))?;
+        if let Some(toml::Value::Array(mesh)) = table.get_mut("mesh") {
+            for n in mesh.iter_mut() {
+                *n = toml::Value::Integer(n.as_integer().unwrap_or(1) * scale);
+            }
+        } else if let Some(density) = table.get("density").and_then(|d| d.as_float()) {
+            table.insert(String::from("density"), toml::Value::Float(density * scale as f64));
+        }
+        Ok(kpoints_config)
+    }
+
+    /// Tags depending on the functional of the task.
+    /// Hybrid functionals use NKRED = 2 on dense, even k-point meshes to reduce the cost of exact exchange.
+    pub fn functional_tags(&self) -> Result<Vec<IncarTag>> {
+        if !HYBRID_TASKS.contains(&self.incar_base().as_str()) {
+            return Ok(vec![]);
+        }
+        match kpoints_mesh(&self.kpoints_contents()?) {
+            Some(mesh) if mesh.iter().all(|&n| n >= 4 && n % 2 == 0) => Ok(vec![IncarTag {
+                name: String::from("NKRED"),
+                value: String::from("2"),
+            }]),
+            _ => Ok(vec![]),
+        }
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_pre_step() {
+        use super::*;
+        let job_config = |contents: &str| JobConfig {
+            toml_contents: toml::from_str(contents).unwrap(),
+            job_dir: String::from("."),
+        };
+        assert!(job_config("[vasp.incar]\nbase = \"relax\"").pre_step().is_none());
+        assert!(job_config("[vasp.incar]\nbase = \"hse\"\npre_converge = false")
+            .pre_step()
+            .is_none());
+
+        let pre_step = job_config("[vasp.incar]\nbase = \"r2scan\"").pre_step().unwrap();
+        assert_eq!(pre_step.name, "pbe");
+        assert_eq!(pre_step.incar_base, "singlepoint");
+        assert!(!pre_step.kpoints);
+        assert!(pre_step.excluded_tags.contains(&String::from("METAGGA")));
+        // ISYM follows the main calculation, also when set in the additional tags.
+        let isym = |pre_step: &PreStep| {
+            pre_step
+                .incar_tags
+                .iter()
+                .find(|t| t.name == "ISYM")
+                .unwrap()
+                .value
+                .clone()
+        };
+        assert_eq!(isym(&pre_step), "0");
+        let pre_step = job_config("[vasp.incar]\nbase = \"hse\"\n[vasp.incar.tags]\nISYM = 3")
+            .pre_step()
+            .unwrap();
+        assert_eq!(isym(&pre_step), "3");
+
+        let pre_step = job_config("[vasp.incar]\nbase = \"dos\"").pre_step().unwrap();
+        assert_eq!(pre_step.name, "scf");
+        assert!(pre_step.kpoints);
+        assert!(pre_step.excluded_tags.is_empty());
+    }
+
+    #[test]
+    fn test_functional_tags() {
+        use super::*;
+        // Only the mesh of `[vasp.kpoints]` is needed, not KPOINTS in the job directory.
+        let functional_tags = |base: &str, mesh: &str| -> Vec<IncarTag> {
+            JobConfig {
+                toml_contents: toml::from_str(&format!(
+                    "[vasp]\nkpoints = {{ scheme = \"Gamma\", mesh = [{}] }}\n[vasp.incar]\nbase = \"{}\"",
+                    mesh, base
+                ))
+                .unwrap(),
+                job_dir: String::from("nonexistent"),
+            }
+            .functional_tags()
+            .unwrap()
+        };
+        let nkred = functional_tags("hse", "4, 4, 6");
+        assert_eq!(nkred.len(), 1);
+        assert_eq!((nkred[0].name.as_str(), nkred[0].value.as_str()), ("NKRED", "2"));
+        assert!(functional_tags("pbe0", "4, 4, 3").is_empty());
+        assert!(functional_tags("hse", "2, 2, 2").is_empty());
+        assert!(functional_tags("scan", "4, 4, 4").is_empty());
+    }
+}
@@ -1,7 +1,27 @@
 pub mod config;
+pub mod gen_constraints;
+pub mod gen_freq;
+pub mod gen_hubbard;
 pub mod gen_incar;
 pub mod gen_job_script;
 pub mod gen_kpoints;
+pub mod gen_magmom;
+pub mod gen_manifest;
+pub mod gen_mlff;
+pub mod gen_neb;
+pub mod gen_parallel;
+pub mod gen_phonon;
 pub mod gen_potcar;
+pub mod gen_strain;
+pub mod gen_workflow;
+pub mod sweep;
 
 pub use config::*;
+pub use gen_constraints::*;
+pub use gen_manifest::*;
+pub use gen_mlff::*;
+pub use gen_neb::*;
+pub use gen_phonon::*;
+pub use gen_strain::*;
+pub use gen_workflow::*;
+pub use sweep::*;
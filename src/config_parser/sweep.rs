@@ -0,0 +1,374 @@
+use super::config::{format_value, JobConfig};
+use eyre::Result;
+use glob::glob;
+use std::path::Path;
+use toml::Value;
+
+/// What a sweep variable changes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SweepTarget {
+    IncarTag(String),
+    KpointsDensity,
+    Structure,
+}
+
+#[derive(Debug, Clone)]
+pub struct SweepVariable {
+    pub name: String,
+    pub target: SweepTarget,
+    pub values: Vec<Value>,
+}
+
+/// Parameter sweep defined in `[sweep]` section of Vasp.toml.
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    pub variables: Vec<SweepVariable>,
+    /// Combine values element-wise instead of taking the cartesian product.
+    pub zip: bool,
+    /// The directory to create jobs in.
+    pub dir: String,
+}
+
+impl JobConfig {
+    /// Parses `[sweep]` section. Returns `None` if it does not exist.
+    pub fn sweep(&self) -> Result<Option<Sweep>> {
+        let sweep_config = match self.toml_contents.get("sweep") {
+            Some(sweep_config) => sweep_config,
+            None => return Ok(None),
+        };
+        let zip = match sweep_config.get("combination").and_then(|c| c.as_str()) {
+            None | Some("product") => false,
+            Some("zip") => true,
+            Some(c) => return Err(eyre::eyre!("Unknown sweep combination '{}'. Use 'product' or 'zip'", c)),
+        };
+        let dir = sweep_config
+            .get("dir")
+            .and_then(|d| d.as_str())
+            .unwrap_or("sweep")
+            .to_string();
+
+        let as_list = |name: &str, value: &Value| -> Result<Vec<Value>> {
+            match value.as_array() {
+                Some(values) if !values.is_empty() => Ok(values.clone()),
+                _ => Err(eyre::eyre!("Sweep variable '{}' should be a non-empty list", name)),
+            }
+        };
+        let mut variables = vec![];
+        if let Some(tags) = sweep_config.get("incar").and_then(|t| t.as_table()) {
+            for (name, values) in tags {
+                variables.push(SweepVariable {
+                    name: name.clone(),
+                    target: SweepTarget::IncarTag(name.clone()),
+                    values: as_list(name, values)?,
+                });
+            }
+        }
+        if let Some(values) = sweep_config.get("kpoints_density") {
+            let values = as_list("kpoints_density", values)?;
+            if let Some(value) = values
+                .iter()
+                .find(|value| kpoints_density(value).is_none_or(|d| d <= 0.0))
+            {
+                return Err(eyre::eyre!(
+                    "Sweep variable 'kpoints_density' should be a list of positive numbers, found {}",
+                    value
+                ));
+            }
+            variables.push(SweepVariable {
+                name: String::from("kdensity"),
+                target: SweepTarget::KpointsDensity,
+                values,
+            });
+        }
+        if let Some(structure) = sweep_config.get("structure") {
+            let files = match structure {
+                Value::String(pattern) => {
+                    let mut files: Vec<String> = glob(pattern)?
+                        .filter_map(|path| path.ok())
+                        .map(|path| path.to_str().unwrap().to_string())
+                        .collect();
+                    files.sort();
+                    files
+                }
+                Value::Array(files) => match files.iter().find(|file| !file.is_str()) {
+                    Some(file) => {
+                        return Err(eyre::eyre!(
+                            "Sweep variable 'structure' should be a list of file names, found {}",
+                            file
+                        ))
+                    }
+                    None => files.iter().map(format_value).collect(),
+                },
+                _ => {
+                    return Err(eyre::eyre!(
+                        "Sweep structure should be a glob pattern or a list of files"
+                    ))
+                }
+            };
+            if files.is_empty() {
+                return Err(eyre::eyre!("No structure matches sweep structure {}", structure));
+            }
+            variables.push(SweepVariable {
+                name: String::from("structure"),
+                target: SweepTarget::Structure,
+                values: files.into_iter().map(Value::String).collect(),
+            });
+        }
+        if variables.is_empty() {
+            return Err(eyre::eyre!("No variable found in [sweep]"));
+        }
+        Ok(Some(Sweep { variables, zip, dir }))
+    }
+
+    /// Returns the configuration of a single job of the sweep.
+    /// `combination` is the index of the value of each sweep variable.
+    pub fn sweep_job(&self, sweep: &Sweep, combination: &[usize]) -> JobConfig {
+        let name = sweep.job_name(combination);
+        let job_dir = Path::new(&sweep.dir).join(&name);
+        let mut job_config = self.with_job_dir(job_dir.to_str().unwrap());
+        job_config.toml_contents.as_table_mut().unwrap().remove("sweep");
+        job_config.set_job_name(&format!("{}_{}", self.job_name(), name));
+        for (variable, &i) in sweep.variables.iter().zip(combination) {
+            let value = variable.values[i].clone();
+            match &variable.target {
+                SweepTarget::IncarTag(tag) => job_config.set_incar_tag(tag, value),
+                // Validated in `sweep`.
+                SweepTarget::KpointsDensity => job_config.set_kpoints_density(kpoints_density(&value).unwrap()),
+                SweepTarget::Structure => job_config.toml_contents["vasp"]["input"] = value,
+            }
+        }
+        job_config
+    }
+}
+
+/// K-points density given as an integer or a float.
+fn kpoints_density(value: &Value) -> Option<f64> {
+    value.as_float().or_else(|| value.as_integer().map(|v| v as f64))
+}
+
+impl Sweep {
+    /// Combinations of the sweep, each given as the index of the value of each variable.
+    pub fn combinations(&self) -> Result<Vec<Vec<usize>>> {
+        if self.zip {
+            let len = self.variables[0].values.len();
+            if self.variables.iter().any(|v| v.values.len() != len) {
+                return Err(eyre::eyre!(
+                    "All sweep variables should have the same number of values to zip"
+                ));
+            }
+            let combinations: Vec<Vec<usize>> = (0..len).map(|i| vec![i; self.variables.len()]).collect();
+            self.check_job_names(&combinations)?;
+            return Ok(combinations);
+        }
+        let mut combinations: Vec<Vec<usize>> = vec![vec![]];
+        for variable in &self.variables {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination| {
+                    (0..variable.values.len()).map(move |i| {
+                        let mut combination = combination.clone();
+                        combination.push(i);
+                        combination
+                    })
+                })
+                .collect();
+        }
+        self.check_job_names(&combinations)?;
+        Ok(combinations)
+    }
+
+    /// Checks that no two combinations share a job directory.
+    fn check_job_names(&self, combinations: &[Vec<usize>]) -> Result<()> {
+        let mut names = std::collections::HashSet::new();
+        for combination in combinations {
+            let name = self.job_name(combination);
+            if !names.insert(name.clone()) {
+                return Err(eyre::eyre!(
+                    "Sweep values give duplicate job directory {}. Use values with distinct labels",
+                    name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Label of the value of a variable, used in directory names and the manifest.
+    pub fn label(&self, variable: &SweepVariable, index: usize) -> String {
+        let value = format_value(&variable.values[index]);
+        match variable.target {
+            SweepTarget::Structure => {
+                let path = Path::new(&value);
+                let file_name = path.file_name().unwrap().to_str().unwrap();
+                // Structures like strain_1/POSCAR are named after the directory.
+                match (file_name, path.parent().and_then(|p| p.file_name())) {
+                    ("POSCAR" | "CONTCAR", Some(parent)) => parent.to_str().unwrap().to_string(),
+                    _ => path.file_stem().unwrap().to_str().unwrap().to_string(),
+                }
+            }
+            _ => value,
+        }
+    }
+
+    /// Name of the job directory of a combination.
+    pub fn job_name(&self, combination: &[usize]) -> String {
+        self.variables
+            .iter()
+            .zip(combination)
+            .map(|(variable, &i)| {
+                let label: String = self
+                    .label(variable, i)
+                    .chars()
+                    .map(|c| match c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                        true => c,
+                        false => '_',
+                    })
+                    .collect();
+                format!("{}_{}", variable.name, label)
+            })
+            .collect::<Vec<String>>()
+            .join("__")
+    }
+
+    /// Manifest in CSV format, mapping job directories to values of variables.
+    pub fn manifest(&self, combinations: &[Vec<usize>]) -> String {
+        let mut header = vec![String::from("directory")];
+        header.extend(self.variables.iter().map(|v| v.name.clone()));
+        let mut manifest = header.join(",") + "\n";
+        for combination in combinations {
+            let mut row = vec![self.job_name(combination)];
+            for (variable, &i) in self.variables.iter().zip(combination) {
+                let value = format_value(&variable.values[i]);
+                row.push(match value.contains([',', ' ', '"', '\n']) {
+                    true => format!("\"{}\"", value.replace('"', "\"\"")),
+                    false => value,
+                });
+            }
+            manifest.push_str(&(row.join(",") + "\n"));
+        }
+        manifest
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_combinations() {
+        use super::*;
+        let mut sweep = Sweep {
+            variables: vec![
+                SweepVariable {
+                    name: String::from("ENCUT"),
+                    target: SweepTarget::IncarTag(String::from("ENCUT")),
+                    values: vec![Value::Integer(400), Value::Integer(500)],
+                },
+                SweepVariable {
+                    name: String::from("kdensity"),
+                    target: SweepTarget::KpointsDensity,
+                    values: vec![Value::Float(2.0), Value::Float(3.0), Value::Float(4.0)],
+                },
+            ],
+            zip: false,
+            dir: String::from("sweep"),
+        };
+        let combinations = sweep.combinations().unwrap();
+        assert_eq!(combinations.len(), 6);
+        assert_eq!(combinations[0], vec![0, 0]);
+        assert_eq!(combinations[1], vec![0, 1]);
+        assert_eq!(combinations[5], vec![1, 2]);
+
+        sweep.zip = true;
+        assert!(sweep.combinations().is_err());
+        sweep.variables[1].values.pop();
+        assert_eq!(sweep.combinations().unwrap(), vec![vec![0, 0], vec![1, 1]]);
+    }
+
+    #[test]
+    fn test_job_name() {
+        use super::*;
+        let toml_contents: Value = toml::from_str(
+            r#"
+            MAGMOM = ["1 1", "1 -1"]
+            structure = ["strain_1/POSCAR", "strain_2/POSCAR", "relaxed.vasp"]
+            "#,
+        )
+        .unwrap();
+        let sweep = Sweep {
+            variables: vec![
+                SweepVariable {
+                    name: String::from("MAGMOM"),
+                    target: SweepTarget::IncarTag(String::from("MAGMOM")),
+                    values: toml_contents["MAGMOM"].as_array().unwrap().clone(),
+                },
+                SweepVariable {
+                    name: String::from("structure"),
+                    target: SweepTarget::Structure,
+                    values: toml_contents["structure"].as_array().unwrap().clone(),
+                },
+            ],
+            zip: false,
+            dir: String::from("sweep"),
+        };
+        assert_eq!(sweep.job_name(&[0, 0]), "MAGMOM_1_1__structure_strain_1");
+        assert_eq!(sweep.job_name(&[1, 2]), "MAGMOM_1_-1__structure_relaxed");
+
+        let combinations = sweep.combinations().unwrap();
+        let manifest = sweep.manifest(&combinations[..2]);
+        assert_eq!(
+            manifest,
+            "directory,MAGMOM,structure\n\
+             MAGMOM_1_1__structure_strain_1,\"1 1\",strain_1/POSCAR\n\
+             MAGMOM_1_1__structure_strain_2,\"1 1\",strain_2/POSCAR\n"
+        );
+    }
+
+    #[test]
+    fn test_manifest_quotes() {
+        use super::*;
+        let values: Value = toml::from_str(r#"values = [["a", "b"], "a b", "a_b"]"#).unwrap();
+        let mut sweep = Sweep {
+            variables: vec![SweepVariable {
+                name: String::from("LIST"),
+                target: SweepTarget::IncarTag(String::from("LIST")),
+                values: values["values"].as_array().unwrap().clone(),
+            }],
+            zip: false,
+            dir: String::from("sweep"),
+        };
+        // "a b" and "a_b" sanitize to the same directory name.
+        assert!(sweep.combinations().is_err());
+        sweep.variables[0].values.pop();
+        assert_eq!(sweep.combinations().unwrap().len(), 2);
+        let manifest = sweep.manifest(&[vec![0]]);
+        assert_eq!(manifest.lines().nth(1).unwrap(), r#"LIST___a____b__,"[""a"", ""b""]""#);
+    }
+
+    #[test]
+    fn test_sweep_values() {
+        use super::*;
+        let config_with_sweep = |sweep: &str| JobConfig {
+            toml_contents: toml::from_str(&format!(
+                "[slurm]\njob_name = \"test\"\n\n[vasp]\nkpoints = {{ scheme = \"Gamma\", mesh = [1, 1, 1] }}\n\n\
+                 [sweep]\n{}\n",
+                sweep
+            ))
+            .unwrap(),
+            job_dir: String::from("."),
+        };
+        let sweep = config_with_sweep("kpoints_density = [2, 3.5]")
+            .sweep()
+            .unwrap()
+            .unwrap();
+        assert_eq!(sweep.variables[0].target, SweepTarget::KpointsDensity);
+        let job_config = config_with_sweep("kpoints_density = [2, 3.5]").sweep_job(&sweep, &[1]);
+        assert_eq!(
+            job_config.toml_contents["vasp"]["kpoints"]["density"].as_float(),
+            Some(3.5)
+        );
+
+        let error = config_with_sweep(r#"kpoints_density = ["a"]"#).sweep().unwrap_err();
+        assert!(error.to_string().contains("kpoints_density"));
+        assert!(config_with_sweep("kpoints_density = [2.0, -1.0]").sweep().is_err());
+        assert!(config_with_sweep("kpoints_density = []").sweep().is_err());
+        let error = config_with_sweep("structure = [1, 2]").sweep().unwrap_err();
+        assert!(error.to_string().contains("structure"));
+    }
+}
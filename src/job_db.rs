@@ -0,0 +1,409 @@
+use crate::config_parser::{format_value, JobConfig};
+use crate::output_parser::RunSummary;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use vasp_files::poscar::Poscar;
+
+/// Directory of the job database, relative to the home directory.
+pub const JOB_DB_DIR: &str = ".local/share/vasp_manager";
+/// File name of the job database.
+pub const JOB_DB_FILE: &str = "jobs.json";
+
+/// State of a job, as last recorded or found by `db sync`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    /// Input files are written.
+    Created,
+    /// Submitted to slurm and waiting in the queue.
+    Submitted,
+    /// Output files are being written, or the job is in the queue.
+    Running,
+    /// Finished and converged.
+    Finished,
+    /// Finished without reaching electronic or ionic convergence.
+    Unconverged,
+    /// Left the queue without finishing.
+    Failed,
+    /// Job directory no longer exists.
+    Missing,
+}
+
+impl std::fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let status = match self {
+            JobStatus::Created => "created",
+            JobStatus::Submitted => "submitted",
+            JobStatus::Running => "running",
+            JobStatus::Finished => "finished",
+            JobStatus::Unconverged => "unconverged",
+            JobStatus::Failed => "failed",
+            JobStatus::Missing => "missing",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+impl std::str::FromStr for JobStatus {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<JobStatus> {
+        match s.to_ascii_lowercase().as_str() {
+            "created" => Ok(JobStatus::Created),
+            "submitted" => Ok(JobStatus::Submitted),
+            "running" => Ok(JobStatus::Running),
+            "finished" => Ok(JobStatus::Finished),
+            "unconverged" => Ok(JobStatus::Unconverged),
+            "failed" => Ok(JobStatus::Failed),
+            "missing" => Ok(JobStatus::Missing),
+            _ => Err(eyre::eyre!("Unknown job status: {}", s)),
+        }
+    }
+}
+
+/// A job in the database. Times are seconds since the Unix epoch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRecord {
+    /// Absolute path of the job directory, which identifies the job.
+    pub path: String,
+    pub job_name: String,
+    pub task: String,
+    pub vasp_version: String,
+    /// Chemical formula of POSCAR, e.g. "Na4Cl4".
+    pub formula: Option<String>,
+    /// Slurm job id, `<array job id>_<task id>` for jobs of a job array.
+    pub job_id: Option<String>,
+    pub status: JobStatus,
+    /// Final free energy (eV) of a finished job.
+    pub energy: Option<f64>,
+    pub created: u64,
+    pub submitted: Option<u64>,
+    pub updated: u64,
+}
+
+impl JobRecord {
+    /// Builds a record of the job in `dir` from its Vasp.toml and POSCAR.
+    /// Fields which cannot be read are left empty.
+    pub fn from_dir(dir: &str) -> Result<JobRecord> {
+        let path = std::fs::canonicalize(dir).map_err(|_| eyre::eyre!("Could not find {}", dir))?;
+        let config = JobConfig::from_dir(dir).ok();
+        let value = |keys: &[&str]| -> String {
+            let mut value = config.as_ref().map(|c| &c.toml_contents);
+            for key in keys {
+                value = value.and_then(|v| v.get(key));
+            }
+            value.map(format_value).unwrap_or_default()
+        };
+        let now = now();
+        Ok(JobRecord {
+            path: path.to_str().unwrap().to_string(),
+            job_name: value(&["slurm", "job_name"]),
+            task: value(&["vasp", "incar", "base"]).to_ascii_lowercase(),
+            vasp_version: value(&["vasp", "version"]),
+            formula: Poscar::from_file(path.join("POSCAR")).ok().map(|p| formula(&p)),
+            job_id: None,
+            status: JobStatus::Created,
+            energy: None,
+            created: now,
+            submitted: None,
+            updated: now,
+        })
+    }
+
+    /// Updates the status and energy from the output files in the job directory.
+    /// `queued` is the list of job ids in the slurm queue, if it could be read.
+    /// Returns true if the status or energy changed.
+    pub fn sync(&mut self, queued: Option<&[String]>) -> bool {
+        let in_queue = match (queued, &self.job_id) {
+            (Some(queued), Some(job_id)) => Some(queued.iter().any(|q| is_same_job(q, job_id))),
+            (Some(_), None) => Some(false),
+            (None, _) => None,
+        };
+        let (status, energy) = if !Path::new(&self.path).is_dir() {
+            (JobStatus::Missing, self.energy)
+        } else {
+            match RunSummary::from_dir(&self.path) {
+                Ok(summary) if summary.finished => {
+                    let status = match summary.ionic_converged && summary.electronic_converged {
+                        true => JobStatus::Finished,
+                        false => JobStatus::Unconverged,
+                    };
+                    (status, summary.free_energy)
+                }
+                Ok(_) => match in_queue {
+                    Some(false) if self.job_id.is_some() => (JobStatus::Failed, None),
+                    _ => (JobStatus::Running, None),
+                },
+                Err(_) => match (self.status, in_queue) {
+                    (JobStatus::Submitted | JobStatus::Running, Some(false)) => (JobStatus::Failed, None),
+                    (JobStatus::Missing, _) => (JobStatus::Created, None),
+                    (status, _) => (status, None),
+                },
+            }
+        };
+        let changed = status != self.status || energy != self.energy;
+        if changed {
+            self.status = status;
+            self.energy = energy;
+            self.updated = now();
+        }
+        changed
+    }
+}
+
+/// Filters of `db query`. Unset filters match every job.
+#[derive(Debug, Clone, Default)]
+pub struct JobFilter {
+    pub task: Option<String>,
+    pub status: Option<JobStatus>,
+    /// Matches the formula of POSCAR or its reduced formula, e.g. "NaCl" matches "Na4Cl4".
+    pub formula: Option<String>,
+    /// Jobs created at or after this time.
+    pub since: Option<u64>,
+    /// Jobs created before this time.
+    pub until: Option<u64>,
+}
+
+impl JobFilter {
+    pub fn matches(&self, record: &JobRecord) -> bool {
+        let formula_matches = |formula: &str| {
+            record
+                .formula
+                .as_deref()
+                .is_some_and(|f| f == formula || reduced_formula(f) == reduced_formula(formula))
+        };
+        self.task
+            .as_ref()
+            .is_none_or(|task| task.eq_ignore_ascii_case(&record.task))
+            && self.status.is_none_or(|status| status == record.status)
+            && self.formula.as_deref().is_none_or(formula_matches)
+            && self.since.is_none_or(|since| record.created >= since)
+            && self.until.is_none_or(|until| record.created < until)
+    }
+}
+
+/// Jobs created and submitted by vasp_manager, stored in `~/.local/share/vasp_manager/jobs.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JobDatabase {
+    pub jobs: Vec<JobRecord>,
+}
+
+impl JobDatabase {
+    /// Path of the database file.
+    pub fn path() -> Result<PathBuf> {
+        let home_dir = home::home_dir().ok_or_else(|| eyre::eyre!("Could not find home directory"))?;
+        Ok(home_dir.join(JOB_DB_DIR).join(JOB_DB_FILE))
+    }
+
+    /// Reads the database, which is empty if the file does not exist yet.
+    pub fn load() -> Result<JobDatabase> {
+        let path = JobDatabase::path()?;
+        if !path.exists() {
+            return Ok(JobDatabase::default());
+        }
+        let contents = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&contents).map_err(|e| eyre::eyre!("Could not parse {}: {}", path.display(), e))
+    }
+
+    /// Writes the database. The file is replaced at once so that it is never left half-written.
+    pub fn save(&self) -> Result<()> {
+        let path = JobDatabase::path()?;
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        let tmp_path = path.with_extension(format!("json.{}", std::process::id()));
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    /// Loads the database, applies `update` and saves it.
+    /// An exclusive lock on `jobs.json.lock` is held meanwhile, so that concurrent runs do not overwrite
+    /// each other's records.
+    pub fn update<F, T>(update: F) -> Result<T>
+    where
+        F: FnOnce(&mut JobDatabase) -> Result<T>,
+    {
+        let path = JobDatabase::path()?;
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        let lock = std::fs::File::create(path.with_extension("json.lock"))?;
+        lock.lock()?;
+        let mut db = JobDatabase::load()?;
+        let result = update(&mut db)?;
+        db.save()?;
+        Ok(result)
+    }
+
+    pub fn get_mut(&mut self, path: &str) -> Option<&mut JobRecord> {
+        self.jobs.iter_mut().find(|job| job.path == path)
+    }
+
+    /// Records the job created in `dir`, replacing the previous record of the directory.
+    pub fn record_created(&mut self, dir: &str) -> Result<()> {
+        let record = JobRecord::from_dir(dir)?;
+        match self.get_mut(&record.path) {
+            Some(job) => *job = record,
+            None => self.jobs.push(record),
+        }
+        Ok(())
+    }
+
+    /// Records submission of the job in `dir` with the slurm job id.
+    pub fn record_submitted(&mut self, dir: &str, job_id: Option<&str>) -> Result<()> {
+        let record = JobRecord::from_dir(dir)?;
+        let job = match self.get_mut(&record.path) {
+            Some(job) => job,
+            None => {
+                self.jobs.push(record);
+                self.jobs.last_mut().unwrap()
+            }
+        };
+        let now = now();
+        job.job_id = job_id.map(String::from);
+        job.status = JobStatus::Submitted;
+        job.energy = None;
+        job.submitted = Some(now);
+        job.updated = now;
+        Ok(())
+    }
+
+    /// Jobs matching the filter, in order of creation.
+    pub fn query(&self, filter: &JobFilter) -> Vec<&JobRecord> {
+        let mut jobs: Vec<&JobRecord> = self.jobs.iter().filter(|job| filter.matches(job)).collect();
+        jobs.sort_by_key(|job| job.created);
+        jobs
+    }
+}
+
+/// Chemical formula of the structure, e.g. "Na4Cl4".
+pub fn formula(poscar: &Poscar) -> String {
+    let mut counts: Vec<(String, usize)> = vec![];
+    for (species, &num) in poscar.species.iter().zip(&poscar.num_atoms) {
+        match counts.iter_mut().find(|(s, _)| s == species) {
+            Some((_, n)) => *n += num,
+            None => counts.push((species.clone(), num)),
+        }
+    }
+    format_formula(&counts)
+}
+
+/// Formula divided by the greatest common divisor of the counts, e.g. "NaCl" for "Na4Cl4".
+pub fn reduced_formula(formula: &str) -> String {
+    let mut counts: Vec<(String, usize)> = vec![];
+    let mut chars = formula.chars().peekable();
+    while let Some(c) = chars.next() {
+        let mut element = c.to_string();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_lowercase()) {
+            element.push(c);
+        }
+        let mut count = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+            count.push(c);
+        }
+        counts.push((element, count.parse().unwrap_or(1)));
+    }
+    let divisor = counts.iter().fold(0, |d, (_, n)| gcd(d, *n)).max(1);
+    for (_, n) in counts.iter_mut() {
+        *n /= divisor;
+    }
+    format_formula(&counts)
+}
+
+fn format_formula(counts: &[(String, usize)]) -> String {
+    counts
+        .iter()
+        .map(|(element, n)| match n {
+            1 => element.clone(),
+            n => format!("{}{}", element, n),
+        })
+        .collect()
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    match b {
+        0 => a,
+        b => gcd(b, a % b),
+    }
+}
+
+/// Whether the job id in the slurm queue is the job, or the pending array containing it.
+/// Pending tasks of an array are listed as e.g. "42_[3-9]".
+fn is_same_job(queued: &str, job_id: &str) -> bool {
+    queued == job_id
+        || job_id
+            .split_once('_')
+            .is_some_and(|(array_id, _)| queued.starts_with(&format!("{}_[", array_id)))
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Formats time as "YYYY-MM-DD HH:MM" in UTC.
+pub fn format_time(time: u64) -> String {
+    let (year, month, day) = civil_from_days((time / 86400) as i64);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        time % 86400 / 3600,
+        time % 3600 / 60
+    )
+}
+
+/// Parses date as "YYYY-MM-DD" to the time of its beginning in UTC.
+pub fn parse_date(date: &str) -> Result<u64> {
+    let invalid = || eyre::eyre!("Invalid date: {}. Use YYYY-MM-DD.", date);
+    let parts: Vec<i64> = date
+        .split('-')
+        .map(|x| x.parse::<i64>().map_err(|_| invalid()))
+        .collect::<Result<_>>()?;
+    match parts[..] {
+        [year, month @ 1..=12, day @ 1..=31] if year >= 1970 => Ok(days_from_civil(year, month, day) as u64 * 86400),
+        _ => Err(invalid()),
+    }
+}
+
+/// Days since 1970-01-01 of the date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Date (year, month, day) of the days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+mod tests {
+    #[test]
+    fn test_formula_and_dates() {
+        use super::*;
+        assert_eq!(reduced_formula("Na4Cl4"), "NaCl");
+        assert_eq!(reduced_formula("Li2Fe2P2O8"), "LiFePO4");
+        assert_eq!(reduced_formula("H2O"), "H2O");
+        assert_eq!(parse_date("2024-03-01").unwrap(), 1709251200);
+        assert_eq!(format_time(1709251200 + 3723), "2024-03-01 01:02");
+        assert!(parse_date("2024-13-01").is_err());
+        assert!(is_same_job("42_[3-9]", "42_5"));
+        assert!(!is_same_job("421_1", "42_1"));
+    }
+}
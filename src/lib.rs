@@ -1,4 +1,9 @@
 pub mod check_util;
 pub mod cli;
 pub mod config_parser;
+pub mod job_db;
+pub mod output_parser;
+pub mod structure_io;
+pub mod structure_util;
 pub mod template;
+pub mod user_config;
@@ -2,28 +2,12 @@ pub mod check_util;
 pub mod cli;
 
 use clap::Parser;
-use home;
 use std::error::Error;
-use std::path::Path;
-use vasp_manager::{config_parser, template};
+use vasp_manager::{config_parser, job_db, output_parser, structure_util, template, user_config};
 
 fn check_setup_status() -> Result<(), String> {
-    let home_dir = home::home_dir().unwrap();
-    // Check vasp binary
-    let vasp_dir = home_dir.join(".local/VASP/bin");
-    if !vasp_dir.exists() {
-        return Err(String::from("VASP binaries are not set up."));
-    }
-    let potcar_path_prefix = std::env::var("POTCAR_PATH_PREFIX");
-    if potcar_path_prefix.is_err() {
-        return Err(String::from("POTCAR_PATH_PREFIX is not set up."));
-    } else {
-        let potcar_path = potcar_path_prefix.unwrap();
-        let potcar_path = Path::new(&potcar_path);
-        if !&potcar_path.exists() || !&potcar_path.is_dir() || &potcar_path.read_dir().unwrap().count() == &0 {
-            return Err(String::from("POTCAR_PATH is not properly set up."));
-        }
-    }
+    check_util::check_vasp_setup()?;
+    check_util::check_potcar_dir()?;
     Ok(())
 }
 
@@ -35,12 +19,16 @@ fn main() -> Result<(), Box<dyn Error>> {
         std::process::exit(0);
     }
 
-    match check_setup_status() {
-        Ok(_) => (),
-        Err(e) => {
+    // Only commands generating VASP inputs need the binaries and POTCARs.
+    let requires_setup = matches!(
+        &args.command,
+        Some(cli::Command::Create(_) | cli::Command::Continue(_) | cli::Command::Converge(_) | cli::Command::Sweep(_))
+    );
+    if requires_setup {
+        if let Err(e) = check_setup_status() {
             println!("Setup error: {}", e);
             println!("It seems that setup is not done");
-            println!("Run `vasp_manager --guide ` to see hot to set up VASP binaries and POTCAR_PATH.");
+            println!("Run `vasp_manager doctor` to check the setup, and `vasp_manager --show-guide` to see how to set up VASP binaries and POTCAR_PATH_PREFIX.");
             std::process::exit(1);
         }
     }
@@ -51,6 +39,21 @@ fn main() -> Result<(), Box<dyn Error>> {
         Some(cli::Command::Submit(submit_job)) => submit_job.run()?,
         Some(cli::Command::Continue(continue_job)) => continue_job.run()?,
         Some(cli::Command::ShowIncar(show_incar)) => show_incar.run()?,
+        Some(cli::Command::Summary(summary)) => summary.run()?,
+        Some(cli::Command::Export(export)) => export.run()?,
+        Some(cli::Command::Check(check)) => check.run()?,
+        Some(cli::Command::Converge(converge)) => converge.run()?,
+        Some(cli::Command::Sweep(sweep)) => sweep.run()?,
+        Some(cli::Command::Freq(freq)) => freq.run()?,
+        Some(cli::Command::Dos(dos)) => dos.run()?,
+        Some(cli::Command::ForceSets(force_sets)) => force_sets.run()?,
+        Some(cli::Command::Eos(eos)) => eos.run()?,
+        Some(cli::Command::Elastic(elastic)) => elastic.run()?,
+        Some(cli::Command::Chgsum(chgsum)) => chgsum.run()?,
+        Some(cli::Command::Bader(bader)) => bader.run()?,
+        Some(cli::Command::Db(db)) => db.run()?,
+        Some(cli::Command::Verify(verify)) => verify.run()?,
+        Some(cli::Command::Doctor(doctor)) => doctor.run()?,
         None => (),
     }
     Ok(())
@@ -0,0 +1,271 @@
+use crate::structure_util::atom_species;
+use eyre::Result;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+use vasp_files::poscar::Poscar;
+
+/// Bader charge of an atom from ACF.dat.
+#[derive(Debug, Clone, Serialize)]
+pub struct BaderAtom {
+    pub element: String,
+    /// Cartesian position (Angstrom).
+    pub position: [f64; 3],
+    /// Electrons in the Bader volume.
+    pub charge: f64,
+    /// Valence (ZVAL of POTCAR) minus the Bader charge. Positive for cations.
+    pub net_charge: f64,
+    pub min_dist: f64,
+    pub volume: f64,
+}
+
+/// Net charges of an element.
+#[derive(Debug, Clone, Serialize)]
+pub struct ElementCharge {
+    pub element: String,
+    pub count: usize,
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Bader analysis of a job, combining ACF.dat of the `bader` program with the valences of POTCAR.
+#[derive(Debug, Clone, Serialize)]
+pub struct Bader {
+    pub directory: String,
+    pub atoms: Vec<BaderAtom>,
+}
+
+impl Bader {
+    /// Reads ACF.dat, POTCAR and POSCAR in `dir`.
+    pub fn from_dir(dir: &str) -> Result<Bader> {
+        let path = Path::new(dir);
+        let read = |name: &str| {
+            std::fs::read_to_string(path.join(name))
+                .map_err(|_| eyre::eyre!("Could not read {}", path.join(name).display()))
+        };
+        let zvals = potcar_zvals(&read("POTCAR")?);
+        let poscar = Poscar::from_file(path.join("POSCAR"))?;
+        if zvals.len() != poscar.species.len() {
+            return Err(eyre::eyre!(
+                "POTCAR has {} elements, but POSCAR has {}",
+                zvals.len(),
+                poscar.species.len()
+            ));
+        }
+        let valences: Vec<f64> = poscar
+            .num_atoms
+            .iter()
+            .zip(&zvals)
+            .flat_map(|(&n, &zval)| vec![zval; n])
+            .collect();
+        let species = atom_species(&poscar);
+
+        let mut atoms = vec![];
+        for line in read("ACF.dat")?.lines() {
+            let values: Vec<f64> = line.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+            // Rows of atoms: index, x, y, z, charge, min dist, volume.
+            if values.len() != 7 || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let i = atoms.len();
+            if i >= species.len() {
+                return Err(eyre::eyre!("ACF.dat has more atoms than POSCAR"));
+            }
+            atoms.push(BaderAtom {
+                element: species[i].clone(),
+                position: [values[1], values[2], values[3]],
+                charge: values[4],
+                net_charge: valences[i] - values[4],
+                min_dist: values[5],
+                volume: values[6],
+            });
+        }
+        if atoms.len() != species.len() {
+            return Err(eyre::eyre!(
+                "ACF.dat has {} atoms, but POSCAR has {}",
+                atoms.len(),
+                species.len()
+            ));
+        }
+        Ok(Bader {
+            directory: dir.to_string(),
+            atoms,
+        })
+    }
+
+    /// Net charges of each element, in order of appearance.
+    pub fn element_charges(&self) -> Vec<ElementCharge> {
+        let mut elements: Vec<String> = vec![];
+        for atom in &self.atoms {
+            if !elements.contains(&atom.element) {
+                elements.push(atom.element.clone());
+            }
+        }
+        elements
+            .into_iter()
+            .map(|element| {
+                let charges: Vec<f64> = self
+                    .atoms
+                    .iter()
+                    .filter(|atom| atom.element == element)
+                    .map(|atom| atom.net_charge)
+                    .collect();
+                ElementCharge {
+                    count: charges.len(),
+                    mean: charges.iter().sum::<f64>() / charges.len() as f64,
+                    min: charges.iter().cloned().fold(f64::INFINITY, f64::min),
+                    max: charges.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+                    element,
+                }
+            })
+            .collect()
+    }
+
+    /// Per-atom charges in CSV format.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("index,element,x,y,z,charge,net_charge,min_dist,volume\n");
+        for (i, atom) in self.atoms.iter().enumerate() {
+            let [x, y, z] = atom.position;
+            csv.push_str(&format!(
+                "{},{},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6}\n",
+                i + 1,
+                atom.element,
+                x,
+                y,
+                z,
+                atom.charge,
+                atom.net_charge,
+                atom.min_dist,
+                atom.volume
+            ));
+        }
+        csv
+    }
+}
+
+impl fmt::Display for Bader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.directory)?;
+        writeln!(f, "{:>6} {:>8} {:>10} {:>10}", "atom", "element", "charge", "net")?;
+        for (i, atom) in self.atoms.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>6} {:>8} {:>10.4} {:>+10.4}",
+                i + 1,
+                atom.element,
+                atom.charge,
+                atom.net_charge
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:>8} {:>6} {:>10} {:>10} {:>10}",
+            "element", "count", "mean", "min", "max"
+        )?;
+        for element in self.element_charges() {
+            writeln!(
+                f,
+                "{:>8} {:>6} {:>+10.4} {:>+10.4} {:>+10.4}",
+                element.element, element.count, element.mean, element.min, element.max
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Valence (ZVAL) of each element in POTCAR, in order.
+/// ZVAL is given once per element, as in `POMASS =   15.999; ZVAL   =    6.000    mass and valenz`.
+pub fn potcar_zvals(potcar: &str) -> Vec<f64> {
+    potcar
+        .lines()
+        .filter_map(|line| {
+            let after = &line[line.find("ZVAL")? + 4..];
+            after
+                .trim_start()
+                .strip_prefix('=')?
+                .split_whitespace()
+                .next()?
+                .parse()
+                .ok()
+        })
+        .collect()
+}
+
+mod tests {
+    #[test]
+    fn test_potcar_zvals() {
+        use super::*;
+        let potcar = "  PAW_PBE Mg_pv 06Sep2000
+ 8.00000000000000000
+ parameters from PSCTR are:
+   VRHFIN =Mg: p3s
+   LEXCH  = PE
+   POMASS =   24.305; ZVAL   =    8.000    mass and valenz
+ End of Dataset
+  PAW_PBE O 08Apr2002
+ 6.00000000000000000
+ parameters from PSCTR are:
+   VRHFIN =O: s2p4
+   POMASS =   16.000; ZVAL   =    6.000    mass and valenz
+ End of Dataset
+";
+        assert_eq!(potcar_zvals(potcar), vec![8.0, 6.0]);
+    }
+
+    #[test]
+    fn test_bader_from_dir() {
+        use super::*;
+        let dir = std::env::temp_dir().join(format!("vasp_manager_test_bader_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("POSCAR"),
+            "MgO\n1.0\n4.212 0.0 0.0\n0.0 4.212 0.0\n0.0 0.0 4.212\nMg O\n1 2\nDirect\n\
+             0.0 0.0 0.0\n0.5 0.5 0.5\n0.5 0.0 0.0\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("POTCAR"),
+            "  PAW_PBE Mg_pv 06Sep2000
+   POMASS =   24.305; ZVAL   =    8.000    mass and valenz
+ End of Dataset
+  PAW_PBE O 08Apr2002
+   POMASS =   16.000; ZVAL   =    6.000    mass and valenz
+ End of Dataset
+",
+        )
+        .unwrap();
+        let acf = "    #         X           Y           Z       CHARGE      MIN DIST   ATOMIC VOL
+ --------------------------------------------------------------------------------
+    1    0.0000      0.0000      0.0000      6.3012      0.9560     7.6123
+    2    2.1060      2.1060      2.1060      7.6500      1.0987    11.0877
+    3    2.1060      0.0000      0.0000      7.7488      1.0987    11.0877
+ --------------------------------------------------------------------------------
+    VACUUM CHARGE:               0.0000
+    VACUUM VOLUME:               0.0000
+    NUMBER OF ELECTRONS:        21.7000
+";
+        std::fs::write(dir.join("ACF.dat"), acf).unwrap();
+        let bader = Bader::from_dir(dir.to_str().unwrap()).unwrap();
+        assert_eq!(bader.atoms.len(), 3);
+        assert_eq!(bader.atoms[0].element, "Mg");
+        assert_eq!(bader.atoms[1].position, [2.106, 2.106, 2.106]);
+        assert!((bader.atoms[0].net_charge - 1.6988).abs() < 1e-9);
+        assert!((bader.atoms[1].net_charge + 1.65).abs() < 1e-9);
+        assert!((bader.atoms[2].net_charge + 1.7488).abs() < 1e-9);
+        let oxygen = &bader.element_charges()[1];
+        assert_eq!((oxygen.element.as_str(), oxygen.count), ("O", 2));
+        assert!((oxygen.mean + 1.6994).abs() < 1e-9);
+
+        // Atom count of ACF.dat differs from POSCAR.
+        let truncated: String = acf
+            .lines()
+            .filter(|line| !line.starts_with("    3"))
+            .map(|line| line.to_string() + "\n")
+            .collect();
+        std::fs::write(dir.join("ACF.dat"), truncated).unwrap();
+        assert!(Bader::from_dir(dir.to_str().unwrap()).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
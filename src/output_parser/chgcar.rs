@@ -0,0 +1,162 @@
+use eyre::Result;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Charge density on the FFT grid, as in CHGCAR and AECCAR files.
+/// Only the total density is kept. Augmentation occupancies and magnetization densities are dropped.
+#[derive(Debug, Clone)]
+pub struct ChargeDensity {
+    /// Structure part of the file, up to the blank line before the grid dimensions.
+    pub header: String,
+    pub grid: [usize; 3],
+    /// Density values, with the first grid index running fastest.
+    pub values: Vec<f64>,
+}
+
+impl ChargeDensity {
+    /// Reads and parses CHGCAR-like file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<ChargeDensity> {
+        let contents =
+            std::fs::read_to_string(&path).map_err(|_| eyre::eyre!("Could not read {}", path.as_ref().display()))?;
+        ChargeDensity::parse(&contents)
+    }
+
+    /// Parses contents of CHGCAR-like file.
+    pub fn parse(contents: &str) -> Result<ChargeDensity> {
+        let invalid = || eyre::eyre!("Invalid charge density file");
+        let mut lines = contents.lines();
+        let mut header = String::new();
+        // Structure ends with a blank line after the positions, which start at line 9 (10 with Selective dynamics).
+        for (n, line) in lines.by_ref().enumerate() {
+            if n >= 8 && line.trim().is_empty() {
+                break;
+            }
+            header.push_str(line);
+            header.push('\n');
+        }
+        let grid: Vec<usize> = lines
+            .next()
+            .ok_or_else(invalid)?
+            .split_whitespace()
+            .map(|n| n.parse().map_err(|_| invalid()))
+            .collect::<Result<_>>()?;
+        let grid: [usize; 3] = grid.try_into().map_err(|_| invalid())?;
+        let num_values = grid.iter().product();
+        let mut values = Vec::with_capacity(num_values);
+        for line in lines {
+            for value in line.split_whitespace() {
+                values.push(value.parse::<f64>().map_err(|_| invalid())?);
+            }
+            if values.len() >= num_values {
+                break;
+            }
+        }
+        if values.len() != num_values {
+            return Err(eyre::eyre!(
+                "Expected {} values of charge density, found {}",
+                num_values,
+                values.len()
+            ));
+        }
+        Ok(ChargeDensity { header, grid, values })
+    }
+
+    /// Sum of two densities on the same grid, with the header of `self`.
+    pub fn add(&self, other: &ChargeDensity) -> Result<ChargeDensity> {
+        if self.grid != other.grid {
+            return Err(eyre::eyre!(
+                "Grids of charge densities differ: {:?} and {:?}",
+                self.grid,
+                other.grid
+            ));
+        }
+        Ok(ChargeDensity {
+            header: self.header.clone(),
+            grid: self.grid,
+            values: self.values.iter().zip(&other.values).map(|(a, b)| a + b).collect(),
+        })
+    }
+
+    /// Writes the density in CHGCAR format.
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut contents = self.header.clone();
+        writeln!(contents)?;
+        writeln!(contents, "{:5}{:5}{:5}", self.grid[0], self.grid[1], self.grid[2])?;
+        for chunk in self.values.chunks(5) {
+            for value in chunk {
+                write!(contents, " {}", fortran_exponent(*value))?;
+            }
+            writeln!(contents)?;
+        }
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+/// Formats a number like Fortran `E18.11`, e.g. `0.12345678901E+01`.
+fn fortran_exponent(value: f64) -> String {
+    if value == 0.0 {
+        return String::from("0.00000000000E+00");
+    }
+    let exponent = value.abs().log10().floor() as i32 + 1;
+    let mantissa = value / 10f64.powi(exponent);
+    // Rounding may carry the mantissa up to 1.0.
+    let (mantissa, exponent) = match format!("{:.11}", mantissa.abs()).starts_with('1') {
+        true => (mantissa / 10.0, exponent + 1),
+        false => (mantissa, exponent),
+    };
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{:.11}E{}{:02}", mantissa, sign, exponent.abs())
+}
+
+mod tests {
+    #[test]
+    fn test_charge_density_round_trip() {
+        use super::*;
+        let contents = "Si
+   5.43000000000000
+     0.000000    2.715000    2.715000
+     2.715000    0.000000    2.715000
+     2.715000    2.715000    0.000000
+   Si
+     2
+Direct
+  0.000000  0.000000  0.000000
+  0.250000  0.250000  0.250000
+
+    2    2    2
+ 0.12345678901E+01 -0.50000000000E-02 0.00000000000E+00 0.12500000000E+03 0.10000000000E+01
+ 0.20000000000E+01 0.30000000000E+01 0.40000000000E+01
+augmentation occupancies   1  15
+";
+        let density = ChargeDensity::parse(contents).unwrap();
+        assert_eq!(density.grid, [2, 2, 2]);
+        assert_eq!(density.values.len(), 8);
+        assert_eq!(density.values[1], -0.005);
+        assert!(density.header.ends_with("0.250000  0.250000  0.250000\n"));
+
+        let path = std::env::temp_dir().join(format!("vasp_manager_test_chgcar_{}", std::process::id()));
+        density.add(&density).unwrap().to_file(&path).unwrap();
+        let doubled = ChargeDensity::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(doubled.header, density.header);
+        assert_eq!(doubled.grid, density.grid);
+        let expected: Vec<f64> = density.values.iter().map(|v| v * 2.0).collect();
+        assert_eq!(doubled.values, expected);
+
+        assert!(ChargeDensity::parse(&contents.replace("    2    2    2", "    2    2    3")).is_err());
+    }
+
+    #[test]
+    fn test_fortran_exponent() {
+        use super::*;
+        assert_eq!(fortran_exponent(0.0), "0.00000000000E+00");
+        assert_eq!(fortran_exponent(1.0), "0.10000000000E+01");
+        assert_eq!(fortran_exponent(12.345678901), "0.12345678901E+02");
+        assert_eq!(fortran_exponent(-0.005), "-0.50000000000E-02");
+        // The mantissa rounding up to 1 carries into the exponent.
+        assert_eq!(fortran_exponent(9.9999999999e0), "0.99999999999E+01");
+        assert_eq!(fortran_exponent(9.99999999999e0), "0.10000000000E+02");
+        assert_eq!(fortran_exponent(-9.999999999999e-3), "-0.10000000000E-01");
+    }
+}
@@ -0,0 +1,312 @@
+use super::outcar::Outcar;
+use eyre::Result;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+/// Convergence verdict of a VASP run.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConvergenceCheck {
+    pub directory: String,
+    /// Whether the run terminated normally.
+    pub finished: bool,
+    /// Whether the run is an ionic relaxation (IBRION = 1, 2 or 3 and NSW > 0).
+    pub is_relaxation: bool,
+    pub ediffg: Option<f64>,
+    pub nsw: Option<usize>,
+    pub nelm: Option<usize>,
+    pub num_ionic_steps: usize,
+    /// Largest atomic force of the last ionic step (eV/Angstrom).
+    pub max_force: Option<f64>,
+    /// Energy change between the last two ionic steps (eV).
+    pub energy_change: Option<f64>,
+    pub ionic_converged: bool,
+    /// Whether the electronic loop of the last ionic step reached EDIFF.
+    pub electronic_converged: bool,
+    /// Whether the relaxation stopped because NSW was exhausted.
+    pub hit_nsw: bool,
+    /// Ionic steps (1-based) whose electronic loop stopped at NELM without reaching EDIFF.
+    pub nelm_capped_steps: Vec<usize>,
+    pub issues: Vec<String>,
+}
+
+impl ConvergenceCheck {
+    /// Checks convergence of the run in `dir` from its OUTCAR.
+    pub fn from_dir(dir: &str) -> Result<ConvergenceCheck> {
+        let outcar = Outcar::from_file(Path::new(dir).join("OUTCAR"))?;
+        Ok(ConvergenceCheck::new(dir, &outcar))
+    }
+
+    /// Check of a run whose output could not be read, not converged with the error as its issue.
+    pub fn unreadable(dir: &str, error: &eyre::Report) -> ConvergenceCheck {
+        ConvergenceCheck {
+            directory: dir.to_string(),
+            finished: false,
+            is_relaxation: false,
+            ediffg: None,
+            nsw: None,
+            nelm: None,
+            num_ionic_steps: 0,
+            max_force: None,
+            energy_change: None,
+            ionic_converged: false,
+            electronic_converged: false,
+            hit_nsw: false,
+            nelm_capped_steps: vec![],
+            issues: vec![error.to_string()],
+        }
+    }
+
+    pub fn new(dir: &str, outcar: &Outcar) -> ConvergenceCheck {
+        let ediffg = outcar.parameter("EDIFFG");
+        let nsw = outcar.parameter("NSW").map(|n| n as usize);
+        let nelm = outcar.parameter("NELM").map(|n| n as usize);
+        let ibrion = outcar.parameter("IBRION").map(|n| n as i64);
+        let is_relaxation = nsw.unwrap_or(0) > 0 && matches!(ibrion, Some(1..=3));
+
+        let completed: Vec<_> = outcar
+            .ionic_steps
+            .iter()
+            .filter(|step| step.free_energy.is_some())
+            .collect();
+        let last = completed.last();
+        let max_force = last.and_then(|step| step.max_force());
+        let energy_change = match completed.len() {
+            n if n >= 2 => Some(completed[n - 1].free_energy.unwrap() - completed[n - 2].free_energy.unwrap()),
+            _ => None,
+        };
+        let electronic_converged = last.is_some_and(|step| step.electronic_converged);
+        let nelm_capped_steps: Vec<usize> = completed
+            .iter()
+            .enumerate()
+            .filter(|(_, step)| !step.electronic_converged && nelm.is_none_or(|n| step.num_electronic_steps >= n))
+            .map(|(i, _)| i + 1)
+            .collect();
+
+        // VASP ignores forces on fixed atoms, so its own verdict takes precedence.
+        let criterion_met = match ediffg {
+            Some(ediffg) if ediffg < 0.0 => max_force.is_some_and(|f| f <= ediffg.abs()),
+            Some(ediffg) => energy_change.is_some_and(|de| de.abs() < ediffg),
+            None => false,
+        };
+        let ionic_converged = !is_relaxation || outcar.ionic_converged || criterion_met;
+        let hit_nsw = is_relaxation && !ionic_converged && nsw.is_some_and(|n| completed.len() >= n);
+
+        let mut issues = vec![];
+        if !outcar.finished {
+            issues.push(String::from("Run did not terminate normally (or is still running)."));
+        }
+        if !ionic_converged {
+            let criterion = match ediffg {
+                Some(ediffg) if ediffg < 0.0 => format!(
+                    "max force {} eV/A > |EDIFFG| = {}",
+                    max_force.map_or(String::from("-"), |f| format!("{:.4}", f)),
+                    ediffg.abs()
+                ),
+                Some(ediffg) => format!(
+                    "energy change {} eV >= EDIFFG = {}",
+                    energy_change.map_or(String::from("-"), |de| format!("{:.2e}", de.abs())),
+                    ediffg
+                ),
+                None => String::from("EDIFFG not found"),
+            };
+            issues.push(format!("Ionic relaxation not converged: {}.", criterion));
+        }
+        if hit_nsw {
+            issues.push(format!("Relaxation exhausted NSW = {} ionic steps.", nsw.unwrap()));
+        }
+        if !nelm_capped_steps.is_empty() {
+            issues.push(format!(
+                "Electronic loop hit NELM = {} in {} ionic step(s).",
+                nelm.map_or(String::from("-"), |n| n.to_string()),
+                nelm_capped_steps.len()
+            ));
+        }
+        if last.is_some() && !electronic_converged {
+            issues.push(String::from("SCF of the last ionic step is not converged."));
+        }
+        if last.is_none() {
+            issues.push(String::from("No ionic step has been completed."));
+        }
+
+        ConvergenceCheck {
+            directory: dir.to_string(),
+            finished: outcar.finished,
+            is_relaxation,
+            ediffg,
+            nsw,
+            nelm,
+            num_ionic_steps: completed.len(),
+            max_force,
+            energy_change,
+            ionic_converged,
+            electronic_converged,
+            hit_nsw,
+            nelm_capped_steps,
+            issues,
+        }
+    }
+
+    /// Whether the run is converged without any issue.
+    pub fn converged(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for ConvergenceCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = match self.converged() {
+            true => "CONVERGED",
+            false => "NOT CONVERGED",
+        };
+        writeln!(f, "{}: {}", self.directory, verdict)?;
+        for issue in &self.issues {
+            writeln!(f, "  - {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_converged_relaxation() {
+        use super::*;
+        let contents = "   NELM   =     60;   NELMIN=  2; NELMDL= -5     # of ELM steps
+   NSW    =     10    number of steps for IOM
+   EDIFFG = -.2E-01   stopping-criterion for IOM
+   IBRION =      2    ionic relax: 0-MD 1-quasi-New 2-CG
+ ----------------------------------------- Iteration    1(   1)  ---------------------------------------
+ ----------------------------------------- Iteration    1(   2)  ---------------------------------------
+ ------------------------ aborting loop because EDIFF is reached ----------------------------------------
+ POSITION                                       TOTAL-FORCE (eV/Angst)
+ -----------------------------------------------------------------------------------
+      0.00000      0.00000      0.00000         0.000000      0.000000      0.100000
+ -----------------------------------------------------------------------------------
+  free  energy   TOTEN  =       -10.00000000 eV
+     LOOP+:  cpu time      1.00: real time      1.00
+ ----------------------------------------- Iteration    2(   1)  ---------------------------------------
+ ------------------------ aborting loop because EDIFF is reached ----------------------------------------
+ POSITION                                       TOTAL-FORCE (eV/Angst)
+ -----------------------------------------------------------------------------------
+      0.00000      0.00000      0.05000         0.000000      0.000000      0.010000
+ -----------------------------------------------------------------------------------
+  free  energy   TOTEN  =       -10.01000000 eV
+     LOOP+:  cpu time      1.00: real time      1.00
+ reached required accuracy - stopping structural energy minimisation
+ General timing and accounting informations for this job:
+";
+        let check = ConvergenceCheck::new("relax", &Outcar::parse(contents));
+        assert!(check.is_relaxation);
+        assert_eq!(check.ediffg, Some(-0.02));
+        assert_eq!(check.num_ionic_steps, 2);
+        assert_eq!(check.max_force, Some(0.01));
+        assert!((check.energy_change.unwrap() + 0.01).abs() < 1e-12);
+        assert!(check.ionic_converged && check.electronic_converged && !check.hit_nsw);
+        assert!(check.converged(), "{}", check);
+    }
+
+    #[test]
+    fn test_hit_nsw() {
+        use super::*;
+        let contents = "   NELM   =     60;   NELMIN=  2; NELMDL= -5     # of ELM steps
+   NSW    =      2    number of steps for IOM
+   EDIFFG = -.2E-01   stopping-criterion for IOM
+   IBRION =      1    ionic relax: 0-MD 1-quasi-New 2-CG
+ ----------------------------------------- Iteration    1(   1)  ---------------------------------------
+ ------------------------ aborting loop because EDIFF is reached ----------------------------------------
+ POSITION                                       TOTAL-FORCE (eV/Angst)
+ -----------------------------------------------------------------------------------
+      0.00000      0.00000      0.00000         0.000000      0.000000      0.100000
+ -----------------------------------------------------------------------------------
+  free  energy   TOTEN  =       -10.00000000 eV
+     LOOP+:  cpu time      1.00: real time      1.00
+ ----------------------------------------- Iteration    2(   1)  ---------------------------------------
+ ------------------------ aborting loop because EDIFF is reached ----------------------------------------
+ POSITION                                       TOTAL-FORCE (eV/Angst)
+ -----------------------------------------------------------------------------------
+      0.00000      0.00000      0.05000         0.030000      0.000000      0.040000
+ -----------------------------------------------------------------------------------
+  free  energy   TOTEN  =       -10.01000000 eV
+     LOOP+:  cpu time      1.00: real time      1.00
+ General timing and accounting informations for this job:
+";
+        let check = ConvergenceCheck::new("relax", &Outcar::parse(contents));
+        assert_eq!(check.nsw, Some(2));
+        assert!(!check.ionic_converged);
+        assert!(check.hit_nsw);
+        assert!(!check.converged());
+        let issues = check.issues.join("\n");
+        assert!(issues.contains("max force 0.0500 eV/A > |EDIFFG| = 0.02"));
+        assert!(issues.contains("Relaxation exhausted NSW = 2 ionic steps."));
+    }
+
+    #[test]
+    fn test_ediffg_criterion() {
+        use super::*;
+        // Negative EDIFFG is a criterion on forces, met here without the verdict of VASP.
+        let contents = "   NSW    =     10    number of steps for IOM
+   EDIFFG = -.2E-01   stopping-criterion for IOM
+   IBRION =      2    ionic relax: 0-MD 1-quasi-New 2-CG
+ ------------------------ aborting loop because EDIFF is reached ----------------------------------------
+ POSITION                                       TOTAL-FORCE (eV/Angst)
+ -----------------------------------------------------------------------------------
+      0.00000      0.00000      0.00000         0.000000      0.000000      0.500000
+ -----------------------------------------------------------------------------------
+  free  energy   TOTEN  =       -10.00000000 eV
+     LOOP+:  cpu time      1.00: real time      1.00
+ ------------------------ aborting loop because EDIFF is reached ----------------------------------------
+ POSITION                                       TOTAL-FORCE (eV/Angst)
+ -----------------------------------------------------------------------------------
+      0.00000      0.00000      0.05000         0.000000      0.000000      0.015000
+ -----------------------------------------------------------------------------------
+  free  energy   TOTEN  =       -10.00050000 eV
+     LOOP+:  cpu time      1.00: real time      1.00
+ General timing and accounting informations for this job:
+";
+        let check = ConvergenceCheck::new("relax", &Outcar::parse(contents));
+        assert!(check.ionic_converged);
+        assert!(check.converged(), "{}", check);
+
+        // Positive EDIFFG is a criterion on the energy change, regardless of forces.
+        let energy_criterion = contents.replace("-.2E-01", "0.1E-02").replace("0.015000", "0.500000");
+        let check = ConvergenceCheck::new("relax", &Outcar::parse(&energy_criterion));
+        assert_eq!(check.ediffg, Some(0.001));
+        assert_eq!(check.max_force, Some(0.5));
+        assert!(check.ionic_converged);
+        assert!(check.converged(), "{}", check);
+
+        let large_change = energy_criterion.replace("-10.00050000", "-10.01000000");
+        let check = ConvergenceCheck::new("relax", &Outcar::parse(&large_change));
+        assert!(!check.ionic_converged);
+        assert!(!check.hit_nsw);
+        assert!(check.issues[0].contains("energy change 1.00e-2 eV >= EDIFFG = 0.001"));
+    }
+
+    #[test]
+    fn test_nelm_capped() {
+        use super::*;
+        let contents = "   NELM   =      3;   NELMIN=  2; NELMDL= -5     # of ELM steps
+   NSW    =      0    number of steps for IOM
+   IBRION =     -1    ionic relax: 0-MD 1-quasi-New 2-CG
+ ----------------------------------------- Iteration    1(   1)  ---------------------------------------
+ ----------------------------------------- Iteration    1(   2)  ---------------------------------------
+ ----------------------------------------- Iteration    1(   3)  ---------------------------------------
+ POSITION                                       TOTAL-FORCE (eV/Angst)
+ -----------------------------------------------------------------------------------
+      0.00000      0.00000      0.00000         0.000000      0.000000      0.000000
+ -----------------------------------------------------------------------------------
+  free  energy   TOTEN  =       -10.00000000 eV
+     LOOP+:  cpu time      1.00: real time      1.00
+ General timing and accounting informations for this job:
+";
+        let check = ConvergenceCheck::new("scf", &Outcar::parse(contents));
+        assert!(!check.is_relaxation);
+        assert!(check.ionic_converged);
+        assert_eq!(check.nelm_capped_steps, vec![1]);
+        assert!(!check.electronic_converged);
+        assert!(!check.converged());
+        let issues = check.issues.join("\n");
+        assert!(issues.contains("Electronic loop hit NELM = 3 in 1 ionic step(s)."));
+        assert!(issues.contains("SCF of the last ionic step is not converged."));
+    }
+}
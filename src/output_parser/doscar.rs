@@ -0,0 +1,196 @@
+use eyre::Result;
+use serde::Serialize;
+use std::path::Path;
+
+/// Orbital names of lm-decomposed projections (LORBIT = 11, 12), in the order of DOSCAR.
+const LM_ORBITALS: [&str; 16] = [
+    "s", "py", "pz", "px", "dxy", "dyz", "dz2", "dxz", "dx2-y2", "fy3x2", "fxyz", "fyz2", "fz3", "fxz2", "fzx2", "fx3",
+];
+
+/// Density of states projected on an atom. Values are indexed by `[spin][orbital][energy]`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectedDos {
+    /// Orbital names, e.g. "s", "p", "d" or lm-decomposed "px", "dxy".
+    pub orbitals: Vec<String>,
+    pub dos: Vec<Vec<Vec<f64>>>,
+}
+
+/// Parsed DOSCAR. Total DOS is indexed by `[spin][energy]`.
+/// Noncollinear runs only keep the total (charge) part of the projections.
+#[derive(Debug, Clone, Serialize)]
+pub struct Doscar {
+    pub num_ions: usize,
+    pub fermi_energy: f64,
+    pub energies: Vec<f64>,
+    pub total: Vec<Vec<f64>>,
+    pub integrated: Vec<Vec<f64>>,
+    /// Empty unless DOSCAR was written with LORBIT.
+    pub projected: Vec<ProjectedDos>,
+}
+
+impl Doscar {
+    /// Reads and parses DOSCAR file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Doscar> {
+        let contents =
+            std::fs::read_to_string(&path).map_err(|_| eyre::eyre!("Could not read {}", path.as_ref().display()))?;
+        Doscar::parse(&contents)
+    }
+
+    /// Parses contents of DOSCAR.
+    pub fn parse(contents: &str) -> Result<Doscar> {
+        let invalid = || eyre::eyre!("Invalid DOSCAR");
+        let parse_line = |line: &str| -> Result<Vec<f64>> {
+            line.split_whitespace()
+                .map(|x| x.parse::<f64>().map_err(|_| invalid()))
+                .collect()
+        };
+        let lines: Vec<&str> = contents.lines().collect();
+        let first_line: Vec<&str> = lines.first().ok_or_else(invalid)?.split_whitespace().collect();
+        let num_ions: usize = first_line.first().and_then(|n| n.parse().ok()).ok_or_else(invalid)?;
+        // NCDIJ: 1 for non-spin-polarized, 2 for spin-polarized and 4 for noncollinear runs.
+        let ncdij: usize = first_line.get(3).and_then(|n| n.parse().ok()).unwrap_or(1);
+        let header = parse_line(lines.get(5).ok_or_else(invalid)?)?;
+        if header.len() < 4 {
+            return Err(invalid());
+        }
+        let nedos = header[2] as usize;
+        let fermi_energy = header[3];
+
+        let total_rows = lines
+            .get(6..6 + nedos)
+            .ok_or_else(invalid)?
+            .iter()
+            .map(|line| parse_line(line))
+            .collect::<Result<Vec<_>>>()?;
+        let num_spins = match total_rows[0].len() {
+            5 => 2,
+            3 => 1,
+            _ => return Err(invalid()),
+        };
+        let energies = total_rows.iter().map(|row| row[0]).collect();
+        let column = |rows: &[Vec<f64>], i: usize| -> Vec<f64> { rows.iter().map(|row| row[i]).collect() };
+        let total = (0..num_spins).map(|s| column(&total_rows, 1 + s)).collect();
+        let integrated = (0..num_spins).map(|s| column(&total_rows, 1 + num_spins + s)).collect();
+
+        let mut projected = vec![];
+        for ion in 0..num_ions {
+            let start = 6 + nedos + ion * (nedos + 1) + 1;
+            let rows = match lines.get(start..start + nedos) {
+                Some(rows) => rows.iter().map(|line| parse_line(line)).collect::<Result<Vec<_>>>()?,
+                None if ion == 0 => break,
+                None => return Err(eyre::eyre!("DOSCAR ends before projected DOS of ion {}", ion + 1)),
+            };
+            // Spin-polarized columns alternate up and down; noncollinear columns are total, mx, my, mz.
+            let stride = match ncdij {
+                2 => 2,
+                4 => 4,
+                _ => 1,
+            };
+            let num_orbitals = (rows[0].len() - 1) / stride;
+            let orbitals = match num_orbitals {
+                3 => vec!["s", "p", "d"],
+                4 => vec!["s", "p", "d", "f"],
+                9 | 16 => LM_ORBITALS[..num_orbitals].to_vec(),
+                _ => return Err(eyre::eyre!("Unknown number of projected orbitals: {}", num_orbitals)),
+            };
+            let num_projected_spins = if ncdij == 2 { 2 } else { 1 };
+            let dos = (0..num_projected_spins)
+                .map(|s| (0..num_orbitals).map(|o| column(&rows, 1 + o * stride + s)).collect())
+                .collect();
+            projected.push(ProjectedDos {
+                orbitals: orbitals.into_iter().map(String::from).collect(),
+                dos,
+            });
+        }
+
+        Ok(Doscar {
+            num_ions,
+            fermi_energy,
+            energies,
+            total,
+            integrated,
+            projected,
+        })
+    }
+
+    pub fn is_spin_polarized(&self) -> bool {
+        self.total.len() == 2
+    }
+
+    /// Energies relative to the Fermi level.
+    pub fn shifted_energies(&self) -> Vec<f64> {
+        self.energies.iter().map(|e| e - self.fermi_energy).collect()
+    }
+
+    /// Projected DOS summed over atoms of each element and over m of each angular momentum.
+    /// `species` is the element of each atom. Returns column names and values indexed by `[column][energy]`.
+    pub fn element_orbital_dos(&self, species: &[String]) -> Result<(Vec<String>, Vec<Vec<f64>>)> {
+        if species.len() != self.projected.len() {
+            return Err(eyre::eyre!(
+                "Number of atoms ({}) does not match projected DOS in DOSCAR ({})",
+                species.len(),
+                self.projected.len()
+            ));
+        }
+        let spin_labels: &[&str] = match self.projected.first().map(|p| p.dos.len()) {
+            Some(2) => &["_up", "_down"],
+            _ => &[""],
+        };
+        let mut names: Vec<String> = vec![];
+        let mut columns: Vec<Vec<f64>> = vec![];
+        for (element, projected) in species.iter().zip(&self.projected) {
+            for (s, spin_label) in spin_labels.iter().enumerate() {
+                for (orbital, values) in projected.orbitals.iter().zip(&projected.dos[s]) {
+                    // First letter of lm-decomposed orbital is the angular momentum.
+                    let name = format!("{}_{}{}", element, &orbital[..1], spin_label);
+                    let i = match names.iter().position(|n| n == &name) {
+                        Some(i) => i,
+                        None => {
+                            names.push(name);
+                            columns.push(vec![0.0; values.len()]);
+                            columns.len() - 1
+                        }
+                    };
+                    for (sum, value) in columns[i].iter_mut().zip(values) {
+                        *sum += value;
+                    }
+                }
+            }
+        }
+        Ok((names, columns))
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_parse_doscar() {
+        use super::*;
+        let contents = "       2       2       1       2
+  0.1E-01  0.1E-01  0.1E-01  0.1E-01  0.5E-15
+  1.0E-004
+  CAR
+ unknown system
+     10.0     -10.0       2      1.50000000    1.00000000
+    -10.000  0.1000E+00  0.2000E+00  0.1000E+00  0.2000E+00
+     10.000  0.3000E+00  0.4000E+00  0.4000E+00  0.6000E+00
+     10.0     -10.0       2      1.50000000    1.00000000
+    -10.000  0.1 0.2 0.3 0.4 0.5 0.6
+     10.000  0.1 0.2 0.3 0.4 0.5 0.6
+     10.0     -10.0       2      1.50000000    1.00000000
+    -10.000  1.0 2.0 3.0 4.0 5.0 6.0
+     10.000  1.0 2.0 3.0 4.0 5.0 6.0
+";
+        let doscar = Doscar::parse(contents).unwrap();
+        assert!(doscar.is_spin_polarized());
+        assert_eq!(doscar.shifted_energies(), vec![-11.5, 8.5]);
+        assert_eq!(doscar.total[1], vec![0.2, 0.4]);
+        assert_eq!(doscar.integrated[0], vec![0.1, 0.4]);
+        assert_eq!(doscar.projected[1].orbitals, vec!["s", "p", "d"]);
+        assert_eq!(doscar.projected[1].dos[1][2], vec![6.0, 6.0]);
+
+        let species = vec![String::from("O"), String::from("O")];
+        let (names, columns) = doscar.element_orbital_dos(&species).unwrap();
+        assert_eq!(names[..4], ["O_s_up", "O_p_up", "O_d_up", "O_s_down"]);
+        assert_eq!(columns[3], vec![2.2, 2.2]);
+    }
+}
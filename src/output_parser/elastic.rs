@@ -0,0 +1,146 @@
+use super::eos::solve;
+use eyre::Result;
+use serde::Serialize;
+use std::fmt;
+
+/// Elastic constants and the Voigt-Reuss-Hill averages of the moduli (GPa).
+#[derive(Debug, Clone, Serialize)]
+pub struct ElasticTensor {
+    /// Elastic constants in Voigt notation (xx, yy, zz, yz, xz, xy).
+    pub c: [[f64; 6]; 6],
+    pub bulk_voigt: f64,
+    pub bulk_reuss: f64,
+    pub bulk_hill: f64,
+    pub shear_voigt: f64,
+    pub shear_reuss: f64,
+    pub shear_hill: f64,
+}
+
+impl ElasticTensor {
+    /// Fits the elastic constants to stresses (GPa, Voigt notation, positive in tension) under strains
+    /// applied to a single Voigt component. Each sample is the strained component, the strain and the stress.
+    /// Cij is the slope of the stress component i against the strain j, so residual stress cancels out.
+    pub fn fit(samples: &[(usize, f64, [f64; 6])]) -> Result<ElasticTensor> {
+        let mut c = [[0.0; 6]; 6];
+        for j in 0..6 {
+            let strained: Vec<&(usize, f64, [f64; 6])> = samples.iter().filter(|(k, _, _)| *k == j).collect();
+            let strain_mean = strained.iter().map(|(_, e, _)| e).sum::<f64>() / strained.len() as f64;
+            let variance: f64 = strained.iter().map(|(_, e, _)| (e - strain_mean).powi(2)).sum();
+            if strained.len() < 2 || variance == 0.0 {
+                return Err(eyre::eyre!(
+                    "At least two different strains of component {} are required",
+                    j + 1
+                ));
+            }
+            for (i, row) in c.iter_mut().enumerate() {
+                let stress_mean = strained.iter().map(|(_, _, s)| s[i]).sum::<f64>() / strained.len() as f64;
+                let covariance: f64 = strained
+                    .iter()
+                    .map(|(_, e, s)| (e - strain_mean) * (s[i] - stress_mean))
+                    .sum();
+                row[j] = covariance / variance;
+            }
+        }
+        // Elastic constants are symmetric. Average out the numerical noise.
+        let transposed = c;
+        for (i, row) in c.iter_mut().enumerate() {
+            for (j, x) in row.iter_mut().enumerate() {
+                *x = (*x + transposed[j][i]) / 2.0;
+            }
+        }
+
+        let mut compliance = [[0.0; 6]; 6];
+        for j in 0..6 {
+            let augmented: [[f64; 7]; 6] = std::array::from_fn(|i| {
+                let mut row = [0.0; 7];
+                row[..6].copy_from_slice(&c[i]);
+                row[6] = if i == j { 1.0 } else { 0.0 };
+                row
+            });
+            let column = solve(augmented).ok_or_else(|| eyre::eyre!("Elastic tensor is singular"))?;
+            for (row, x) in compliance.iter_mut().zip(column) {
+                row[j] = x;
+            }
+        }
+
+        let bulk_voigt = ((c[0][0] + c[1][1] + c[2][2]) + 2.0 * (c[0][1] + c[1][2] + c[2][0])) / 9.0;
+        let shear_voigt = ((c[0][0] + c[1][1] + c[2][2]) - (c[0][1] + c[1][2] + c[2][0])
+            + 3.0 * (c[3][3] + c[4][4] + c[5][5]))
+            / 15.0;
+        let s = compliance;
+        let bulk_reuss = 1.0 / ((s[0][0] + s[1][1] + s[2][2]) + 2.0 * (s[0][1] + s[1][2] + s[2][0]));
+        let shear_reuss = 15.0
+            / (4.0 * (s[0][0] + s[1][1] + s[2][2]) - 4.0 * (s[0][1] + s[1][2] + s[2][0])
+                + 3.0 * (s[3][3] + s[4][4] + s[5][5]));
+        Ok(ElasticTensor {
+            c,
+            bulk_voigt,
+            bulk_reuss,
+            bulk_hill: (bulk_voigt + bulk_reuss) / 2.0,
+            shear_voigt,
+            shear_reuss,
+            shear_hill: (shear_voigt + shear_reuss) / 2.0,
+        })
+    }
+}
+
+impl fmt::Display for ElasticTensor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Elastic constants (GPa)")?;
+        for row in &self.c {
+            let row: Vec<String> = row.iter().map(|c| format!("{:9.2}", c)).collect();
+            writeln!(f, "{}", row.join(" "))?;
+        }
+        writeln!(f)?;
+        writeln!(f, "{:>14} {:>9} {:>9} {:>9}", "", "Voigt", "Reuss", "Hill")?;
+        writeln!(
+            f,
+            "{:>14} {:9.2} {:9.2} {:9.2}",
+            "Bulk modulus", self.bulk_voigt, self.bulk_reuss, self.bulk_hill
+        )?;
+        writeln!(
+            f,
+            "{:>14} {:9.2} {:9.2} {:9.2}",
+            "Shear modulus", self.shear_voigt, self.shear_reuss, self.shear_hill
+        )
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_fit_elastic_tensor() {
+        use super::*;
+        // Cubic elastic constants of Si (GPa).
+        let (c11, c12, c44) = (166.0, 64.0, 80.0);
+        let c: [[f64; 6]; 6] = std::array::from_fn(|i| {
+            std::array::from_fn(|j| match (i < 3, j < 3) {
+                (true, true) if i == j => c11,
+                (true, true) => c12,
+                (false, false) if i == j => c44,
+                _ => 0.0,
+            })
+        });
+        // Residual stress of the unstrained cell cancels out in the slopes.
+        let residual = [0.3, 0.3, 0.3, 0.0, 0.0, 0.0];
+        let samples: Vec<(usize, f64, [f64; 6])> = (0..6)
+            .flat_map(|j| {
+                [-0.01, -0.005, 0.005, 0.01].map(|e| (j, e, std::array::from_fn(|i| residual[i] + c[i][j] * e)))
+            })
+            .collect();
+        let tensor = ElasticTensor::fit(&samples).unwrap();
+        for (fitted, expected) in tensor.c.iter().flatten().zip(c.iter().flatten()) {
+            assert!((fitted - expected).abs() < 1e-8);
+        }
+        let bulk = (c11 + 2.0 * c12) / 3.0;
+        assert!((tensor.bulk_voigt - bulk).abs() < 1e-8);
+        assert!((tensor.bulk_reuss - bulk).abs() < 1e-8);
+        assert!((tensor.shear_voigt - (c11 - c12 + 3.0 * c44) / 5.0).abs() < 1e-8);
+        let shear_reuss = 5.0 * (c11 - c12) * c44 / (4.0 * c44 + 3.0 * (c11 - c12));
+        assert!((tensor.shear_reuss - shear_reuss).abs() < 1e-8);
+        assert!((tensor.shear_hill - (tensor.shear_voigt + shear_reuss) / 2.0).abs() < 1e-8);
+
+        // Each component needs two different strains.
+        let missing: Vec<_> = samples.iter().filter(|(j, _, _)| *j != 4).cloned().collect();
+        assert!(ElasticTensor::fit(&missing).is_err());
+    }
+}
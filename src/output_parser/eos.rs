@@ -0,0 +1,157 @@
+use eyre::Result;
+use serde::Serialize;
+use std::fmt;
+
+/// Conversion factor from eV/Angstrom^3 to GPa.
+pub const EV_PER_ANG3_TO_GPA: f64 = 160.21766208;
+
+/// Third-order Birch-Murnaghan equation of state.
+#[derive(Debug, Clone, Serialize)]
+pub struct BirchMurnaghan {
+    /// Minimum energy (eV).
+    pub e0: f64,
+    /// Equilibrium volume (Angstrom^3).
+    pub v0: f64,
+    /// Bulk modulus (GPa).
+    pub b0: f64,
+    /// Pressure derivative of the bulk modulus.
+    pub b0_prime: f64,
+}
+
+impl BirchMurnaghan {
+    /// Fits the equation of state to energies (eV) at volumes (Angstrom^3).
+    /// The Birch-Murnaghan energy is a cubic polynomial of V^(-2/3), which is fitted by linear least squares.
+    pub fn fit(volumes: &[f64], energies: &[f64]) -> Result<BirchMurnaghan> {
+        if volumes.len() < 4 || volumes.len() != energies.len() {
+            return Err(eyre::eyre!(
+                "At least 4 volumes are required to fit the equation of state"
+            ));
+        }
+        // Centered and scaled t = (x - mean) / scale, x = V^(-2/3), keeps the fit well conditioned.
+        let xs: Vec<f64> = volumes.iter().map(|v| v.powf(-2.0 / 3.0)).collect();
+        let mean = xs.iter().sum::<f64>() / xs.len() as f64;
+        let scale = xs.iter().map(|x| (x - mean).abs()).fold(0.0, f64::max);
+        if scale == 0.0 {
+            return Err(eyre::eyre!("Volumes should be different to fit the equation of state"));
+        }
+        let ts: Vec<f64> = xs.iter().map(|x| (x - mean) / scale).collect();
+        let [a, b, c, d] =
+            fit_cubic(&ts, energies).ok_or_else(|| eyre::eyre!("Could not fit the equation of state"))?;
+
+        // Minimum where dE/dt = b + 2ct + 3dt^2 = 0 and d2E/dt2 > 0, closest to the sampled volumes.
+        let roots: Vec<f64> = match d.abs() < 1e-12 * c.abs() {
+            true => vec![-b / (2.0 * c)],
+            false => {
+                let discriminant = 4.0 * c * c - 12.0 * b * d;
+                match discriminant >= 0.0 {
+                    true => vec![
+                        (-2.0 * c + discriminant.sqrt()) / (6.0 * d),
+                        (-2.0 * c - discriminant.sqrt()) / (6.0 * d),
+                    ],
+                    false => vec![],
+                }
+            }
+        };
+        let t0 = roots
+            .into_iter()
+            .filter(|&t| mean + scale * t > 0.0 && 2.0 * c + 6.0 * d * t > 0.0)
+            .min_by(|t, u| t.abs().partial_cmp(&u.abs()).unwrap())
+            .ok_or_else(|| eyre::eyre!("No energy minimum found. Check the volume range."))?;
+
+        let x0 = mean + scale * t0;
+        let v0 = x0.powf(-1.5);
+        let second = (2.0 * c + 6.0 * d * t0) / (scale * scale);
+        let third = 6.0 * d / scale.powi(3);
+        // B = V d2E/dV2 and B' = dB/dP, in terms of derivatives with respect to x.
+        let b0 = 4.0 * x0 * x0 * second / (9.0 * v0);
+        let b0_prime = 4.0 + 2.0 / 3.0 * x0 * third / second;
+        Ok(BirchMurnaghan {
+            e0: a + b * t0 + c * t0 * t0 + d * t0.powi(3),
+            v0,
+            b0: b0 * EV_PER_ANG3_TO_GPA,
+            b0_prime,
+        })
+    }
+
+    /// Energy (eV) at volume `v` (Angstrom^3).
+    pub fn energy(&self, v: f64) -> f64 {
+        let eta = (self.v0 / v).powf(2.0 / 3.0) - 1.0;
+        let b0 = self.b0 / EV_PER_ANG3_TO_GPA;
+        self.e0 + 9.0 * self.v0 * b0 / 16.0 * (eta.powi(3) * self.b0_prime + eta.powi(2) * (6.0 - 4.0 * (eta + 1.0)))
+    }
+}
+
+impl fmt::Display for BirchMurnaghan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Birch-Murnaghan equation of state")?;
+        writeln!(f, "E0  = {:.6} eV", self.e0)?;
+        writeln!(f, "V0  = {:.4} A^3", self.v0)?;
+        writeln!(f, "B0  = {:.2} GPa", self.b0)?;
+        writeln!(f, "B0' = {:.3}", self.b0_prime)
+    }
+}
+
+/// Least squares fit of y = a + bx + cx^2 + dx^3. Returns [a, b, c, d].
+fn fit_cubic(xs: &[f64], ys: &[f64]) -> Option<[f64; 4]> {
+    let mut normal = [[0.0; 5]; 4];
+    for (&x, y) in xs.iter().zip(ys) {
+        let powers = [1.0, x, x * x, x * x * x];
+        for i in 0..4 {
+            for j in 0..4 {
+                normal[i][j] += powers[i] * powers[j];
+            }
+            normal[i][4] += powers[i] * y;
+        }
+    }
+    solve(normal)
+}
+
+/// Solves linear equations given as augmented matrix by Gaussian elimination with partial pivoting.
+pub(super) fn solve<const N: usize, const M: usize>(mut matrix: [[f64; M]; N]) -> Option<[f64; N]> {
+    for col in 0..N {
+        let pivot = (col..N).max_by(|&a, &b| matrix[a][col].abs().partial_cmp(&matrix[b][col].abs()).unwrap())?;
+        if matrix[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(col, pivot);
+        let pivot_row = matrix[col];
+        for (row, values) in matrix.iter_mut().enumerate() {
+            if row != col {
+                let factor = values[col] / pivot_row[col];
+                for (x, p) in values[col..].iter_mut().zip(&pivot_row[col..]) {
+                    *x -= factor * p;
+                }
+            }
+        }
+    }
+    let mut solution = [0.0; N];
+    for (i, x) in solution.iter_mut().enumerate() {
+        *x = matrix[i][N] / matrix[i][i];
+    }
+    Some(solution)
+}
+
+mod tests {
+    #[test]
+    fn test_fit_birch_murnaghan() {
+        use super::*;
+        let expected = BirchMurnaghan {
+            e0: -10.8,
+            v0: 40.9,
+            b0: 98.0,
+            b0_prime: 4.2,
+        };
+        let volumes: Vec<f64> = (0..7).map(|i| expected.v0 * (0.94 + 0.02 * i as f64)).collect();
+        let energies: Vec<f64> = volumes.iter().map(|&v| expected.energy(v)).collect();
+        let fitted = BirchMurnaghan::fit(&volumes, &energies).unwrap();
+        assert!((fitted.e0 - expected.e0).abs() < 1e-8);
+        assert!((fitted.v0 - expected.v0).abs() < 1e-6);
+        assert!((fitted.b0 - expected.b0).abs() < 1e-4);
+        assert!((fitted.b0_prime - expected.b0_prime).abs() < 1e-4);
+
+        assert!(BirchMurnaghan::fit(&volumes[..3], &energies[..3]).is_err());
+        // Energies decreasing over the whole range have no minimum.
+        let decreasing: Vec<f64> = volumes.iter().map(|v| -v).collect();
+        assert!(BirchMurnaghan::fit(&volumes, &decreasing).is_err());
+    }
+}
@@ -0,0 +1,23 @@
+pub mod bader;
+pub mod chgcar;
+pub mod convergence;
+pub mod doscar;
+pub mod elastic;
+pub mod eos;
+pub mod oszicar;
+pub mod outcar;
+pub mod summary;
+pub mod vasprun;
+pub mod vibrations;
+
+pub use bader::*;
+pub use chgcar::*;
+pub use convergence::*;
+pub use doscar::*;
+pub use elastic::*;
+pub use eos::*;
+pub use oszicar::*;
+pub use outcar::*;
+pub use summary::*;
+pub use vasprun::*;
+pub use vibrations::*;
@@ -0,0 +1,172 @@
+use eyre::Result;
+use serde::Serialize;
+use std::path::Path;
+
+/// A single electronic (SCF) step of OSZICAR.
+#[derive(Debug, Clone, Serialize)]
+pub struct ElectronicStep {
+    /// Algorithm of the step, e.g. "DAV", "RMM".
+    pub algo: String,
+    pub n: usize,
+    pub energy: f64,
+    pub d_e: f64,
+    pub d_eps: f64,
+    pub ncg: usize,
+    pub rms: f64,
+    pub rms_c: Option<f64>,
+}
+
+/// A single ionic step of OSZICAR, with the electronic steps belonging to it.
+#[derive(Debug, Clone, Serialize)]
+pub struct IonicStep {
+    pub n: usize,
+    /// Free energy (F).
+    pub free_energy: f64,
+    /// Energy extrapolated to sigma -> 0 (E0).
+    pub e0: f64,
+    /// Energy change from the previous ionic step (dE).
+    pub d_e: Option<f64>,
+    /// Total magnetization. Three components for noncollinear runs, empty if not spin-polarized.
+    pub magnetization: Vec<f64>,
+    /// Temperature of MD steps (T).
+    pub temperature: Option<f64>,
+    pub electronic_steps: Vec<ElectronicStep>,
+}
+
+/// Parsed OSZICAR.
+#[derive(Debug, Clone, Serialize)]
+pub struct Oszicar {
+    pub ionic_steps: Vec<IonicStep>,
+}
+
+impl Oszicar {
+    /// Reads and parses OSZICAR file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Oszicar> {
+        let contents =
+            std::fs::read_to_string(&path).map_err(|_| eyre::eyre!("Could not read {}", path.as_ref().display()))?;
+        Oszicar::parse(&contents)
+    }
+
+    /// Parses contents of OSZICAR.
+    pub fn parse(contents: &str) -> Result<Oszicar> {
+        let mut ionic_steps = vec![];
+        let mut electronic_steps = vec![];
+        for line in contents.lines() {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('N') {
+                continue;
+            }
+            if trimmed.as_bytes().get(3) == Some(&b':') {
+                electronic_steps.push(parse_electronic_step(trimmed)?);
+            } else if trimmed.contains("E0=") {
+                let mut step = parse_ionic_step(trimmed)?;
+                step.electronic_steps = std::mem::take(&mut electronic_steps);
+                ionic_steps.push(step);
+            }
+        }
+        // Unfinished ionic step: keep the electronic steps so that callers can inspect SCF progress.
+        if !electronic_steps.is_empty() {
+            ionic_steps.push(IonicStep {
+                n: ionic_steps.len() + 1,
+                free_energy: f64::NAN,
+                e0: f64::NAN,
+                d_e: None,
+                magnetization: vec![],
+                temperature: None,
+                electronic_steps,
+            });
+        }
+        Ok(Oszicar { ionic_steps })
+    }
+
+    /// Returns ionic steps that have been completed.
+    pub fn completed_steps(&self) -> impl Iterator<Item = &IonicStep> {
+        self.ionic_steps.iter().filter(|step| !step.free_energy.is_nan())
+    }
+
+    /// Returns the last completed ionic step.
+    pub fn last_step(&self) -> Option<&IonicStep> {
+        self.completed_steps().last()
+    }
+}
+
+fn parse_float(token: &str) -> Result<f64> {
+    token
+        .parse::<f64>()
+        .map_err(|_| eyre::eyre!("Could not parse '{}' as number in OSZICAR", token))
+}
+
+fn parse_electronic_step(line: &str) -> Result<ElectronicStep> {
+    let algo = line[..3].trim().to_string();
+    let fields: Vec<&str> = line[4..].split_whitespace().collect();
+    if fields.len() < 6 {
+        return Err(eyre::eyre!("Malformed electronic step in OSZICAR: {}", line));
+    }
+    Ok(ElectronicStep {
+        algo,
+        n: fields[0].parse()?,
+        energy: parse_float(fields[1])?,
+        d_e: parse_float(fields[2])?,
+        d_eps: parse_float(fields[3])?,
+        ncg: fields[4].parse()?,
+        rms: parse_float(fields[5])?,
+        rms_c: fields.get(6).and_then(|value| value.parse().ok()),
+    })
+}
+
+/// Parses ionic step line, e.g.
+/// `1 F= -.41430745E+03 E0= -.41429958E+03  d E =-.414307E+03  mag=     0.0002`
+fn parse_ionic_step(line: &str) -> Result<IonicStep> {
+    let normalized = line.replace("d E =", "dE=").replace('=', "= ");
+    let tokens: Vec<&str> = normalized.split_whitespace().collect();
+    let n = tokens[0].parse()?;
+
+    let values_of = |key: &str| -> Vec<f64> {
+        match tokens.iter().position(|token| *token == key) {
+            Some(i) => tokens[i + 1..]
+                .iter()
+                .take_while(|token| !token.ends_with('='))
+                .filter_map(|token| token.parse().ok())
+                .collect(),
+            None => vec![],
+        }
+    };
+    let value_of = |key: &str| values_of(key).first().copied();
+
+    Ok(IonicStep {
+        n,
+        free_energy: value_of("F=").ok_or_else(|| eyre::eyre!("F not found in OSZICAR line: {}", line))?,
+        e0: value_of("E0=").ok_or_else(|| eyre::eyre!("E0 not found in OSZICAR line: {}", line))?,
+        d_e: value_of("dE="),
+        magnetization: values_of("mag="),
+        temperature: value_of("T="),
+        electronic_steps: vec![],
+    })
+}
+
+mod tests {
+    #[test]
+    fn test_parse_oszicar() {
+        use super::*;
+        let contents = "       N       E                     dE             d eps       ncg     rms          rms(c)
+DAV:   1     0.441919013857E+03    0.44192E+03   -0.18221E+04  2000   0.152E+03
+RMM:   2    -0.41431493E+03   -0.23849E+01   -0.17437E+01  1744   0.154E+01    0.743E+00
+   1 F= -.41430745E+03 E0= -.41429958E+03  d E =-.414307E+03  mag=     0.0002
+DAV:   1    -0.41435000E+03   -0.40000E-01   -0.10000E+00  2000   0.100E+00
+   2 T=   400. E= -.38937497E+03 F= -.41440000E+03 E0= -.41439000E+03  EK= 0.37810E-01 SP= 0.00E+00 SK= 0.00E+00 mag=    -1.0000    0.0000    2.0000
+RMM:   1    -0.41436000E+03   -0.10000E-01   -0.10000E+00  2000   0.100E+00
+";
+        let oszicar = Oszicar::parse(contents).unwrap();
+        assert_eq!(oszicar.ionic_steps.len(), 3);
+        let first = &oszicar.ionic_steps[0];
+        assert_eq!(first.electronic_steps.len(), 2);
+        assert_eq!(first.electronic_steps[1].rms_c, Some(0.743));
+        assert_eq!(first.free_energy, -414.30745);
+        assert_eq!(first.d_e, Some(-414.307));
+        assert_eq!(first.magnetization, vec![0.0002]);
+        let second = &oszicar.ionic_steps[1];
+        assert_eq!(second.temperature, Some(400.0));
+        assert_eq!(second.magnetization, vec![-1.0, 0.0, 2.0]);
+        assert_eq!(oszicar.last_step().unwrap().n, 2);
+    }
+}
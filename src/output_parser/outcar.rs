@@ -0,0 +1,329 @@
+use eyre::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Quantities of a single ionic step in OUTCAR.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OutcarStep {
+    /// Free energy TOTEN (eV).
+    pub free_energy: Option<f64>,
+    /// Energy without entropy (eV).
+    pub energy_without_entropy: Option<f64>,
+    /// Energy extrapolated to sigma -> 0 (eV).
+    pub energy_sigma0: Option<f64>,
+    /// Cartesian positions (Angstrom).
+    pub positions: Vec<[f64; 3]>,
+    /// Total forces (eV/Angstrom).
+    pub forces: Vec<[f64; 3]>,
+    /// Stress in kB, ordered as XX, YY, ZZ, XY, YZ, ZX.
+    pub stress: Option<[f64; 6]>,
+    /// Volume of the cell (Angstrom^3).
+    pub volume: Option<f64>,
+    /// Number of electronic iterations.
+    pub num_electronic_steps: usize,
+    /// Whether EDIFF was reached in this step.
+    pub electronic_converged: bool,
+}
+
+impl OutcarStep {
+    /// Largest norm of the atomic forces.
+    pub fn max_force(&self) -> Option<f64> {
+        self.forces
+            .iter()
+            .map(|f| (f[0] * f[0] + f[1] * f[1] + f[2] * f[2]).sqrt())
+            .fold(None, |max, f| Some(max.map_or(f, |m: f64| m.max(f))))
+    }
+}
+
+/// Vibrational mode from the eigenvectors of the dynamical matrix (IBRION = 5-8).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VibrationalMode {
+    pub frequency_thz: f64,
+    pub frequency_cm: f64,
+    pub energy_mev: f64,
+    /// Whether the frequency is imaginary (`f/i`).
+    pub imaginary: bool,
+    /// Normalized eigenvector (dx, dy, dz) of each atom.
+    pub eigenvector: Vec<[f64; 3]>,
+}
+
+/// Timing and memory information printed at the end of OUTCAR.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Timing {
+    pub cpu_time: Option<f64>,
+    pub elapsed_time: Option<f64>,
+    pub max_memory_kb: Option<f64>,
+}
+
+/// Parsed OUTCAR.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Outcar {
+    pub vasp_version: Option<String>,
+    pub num_ions: Option<usize>,
+    /// Number of irreducible k-points.
+    pub num_kpoints: Option<usize>,
+    /// Selected INCAR parameters echoed in OUTCAR (NELM, NSW, EDIFF, EDIFFG, IBRION, ISIF, ...).
+    pub parameters: HashMap<String, f64>,
+    pub ionic_steps: Vec<OutcarStep>,
+    pub fermi_energy: Option<f64>,
+    pub warnings: Vec<String>,
+    pub vibrational_modes: Vec<VibrationalMode>,
+    /// Whether VASP reported "reached required accuracy" for the ionic relaxation.
+    pub ionic_converged: bool,
+    /// Whether the run terminated normally (timing information was written).
+    pub finished: bool,
+    pub timing: Timing,
+}
+
+/// INCAR parameters picked up from the parameter section of OUTCAR.
+const PARAMETERS: [&str; 9] = [
+    "NELM", "NSW", "EDIFF", "EDIFFG", "IBRION", "ISIF", "ISPIN", "ENCUT", "POTIM",
+];
+
+impl Outcar {
+    /// Reads and parses OUTCAR file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Outcar> {
+        let contents =
+            std::fs::read_to_string(&path).map_err(|_| eyre::eyre!("Could not read {}", path.as_ref().display()))?;
+        Ok(Outcar::parse(&contents))
+    }
+
+    /// Parses contents of OUTCAR.
+    pub fn parse(contents: &str) -> Outcar {
+        let mut outcar = Outcar::default();
+        let mut step = OutcarStep::default();
+        let mut lines = contents.lines();
+
+        while let Some(line) = lines.next() {
+            let trimmed = line.trim();
+            if outcar.vasp_version.is_none() && trimmed.starts_with("vasp.") {
+                outcar.vasp_version = trimmed
+                    .split_whitespace()
+                    .next()
+                    .map(|v| v.trim_start_matches("vasp.").to_string());
+            } else if trimmed.contains("NIONS =") {
+                outcar.num_ions = value_after(trimmed, "NIONS =").map(|n| n as usize);
+            } else if trimmed.contains("NKPTS =") {
+                outcar.num_kpoints = value_after(trimmed, "NKPTS =").map(|n| n as usize);
+            } else if trimmed.starts_with('-') && trimmed.contains(" Iteration ") {
+                step.num_electronic_steps += 1;
+            } else if trimmed.contains("aborting loop because EDIFF is reached") {
+                step.electronic_converged = true;
+            } else if trimmed.starts_with("free  energy   TOTEN") {
+                step.free_energy = value_after(trimmed, "=");
+            } else if trimmed.starts_with("energy  without entropy") {
+                step.energy_without_entropy = value_after(trimmed, "entropy=");
+                step.energy_sigma0 = value_after(trimmed, "energy(sigma->0) =");
+            } else if let Some(stress) = trimmed.strip_prefix("in kB") {
+                let values: Vec<f64> = stress.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+                if values.len() == 6 {
+                    step.stress = Some([values[0], values[1], values[2], values[3], values[4], values[5]]);
+                }
+            } else if trimmed.starts_with("volume of cell :") {
+                step.volume = value_after(trimmed, ":");
+            } else if trimmed.starts_with("POSITION") && trimmed.contains("TOTAL-FORCE") {
+                lines.next(); // separator
+                let mut positions = vec![];
+                let mut forces = vec![];
+                for row in lines.by_ref() {
+                    let values: Vec<f64> = row.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+                    if values.len() != 6 {
+                        break;
+                    }
+                    positions.push([values[0], values[1], values[2]]);
+                    forces.push([values[3], values[4], values[5]]);
+                }
+                step.positions = positions;
+                step.forces = forces;
+            } else if trimmed.starts_with("LOOP+") {
+                // LOOP+ closes an ionic step.
+                outcar.ionic_steps.push(std::mem::take(&mut step));
+            } else if trimmed.starts_with("E-fermi :") {
+                outcar.fermi_energy = value_after(trimmed, ":");
+            } else if trimmed.contains("reached required accuracy") {
+                outcar.ionic_converged = true;
+            } else if trimmed.contains("W    W    AA    RRRRR") {
+                // Skip the rest of the banner, which ends with an empty row.
+                let mut message = vec![];
+                let mut in_text = false;
+                for row in lines.by_ref() {
+                    let row = row.trim();
+                    if !row.starts_with('|') {
+                        break;
+                    }
+                    let text = row.trim_matches('|').trim();
+                    if !in_text {
+                        in_text = text.is_empty();
+                    } else if !text.is_empty() {
+                        message.push(text.to_string());
+                    }
+                }
+                outcar.warnings.push(message.join(" "));
+            } else if trimmed.starts_with("WARNING") || trimmed.starts_with("-----  WARNING") {
+                outcar.warnings.push(trimmed.trim_start_matches('-').trim().to_string());
+            } else if trimmed.starts_with("Eigenvectors and eigenvalues of the dynamical matrix")
+                && outcar.vibrational_modes.is_empty()
+            {
+                outcar.vibrational_modes = parse_vibrational_modes(&mut lines);
+            } else if trimmed.starts_with("General timing and accounting") {
+                outcar.finished = true;
+            } else if trimmed.starts_with("Total CPU time used (sec):") {
+                outcar.timing.cpu_time = value_after(trimmed, ":");
+            } else if trimmed.starts_with("Elapsed time (sec):") {
+                outcar.timing.elapsed_time = value_after(trimmed, ":");
+            } else if trimmed.starts_with("Maximum memory used (kb):") {
+                outcar.timing.max_memory_kb = value_after(trimmed, ":");
+            } else if let Some(name) = PARAMETERS
+                .iter()
+                .find(|name| trimmed.starts_with(*name) && trimmed[name.len()..].trim_start().starts_with('='))
+            {
+                if !outcar.parameters.contains_key(*name) {
+                    if let Some(value) = value_after(trimmed, "=") {
+                        outcar.parameters.insert(name.to_string(), value);
+                    }
+                }
+            }
+        }
+        if step.num_electronic_steps > 0 {
+            outcar.ionic_steps.push(step);
+        }
+        outcar
+    }
+
+    /// Returns an INCAR parameter echoed in OUTCAR.
+    pub fn parameter(&self, name: &str) -> Option<f64> {
+        self.parameters.get(name).copied()
+    }
+
+    /// Returns the last ionic step with computed energies.
+    pub fn last_step(&self) -> Option<&OutcarStep> {
+        self.ionic_steps.iter().rev().find(|step| step.free_energy.is_some())
+    }
+}
+
+/// Parses modes following the header of eigenvectors, until the end of the section.
+fn parse_vibrational_modes<'a, I: Iterator<Item = &'a str>>(lines: &mut I) -> Vec<VibrationalMode> {
+    let mut modes: Vec<VibrationalMode> = vec![];
+    for line in lines {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("---") || trimmed.starts_with('X') {
+            continue;
+        }
+        if let Some((header, values)) = trimmed.split_once('=').filter(|(h, _)| h.contains(" f")) {
+            let values: Vec<f64> = values.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+            if values.len() < 4 {
+                break;
+            }
+            modes.push(VibrationalMode {
+                frequency_thz: values[0],
+                frequency_cm: values[2],
+                energy_mev: values[3],
+                imaginary: header.contains("f/i"),
+                eigenvector: vec![],
+            });
+            continue;
+        }
+        let values: Vec<f64> = trimmed.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+        match (values.len(), modes.last_mut()) {
+            (6, Some(mode)) => mode.eigenvector.push([values[3], values[4], values[5]]),
+            _ => break,
+        }
+    }
+    modes
+}
+
+/// Parses the first number after `key` in `line`.
+fn value_after(line: &str, key: &str) -> Option<f64> {
+    let start = line.find(key)? + key.len();
+    line[start..]
+        .split(|c: char| c.is_whitespace() || c == ';')
+        .find(|token| !token.is_empty())
+        .and_then(|token| token.parse().ok())
+}
+
+mod tests {
+    #[test]
+    fn test_parse_outcar() {
+        use super::*;
+        let contents = " vasp.6.3.2 27Jun22 (build Jul 19 2022 17:30:41) complex
+
+   number of dos      NEDOS =    301   number of ions     NIONS =      2
+   k-points           NKPTS =      8   k-points in BZ     NKDIM =      8   number of bands    NBANDS=      8
+   NELM   =     60;   NELMIN=  2; NELMDL= -5     # of ELM steps
+   EDIFF  = 0.1E-05   stopping-criterion for ELM
+   NSW    =     10    number of steps for IOM
+   EDIFFG = -.1E-01   stopping-criterion for IOM
+   IBRION =      2    ionic relax: 0-MD 1-quasi-New 2-CG
+   ISIF   =      3    stress and relaxation
+ ----------------------------------------- Iteration    1(   1)  ---------------------------------------
+ ----------------------------------------- Iteration    1(   2)  ---------------------------------------
+ ------------------------ aborting loop because EDIFF is reached ----------------------------------------
+  FORCE on cell =-STRESS in cart. coord.  units (eV):
+  Direction    XX          YY          ZZ          XY          YZ          ZX
+  in kB     -12.34567   -11.00000   -10.50000     0.10000    -0.20000     0.30000
+  external pressure =      -11.28 kB  Pullay stress =        0.00 kB
+  energy-cutoff  :      520.00
+  volume of cell :       40.89
+ POSITION                                       TOTAL-FORCE (eV/Angst)
+ -----------------------------------------------------------------------------------
+      0.00000      0.00000      0.00000         0.030000      0.000000      0.040000
+      1.35750      1.35750      1.35750        -0.030000      0.000000     -0.040000
+ -----------------------------------------------------------------------------------
+    total drift:                                0.000000      0.000000      0.000000
+  free  energy   TOTEN  =       -10.84733536 eV
+  energy  without entropy=      -10.84700000  energy(sigma->0) =      -10.84716768
+     LOOP+:  cpu time      1.23: real time      1.25
+ ----------------------------------------- Iteration    2(   1)  ---------------------------------------
+ ------------------------ aborting loop because EDIFF is reached ----------------------------------------
+  in kB      -1.00000    -1.00000    -1.00000     0.00000     0.00000     0.00000
+  volume of cell :       39.50
+ POSITION                                       TOTAL-FORCE (eV/Angst)
+ -----------------------------------------------------------------------------------
+      0.00000      0.00000      0.00000         0.000000      0.000000      0.001000
+      1.34000      1.34000      1.34000         0.000000      0.000000     -0.001000
+ -----------------------------------------------------------------------------------
+  free  energy   TOTEN  =       -10.90000000 eV
+  energy  without entropy=      -10.89900000  energy(sigma->0) =      -10.89950000
+     LOOP+:  cpu time      0.80: real time      0.82
+ E-fermi :   5.6789     XC(G=0):  -8.1234     alpha+bet : -4.5678
+ reached required accuracy - stopping structural energy minimisation
+ General timing and accounting informations for this job:
+ ========================================================
+                  Total CPU time used (sec):        2.468
+                         Elapsed time (sec):        2.515
+                   Maximum memory used (kb):      123456.
+";
+        let outcar = Outcar::parse(contents);
+        assert_eq!(outcar.vasp_version.as_deref(), Some("6.3.2"));
+        assert_eq!(outcar.num_ions, Some(2));
+        assert_eq!(outcar.num_kpoints, Some(8));
+        assert_eq!(outcar.parameter("NELM"), Some(60.0));
+        assert_eq!(outcar.parameter("EDIFF"), Some(1e-6));
+        assert_eq!(outcar.parameter("EDIFFG"), Some(-0.01));
+        assert_eq!(outcar.parameter("ISIF"), Some(3.0));
+
+        assert_eq!(outcar.ionic_steps.len(), 2);
+        let first = &outcar.ionic_steps[0];
+        assert_eq!(first.num_electronic_steps, 2);
+        assert!(first.electronic_converged);
+        assert_eq!(first.free_energy, Some(-10.84733536));
+        assert_eq!(first.energy_without_entropy, Some(-10.847));
+        assert_eq!(first.energy_sigma0, Some(-10.84716768));
+        assert_eq!(first.stress, Some([-12.34567, -11.0, -10.5, 0.1, -0.2, 0.3]));
+        assert_eq!(first.volume, Some(40.89));
+        assert_eq!(first.positions[1], [1.3575, 1.3575, 1.3575]);
+        assert_eq!(first.forces, vec![[0.03, 0.0, 0.04], [-0.03, 0.0, -0.04]]);
+        assert!((first.max_force().unwrap() - 0.05).abs() < 1e-12);
+
+        let last = outcar.last_step().unwrap();
+        assert_eq!(last.free_energy, Some(-10.9));
+        assert_eq!(last.volume, Some(39.5));
+        assert_eq!(last.max_force(), Some(0.001));
+        assert_eq!(outcar.fermi_energy, Some(5.6789));
+        assert!(outcar.ionic_converged);
+        assert!(outcar.finished);
+        assert_eq!(outcar.timing.elapsed_time, Some(2.515));
+        assert_eq!(outcar.timing.max_memory_kb, Some(123456.0));
+    }
+}
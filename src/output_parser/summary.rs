@@ -0,0 +1,145 @@
+use super::oszicar::Oszicar;
+use super::outcar::Outcar;
+use eyre::Result;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+/// Summary of a VASP run, collected from OSZICAR and OUTCAR.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub directory: String,
+    pub vasp_version: Option<String>,
+    pub num_ions: Option<usize>,
+    /// Whether the run terminated normally.
+    pub finished: bool,
+    pub num_ionic_steps: usize,
+    pub num_electronic_steps: usize,
+    /// Final free energy F (eV).
+    pub free_energy: Option<f64>,
+    /// Final energy extrapolated to sigma -> 0, E0 (eV).
+    pub energy_sigma0: Option<f64>,
+    /// Energy change of the last ionic step (eV).
+    pub energy_change: Option<f64>,
+    pub magnetization: Vec<f64>,
+    pub fermi_energy: Option<f64>,
+    /// Largest atomic force of the last ionic step (eV/Angstrom).
+    pub max_force: Option<f64>,
+    pub forces: Vec<[f64; 3]>,
+    /// Stress of the last ionic step in kB (XX, YY, ZZ, XY, YZ, ZX).
+    pub stress: Option<[f64; 6]>,
+    /// Whether VASP reported that the ionic relaxation reached the required accuracy.
+    pub ionic_converged: bool,
+    /// Whether the electronic loop of the last ionic step reached EDIFF.
+    pub electronic_converged: bool,
+    pub cpu_time: Option<f64>,
+    pub elapsed_time: Option<f64>,
+    pub warnings: Vec<String>,
+}
+
+impl RunSummary {
+    /// Builds summary from OSZICAR and OUTCAR in `dir`.
+    /// At least one of them should exist.
+    pub fn from_dir(dir: &str) -> Result<RunSummary> {
+        let dir_path = Path::new(dir);
+        let oszicar = match dir_path.join("OSZICAR").exists() {
+            true => Some(Oszicar::from_file(dir_path.join("OSZICAR"))?),
+            false => None,
+        };
+        let outcar = match dir_path.join("OUTCAR").exists() {
+            true => Some(Outcar::from_file(dir_path.join("OUTCAR"))?),
+            false => None,
+        };
+        if oszicar.is_none() && outcar.is_none() {
+            return Err(eyre::eyre!("Neither OSZICAR nor OUTCAR found in {}", dir));
+        }
+        Ok(RunSummary::new(dir, oszicar.as_ref(), outcar.as_ref()))
+    }
+
+    /// Builds summary from already parsed output files.
+    pub fn new(dir: &str, oszicar: Option<&Oszicar>, outcar: Option<&Outcar>) -> RunSummary {
+        let last_oszicar_step = oszicar.and_then(|o| o.last_step());
+        let last_outcar_step = outcar.and_then(|o| o.last_step());
+
+        let num_ionic_steps = match (oszicar, outcar) {
+            (Some(o), _) => o.completed_steps().count(),
+            (None, Some(o)) => o.ionic_steps.iter().filter(|step| step.free_energy.is_some()).count(),
+            (None, None) => 0,
+        };
+        let num_electronic_steps = match (oszicar, outcar) {
+            (Some(o), _) => o.ionic_steps.iter().map(|step| step.electronic_steps.len()).sum(),
+            (None, Some(o)) => o.ionic_steps.iter().map(|step| step.num_electronic_steps).sum(),
+            (None, None) => 0,
+        };
+
+        RunSummary {
+            directory: dir.to_string(),
+            vasp_version: outcar.and_then(|o| o.vasp_version.clone()),
+            num_ions: outcar.and_then(|o| o.num_ions),
+            finished: outcar.is_some_and(|o| o.finished),
+            num_ionic_steps,
+            num_electronic_steps,
+            free_energy: last_oszicar_step
+                .map(|step| step.free_energy)
+                .or_else(|| last_outcar_step.and_then(|step| step.free_energy)),
+            energy_sigma0: last_oszicar_step
+                .map(|step| step.e0)
+                .or_else(|| last_outcar_step.and_then(|step| step.energy_sigma0)),
+            energy_change: last_oszicar_step.and_then(|step| step.d_e),
+            magnetization: last_oszicar_step.map_or(vec![], |step| step.magnetization.clone()),
+            fermi_energy: outcar.and_then(|o| o.fermi_energy),
+            max_force: last_outcar_step.and_then(|step| step.max_force()),
+            forces: last_outcar_step.map_or(vec![], |step| step.forces.clone()),
+            stress: last_outcar_step.and_then(|step| step.stress),
+            ionic_converged: outcar.is_some_and(|o| o.ionic_converged),
+            electronic_converged: last_outcar_step.is_some_and(|step| step.electronic_converged),
+            cpu_time: outcar.and_then(|o| o.timing.cpu_time),
+            elapsed_time: outcar.and_then(|o| o.timing.elapsed_time),
+            warnings: outcar.map_or(vec![], |o| o.warnings.clone()),
+        }
+    }
+}
+
+fn format_option(value: Option<f64>, unit: &str) -> String {
+    match value {
+        Some(v) => format!("{:.6} {}", v, unit),
+        None => String::from("-"),
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "========= {} =========", self.directory)?;
+        writeln!(
+            f,
+            "VASP version         : {}",
+            self.vasp_version.as_deref().unwrap_or("-")
+        )?;
+        if let Some(num_ions) = self.num_ions {
+            writeln!(f, "Number of ions       : {}", num_ions)?;
+        }
+        writeln!(f, "Finished             : {}", self.finished)?;
+        writeln!(f, "Ionic steps          : {}", self.num_ionic_steps)?;
+        writeln!(f, "Electronic steps     : {}", self.num_electronic_steps)?;
+        writeln!(f, "Free energy (F)      : {}", format_option(self.free_energy, "eV"))?;
+        writeln!(f, "Energy (E0)          : {}", format_option(self.energy_sigma0, "eV"))?;
+        writeln!(f, "Energy change (dE)   : {}", format_option(self.energy_change, "eV"))?;
+        if !self.magnetization.is_empty() {
+            let mag: Vec<String> = self.magnetization.iter().map(|m| format!("{:.4}", m)).collect();
+            writeln!(f, "Magnetization        : {}", mag.join(" "))?;
+        }
+        writeln!(f, "Fermi energy         : {}", format_option(self.fermi_energy, "eV"))?;
+        writeln!(f, "Max force            : {}", format_option(self.max_force, "eV/A"))?;
+        if let Some(stress) = self.stress {
+            let stress: Vec<String> = stress.iter().map(|s| format!("{:.3}", s)).collect();
+            writeln!(f, "Stress (kB)          : {}", stress.join(" "))?;
+        }
+        writeln!(f, "Ionic converged      : {}", self.ionic_converged)?;
+        writeln!(f, "Electronic converged : {}", self.electronic_converged)?;
+        writeln!(f, "Elapsed time         : {}", format_option(self.elapsed_time, "s"))?;
+        for warning in &self.warnings {
+            writeln!(f, "Warning: {}", warning)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,635 @@
+use eyre::Result;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use vasp_files::poscar::Poscar;
+
+/// A single ionic step (`<calculation>`) of vasprun.xml.
+#[derive(Debug, Clone)]
+pub struct VasprunStep {
+    pub index: usize,
+    pub structure: Poscar,
+    /// Free energy (eV).
+    pub free_energy: Option<f64>,
+    /// Energy without entropy (eV).
+    pub energy_without_entropy: Option<f64>,
+    /// Energy extrapolated to sigma -> 0 (eV).
+    pub energy_sigma0: Option<f64>,
+    /// Forces (eV/Angstrom).
+    pub forces: Vec<[f64; 3]>,
+    /// Stress tensor (kB).
+    pub stress: Option<[[f64; 3]; 3]>,
+    pub num_electronic_steps: usize,
+}
+
+/// K-points actually used in the calculation.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VasprunKpoints {
+    pub scheme: Option<String>,
+    pub divisions: Option<[u32; 3]>,
+    pub kpoints: Vec<[f64; 3]>,
+    pub weights: Vec<f64>,
+}
+
+/// Eigenvalues and occupations, indexed by spin, k-point and band.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Eigenvalues {
+    pub eigenvalues: Vec<Vec<Vec<f64>>>,
+    pub occupations: Vec<Vec<Vec<f64>>>,
+}
+
+/// Density of states. Energies are not shifted.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Dos {
+    pub efermi: Option<f64>,
+    pub energies: Vec<f64>,
+    /// Total DOS, indexed by spin and energy.
+    pub total: Vec<Vec<f64>>,
+    /// Names of the orbitals of projected DOS, e.g. "s", "py", ...
+    pub orbitals: Vec<String>,
+    /// Projected DOS, indexed by ion, spin, energy and orbital.
+    pub partial: Vec<Vec<Vec<Vec<f64>>>>,
+}
+
+/// Items of vasprun.xml, yielded in the order they appear in the file.
+#[derive(Debug, Clone)]
+pub enum VasprunItem {
+    Generator {
+        version: Option<String>,
+    },
+    Incar(HashMap<String, String>),
+    Kpoints(VasprunKpoints),
+    /// Element of each atom.
+    AtomInfo(Vec<String>),
+    /// Initial or final structure, named "initialpos" and "finalpos" by VASP.
+    Structure {
+        name: String,
+        structure: Poscar,
+    },
+    Step(Box<VasprunStep>),
+    Eigenvalues(Eigenvalues),
+    Dos(Dos),
+}
+
+/// Streaming parser of vasprun.xml.
+///
+/// Only the item being parsed is kept in memory, so that huge AIMD outputs can be processed
+/// step by step. Use [`Vasprun::from_file`] to collect everything at once.
+pub struct VasprunReader<R: BufRead> {
+    reader: Reader<R>,
+    buf: Vec<u8>,
+    /// Open elements, with their `name` and `comment` attributes.
+    stack: Vec<(String, String, String)>,
+    text: String,
+    generator_version: Option<String>,
+    incar: HashMap<String, String>,
+    kpoints: VasprunKpoints,
+    elements: Vec<String>,
+    lattice: Vec<[f64; 3]>,
+    positions: Vec<[f64; 3]>,
+    step: Option<VasprunStep>,
+    num_steps: usize,
+    /// Column of the current `<c>` in `<rc>` and row of the current stress tensor.
+    column: usize,
+    stress_rows: usize,
+    eigenvalues: Eigenvalues,
+    dos: Dos,
+    finished: bool,
+}
+
+impl VasprunReader<BufReader<File>> {
+    /// Opens vasprun.xml for streaming.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(&path).map_err(|_| eyre::eyre!("Could not read {}", path.as_ref().display()))?;
+        Ok(VasprunReader::new(BufReader::new(file)))
+    }
+}
+
+impl<R: BufRead> VasprunReader<R> {
+    pub fn new(reader: R) -> Self {
+        let mut reader = Reader::from_reader(reader);
+        reader.trim_text(true);
+        VasprunReader {
+            reader,
+            buf: vec![],
+            stack: vec![],
+            text: String::new(),
+            generator_version: None,
+            incar: HashMap::new(),
+            kpoints: VasprunKpoints::default(),
+            elements: vec![],
+            lattice: vec![],
+            positions: vec![],
+            step: None,
+            num_steps: 0,
+            column: 0,
+            stress_rows: 0,
+            eigenvalues: Eigenvalues::default(),
+            dos: Dos::default(),
+            finished: false,
+        }
+    }
+
+    fn inside(&self, tag: &str) -> bool {
+        self.stack.iter().any(|(t, _, _)| t == tag)
+    }
+
+    fn parent(&self, depth: usize) -> Option<&(String, String, String)> {
+        self.stack.iter().rev().nth(depth)
+    }
+
+    fn parent_is(&self, depth: usize, tag: &str) -> bool {
+        self.parent(depth).is_some_and(|(t, _, _)| t == tag)
+    }
+
+    fn parent_name_is(&self, depth: usize, name: &str) -> bool {
+        self.parent(depth).is_some_and(|(_, n, _)| n == name)
+    }
+
+    fn start(&mut self, e: &BytesStart) -> Result<()> {
+        let tag = String::from_utf8_lossy(e.name()).to_string();
+        let mut name = String::new();
+        let mut comment = String::new();
+        for attr in e.attributes() {
+            let attr = attr?;
+            match attr.key {
+                b"name" => name = String::from_utf8_lossy(&attr.value).to_string(),
+                b"comment" => comment = String::from_utf8_lossy(&attr.value).to_string(),
+                b"param" if tag == "generation" => {
+                    self.kpoints.scheme = Some(String::from_utf8_lossy(&attr.value).to_string())
+                }
+                _ => (),
+            }
+        }
+        if self.inside("projected") {
+            self.stack.push((tag, name, comment));
+            return Ok(());
+        }
+        match tag.as_str() {
+            "calculation" => {
+                self.num_steps += 1;
+                self.step = Some(VasprunStep {
+                    index: self.num_steps,
+                    structure: self.structure("")?,
+                    free_energy: None,
+                    energy_without_entropy: None,
+                    energy_sigma0: None,
+                    forces: vec![],
+                    stress: None,
+                    num_electronic_steps: 0,
+                });
+            }
+            "scstep" => {
+                if let Some(step) = self.step.as_mut() {
+                    step.num_electronic_steps += 1;
+                }
+            }
+            "structure" => {
+                self.lattice.clear();
+                self.positions.clear();
+            }
+            "rc" => self.column = 0,
+            "set" if comment.starts_with("spin") && self.parent_is(1, "array") => {
+                if self.inside("eigenvalues") {
+                    self.eigenvalues.eigenvalues.push(vec![]);
+                    self.eigenvalues.occupations.push(vec![]);
+                } else if self.inside("total") {
+                    self.dos.total.push(vec![]);
+                }
+            }
+            "set" if comment.starts_with("kpoint") && self.inside("eigenvalues") => {
+                if let Some(spin) = self.eigenvalues.eigenvalues.last_mut() {
+                    spin.push(vec![]);
+                }
+                if let Some(spin) = self.eigenvalues.occupations.last_mut() {
+                    spin.push(vec![]);
+                }
+            }
+            "set" if comment.starts_with("ion") && self.inside("partial") => self.dos.partial.push(vec![]),
+            "set" if comment.starts_with("spin") && self.inside("partial") => {
+                if let Some(ion) = self.dos.partial.last_mut() {
+                    ion.push(vec![]);
+                }
+            }
+            _ => (),
+        }
+        self.stack.push((tag, name, comment));
+        self.text.clear();
+        Ok(())
+    }
+
+    /// Handles the end of an element. Returns an item if one is completed.
+    fn end(&mut self) -> Result<Option<VasprunItem>> {
+        let text = std::mem::take(&mut self.text);
+        let values = || -> Vec<f64> { text.split_whitespace().filter_map(|v| v.parse().ok()).collect() };
+        // Rows of numbers must be complete, e.g. VASP writes "********" for numbers overflowing the field.
+        let row = |len: usize| -> Result<Vec<f64>> {
+            let v: Vec<f64> = text
+                .split_whitespace()
+                .map(|v| v.parse())
+                .collect::<Result<_, _>>()
+                .unwrap_or_default();
+            match v.len() >= len {
+                true => Ok(v),
+                false => Err(eyre::eyre!("Invalid vasprun.xml: \"{}\"", text.trim())),
+            }
+        };
+        let vector = || -> Result<[f64; 3]> {
+            match row(3)?.as_slice() {
+                &[x, y, z] => Ok([x, y, z]),
+                _ => Err(eyre::eyre!("Invalid vasprun.xml: \"{}\"", text.trim())),
+            }
+        };
+        let (tag, name, _) = match self.stack.last() {
+            Some(last) => last.clone(),
+            None => return Ok(None),
+        };
+        // Projected eigenvalues are not supported.
+        if self.inside("projected") {
+            self.stack.pop();
+            return Ok(None);
+        }
+
+        let mut item = None;
+        match tag.as_str() {
+            "i" if self.parent_is(1, "generator") && name == "version" => {
+                self.generator_version = Some(text.trim().to_string())
+            }
+            "i" | "v" if self.parent_is(1, "incar") => {
+                self.incar.insert(name, text.trim().to_string());
+            }
+            "v" if self.parent_is(1, "generation") && name == "divisions" => {
+                let v = vector()?;
+                self.kpoints.divisions = Some([v[0] as u32, v[1] as u32, v[2] as u32]);
+            }
+            "v" if self.parent_is(2, "kpoints") && self.parent_name_is(1, "kpointlist") => {
+                self.kpoints.kpoints.push(vector()?);
+            }
+            "v" if self.parent_is(2, "kpoints") && self.parent_name_is(1, "weights") => {
+                self.kpoints.weights.push(row(1)?[0]);
+            }
+            "c" if self.inside("atominfo") && self.parent_is(1, "rc") && self.parent_name_is(3, "atoms") => {
+                // Only the first column (element) of each row.
+                if self.column == 0 {
+                    self.elements.push(text.trim().to_string());
+                }
+                self.column += 1;
+            }
+            "v" if self.parent_name_is(1, "basis") && self.parent_is(2, "crystal") => {
+                self.lattice.push(vector()?);
+            }
+            "v" if self.parent_name_is(1, "positions") && self.parent_is(2, "structure") => {
+                self.positions.push(vector()?);
+            }
+            "v" if self.parent_name_is(1, "forces") && self.parent_is(2, "calculation") => {
+                let v = vector()?;
+                if let Some(step) = self.step.as_mut() {
+                    step.forces.push(v);
+                }
+            }
+            "v" if self.parent_name_is(1, "stress") && self.parent_is(2, "calculation") => {
+                let v = vector()?;
+                if let Some(step) = self.step.as_mut() {
+                    let stress = step.stress.get_or_insert([[0.0; 3]; 3]);
+                    stress[self.stress_rows.min(2)] = v;
+                }
+                self.stress_rows += 1;
+            }
+            "i" if self.parent_is(1, "energy") && self.parent_is(2, "calculation") => {
+                let value = values().first().copied();
+                if let Some(step) = self.step.as_mut() {
+                    match name.as_str() {
+                        "e_fr_energy" => step.free_energy = value,
+                        "e_wo_entrp" => step.energy_without_entropy = value,
+                        "e_0_energy" => step.energy_sigma0 = value,
+                        _ => (),
+                    }
+                }
+            }
+            "r" if self.inside("eigenvalues") => {
+                let v = row(2)?;
+                if let Some(k) = self.eigenvalues.eigenvalues.last_mut().and_then(|s| s.last_mut()) {
+                    k.push(v[0]);
+                }
+                if let Some(k) = self.eigenvalues.occupations.last_mut().and_then(|s| s.last_mut()) {
+                    k.push(v[1]);
+                }
+            }
+            "i" if self.parent_is(1, "dos") && name == "efermi" => self.dos.efermi = values().first().copied(),
+            "field" if self.inside("partial") => {
+                let field = text.trim().to_string();
+                if field != "energy" {
+                    self.dos.orbitals.push(field);
+                }
+            }
+            "r" if self.inside("total") && self.inside("dos") => {
+                let v = row(2)?;
+                if self.dos.total.len() == 1 {
+                    self.dos.energies.push(v[0]);
+                }
+                if let Some(spin) = self.dos.total.last_mut() {
+                    spin.push(v[1]);
+                }
+            }
+            "r" if self.inside("partial") => {
+                let v = row(2)?;
+                if let Some(spin) = self.dos.partial.last_mut().and_then(|ion| ion.last_mut()) {
+                    spin.push(v[1..].to_vec());
+                }
+            }
+            "atominfo" => item = Some(VasprunItem::AtomInfo(self.elements.clone())),
+            "incar" => item = Some(VasprunItem::Incar(self.incar.clone())),
+            "generator" => {
+                item = Some(VasprunItem::Generator {
+                    version: self.generator_version.clone(),
+                })
+            }
+            "kpoints" if !self.inside("calculation") => item = Some(VasprunItem::Kpoints(self.kpoints.clone())),
+            "structure" => {
+                let structure = self.structure(&name)?;
+                let in_calculation = self.inside("calculation");
+                match self.step.as_mut() {
+                    Some(step) if in_calculation => step.structure = structure,
+                    _ => item = Some(VasprunItem::Structure { name, structure }),
+                }
+            }
+            "eigenvalues" => item = Some(VasprunItem::Eigenvalues(std::mem::take(&mut self.eigenvalues))),
+            "dos" => item = Some(VasprunItem::Dos(std::mem::take(&mut self.dos))),
+            "calculation" => {
+                self.stress_rows = 0;
+                item = self.step.take().map(|step| VasprunItem::Step(Box::new(step)));
+            }
+            _ => (),
+        }
+        self.stack.pop();
+        Ok(item)
+    }
+
+    /// Builds structure from the lattice and positions parsed so far.
+    fn structure(&self, name: &str) -> Result<Poscar> {
+        let mut species: Vec<String> = vec![];
+        let mut num_atoms: Vec<usize> = vec![];
+        for element in &self.elements {
+            match species.last() {
+                Some(last) if last == element => *num_atoms.last_mut().unwrap() += 1,
+                _ => {
+                    species.push(element.clone());
+                    num_atoms.push(1);
+                }
+            }
+        }
+        let lattice = match self.lattice.len() {
+            3 => [self.lattice[0], self.lattice[1], self.lattice[2]],
+            0 => [[0.0; 3]; 3],
+            _ => return Err(eyre::eyre!("Malformed lattice in vasprun.xml")),
+        };
+        Ok(Poscar {
+            comment: name.to_string(),
+            scale: 1.0,
+            lattice,
+            species,
+            num_atoms,
+            frac_coords: self.positions.clone(),
+            selective_dynamics: None,
+        })
+    }
+
+    fn next_item(&mut self) -> Result<Option<VasprunItem>> {
+        loop {
+            self.buf.clear();
+            match self.reader.read_event(&mut self.buf)? {
+                Event::Start(ref e) => {
+                    let e = e.to_owned();
+                    self.start(&e)?;
+                }
+                Event::Empty(_) => (),
+                Event::Text(ref e) => {
+                    let text = e.unescape_and_decode(&self.reader)?;
+                    self.text.push_str(&text);
+                }
+                Event::End(_) => {
+                    if let Some(item) = self.end()? {
+                        return Ok(Some(item));
+                    }
+                }
+                Event::Eof => return Ok(None),
+                _ => (),
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for VasprunReader<R> {
+    type Item = Result<VasprunItem>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.next_item() {
+            Ok(Some(item)) => Some(Ok(item)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Fully parsed vasprun.xml.
+#[derive(Debug, Clone, Default)]
+pub struct Vasprun {
+    pub version: Option<String>,
+    pub incar: HashMap<String, String>,
+    pub kpoints: VasprunKpoints,
+    pub elements: Vec<String>,
+    pub initial_structure: Option<Poscar>,
+    pub final_structure: Option<Poscar>,
+    pub steps: Vec<VasprunStep>,
+    pub eigenvalues: Option<Eigenvalues>,
+    pub dos: Option<Dos>,
+}
+
+impl Vasprun {
+    /// Reads whole vasprun.xml into memory.
+    /// For large files, consider iterating [`VasprunReader`] instead.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Vasprun> {
+        let mut vasprun = Vasprun::default();
+        for item in VasprunReader::from_file(path)? {
+            match item? {
+                VasprunItem::Generator { version } => vasprun.version = version,
+                VasprunItem::Incar(incar) => vasprun.incar = incar,
+                VasprunItem::Kpoints(kpoints) => vasprun.kpoints = kpoints,
+                VasprunItem::AtomInfo(elements) => vasprun.elements = elements,
+                VasprunItem::Structure { name, structure } if name == "initialpos" => {
+                    vasprun.initial_structure = Some(structure)
+                }
+                VasprunItem::Structure { name, structure } if name == "finalpos" => {
+                    vasprun.final_structure = Some(structure)
+                }
+                VasprunItem::Structure { .. } => (),
+                VasprunItem::Step(step) => vasprun.steps.push(*step),
+                VasprunItem::Eigenvalues(eigenvalues) => vasprun.eigenvalues = Some(eigenvalues),
+                VasprunItem::Dos(dos) => vasprun.dos = Some(dos),
+            }
+        }
+        Ok(vasprun)
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_parse_vasprun() {
+        use super::*;
+        let lattice = r#"<crystal>
+   <varray name="basis" >
+    <v>       5.00000000       0.00000000       0.00000000 </v>
+    <v>       0.00000000       5.00000000       0.00000000 </v>
+    <v>       0.00000000       0.00000000       5.00000000 </v>
+   </varray>
+   <i name="volume">    125.00000000 </i>
+  </crystal>"#;
+        let contents = format!(
+            r#"<?xml version="1.0" encoding="ISO-8859-1"?>
+<modeling>
+ <generator>
+  <i name="program" type="string">vasp </i>
+  <i name="version" type="string">6.4.2  </i>
+ </generator>
+ <incar>
+  <i type="string" name="PREC">accurate</i>
+  <i name="ENCUT">    520.00000000</i>
+ </incar>
+ <kpoints>
+  <generation param="Gamma">
+   <v type="int" name="divisions">       4        4        4 </v>
+  </generation>
+  <varray name="kpointlist" >
+   <v>       0.00000000       0.00000000       0.00000000 </v>
+   <v>       0.25000000       0.00000000       0.00000000 </v>
+  </varray>
+  <varray name="weights" >
+   <v>       0.25000000 </v>
+   <v>       0.75000000 </v>
+  </varray>
+ </kpoints>
+ <atominfo>
+  <atoms>       2 </atoms>
+  <array name="atoms" >
+   <dimension dim="1">ion</dimension>
+   <field type="string">element</field>
+   <field type="int">atomtype</field>
+   <set>
+    <rc><c>Na</c><c>   1</c></rc>
+    <rc><c>Cl</c><c>   2</c></rc>
+   </set>
+  </array>
+ </atominfo>
+ <structure name="initialpos" >
+  {lattice}
+  <varray name="positions" >
+   <v>       0.00000000       0.00000000       0.00000000 </v>
+   <v>       0.50000000       0.50000000       0.50000000 </v>
+  </varray>
+ </structure>
+ <calculation>
+  <scstep>
+   <energy>
+    <i name="e_fr_energy">     -9.00000000 </i>
+   </energy>
+  </scstep>
+  <scstep>
+   <energy>
+    <i name="e_fr_energy">    -10.50000000 </i>
+   </energy>
+  </scstep>
+  <structure>
+   {lattice}
+   <varray name="positions" >
+    <v>       0.00000000       0.00000000       0.00000000 </v>
+    <v>       0.51000000       0.50000000       0.50000000 </v>
+   </varray>
+  </structure>
+  <varray name="forces" >
+   <v>       0.10000000       0.00000000      -0.20000000 </v>
+   <v>      -0.10000000       0.00000000       0.20000000 </v>
+  </varray>
+  <varray name="stress" >
+   <v>      -1.50000000       0.00000000       0.00000000 </v>
+   <v>       0.00000000      -2.50000000       0.00000000 </v>
+   <v>       0.00000000       0.00000000      -3.50000000 </v>
+  </varray>
+  <energy>
+   <i name="e_fr_energy">    -10.50000000 </i>
+   <i name="e_wo_entrp">    -10.40000000 </i>
+   <i name="e_0_energy">    -10.45000000 </i>
+  </energy>
+ </calculation>
+ <structure name="finalpos" >
+  {lattice}
+  <varray name="positions" >
+   <v>       0.00000000       0.00000000       0.00000000 </v>
+   <v>       0.51000000       0.50000000       0.50000000 </v>
+  </varray>
+ </structure>
+</modeling>
+"#
+        );
+        let mut vasprun = Vasprun::default();
+        for item in VasprunReader::new(contents.as_bytes()) {
+            match item.unwrap() {
+                VasprunItem::Generator { version } => vasprun.version = version,
+                VasprunItem::Incar(incar) => vasprun.incar = incar,
+                VasprunItem::Kpoints(kpoints) => vasprun.kpoints = kpoints,
+                VasprunItem::AtomInfo(elements) => vasprun.elements = elements,
+                VasprunItem::Structure { name, structure } if name == "initialpos" => {
+                    vasprun.initial_structure = Some(structure)
+                }
+                VasprunItem::Structure { name, structure } if name == "finalpos" => {
+                    vasprun.final_structure = Some(structure)
+                }
+                VasprunItem::Step(step) => vasprun.steps.push(*step),
+                _ => (),
+            }
+        }
+        assert_eq!(vasprun.version.as_deref(), Some("6.4.2"));
+        assert_eq!(vasprun.incar["ENCUT"], "520.00000000");
+        assert_eq!(vasprun.kpoints.scheme.as_deref(), Some("Gamma"));
+        assert_eq!(vasprun.kpoints.divisions, Some([4, 4, 4]));
+        assert_eq!(vasprun.kpoints.kpoints[1], [0.25, 0.0, 0.0]);
+        assert_eq!(vasprun.kpoints.weights, vec![0.25, 0.75]);
+        assert_eq!(vasprun.elements, vec!["Na", "Cl"]);
+
+        let initial = vasprun.initial_structure.unwrap();
+        assert_eq!(initial.species, vec!["Na", "Cl"]);
+        assert_eq!(initial.num_atoms, vec![1, 1]);
+        assert_eq!(initial.lattice[1], [0.0, 5.0, 0.0]);
+        assert_eq!(initial.frac_coords[1], [0.5, 0.5, 0.5]);
+        assert_eq!(vasprun.final_structure.unwrap().frac_coords[1], [0.51, 0.5, 0.5]);
+
+        assert_eq!(vasprun.steps.len(), 1);
+        let step = &vasprun.steps[0];
+        assert_eq!(step.num_electronic_steps, 2);
+        assert_eq!(step.structure.frac_coords[1], [0.51, 0.5, 0.5]);
+        assert_eq!(step.forces, vec![[0.1, 0.0, -0.2], [-0.1, 0.0, 0.2]]);
+        assert_eq!(step.stress.unwrap()[2], [0.0, 0.0, -3.5]);
+        assert_eq!(step.free_energy, Some(-10.5));
+        assert_eq!(step.energy_without_entropy, Some(-10.4));
+        assert_eq!(step.energy_sigma0, Some(-10.45));
+
+        // Overflowing and truncated rows are errors instead of panics.
+        for row in ["0.1 ******** 0.0", "0.1 0.0"] {
+            let broken = contents.replacen("0.10000000       0.00000000      -0.20000000", row, 1);
+            let result: Result<Vec<VasprunItem>> = VasprunReader::new(broken.as_bytes()).collect();
+            assert!(result.is_err());
+        }
+    }
+}
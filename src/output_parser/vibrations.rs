@@ -0,0 +1,125 @@
+use super::outcar::{Outcar, VibrationalMode};
+use eyre::Result;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+/// Vibrational analysis of a frequency run.
+#[derive(Debug, Clone, Serialize)]
+pub struct Vibrations {
+    pub directory: String,
+    pub modes: Vec<VibrationalMode>,
+    /// Zero-point energy of the real modes (eV).
+    pub zero_point_energy: f64,
+}
+
+impl Vibrations {
+    /// Reads vibrational modes from OUTCAR in `dir`.
+    pub fn from_dir(dir: &str) -> Result<Vibrations> {
+        let outcar = Outcar::from_file(Path::new(dir).join("OUTCAR"))?;
+        Vibrations::new(dir, &outcar)
+    }
+
+    pub fn new(dir: &str, outcar: &Outcar) -> Result<Vibrations> {
+        if outcar.vibrational_modes.is_empty() {
+            return Err(eyre::eyre!("No vibrational modes found in OUTCAR of {}", dir));
+        }
+        let zero_point_energy = outcar
+            .vibrational_modes
+            .iter()
+            .filter(|mode| !mode.imaginary)
+            .map(|mode| mode.energy_mev / 2.0 / 1000.0)
+            .sum();
+        Ok(Vibrations {
+            directory: dir.to_string(),
+            modes: outcar.vibrational_modes.clone(),
+            zero_point_energy,
+        })
+    }
+
+    pub fn imaginary_modes(&self) -> Vec<&VibrationalMode> {
+        self.modes.iter().filter(|mode| mode.imaginary).collect()
+    }
+
+    /// The imaginary mode with the largest magnitude, e.g. the reaction coordinate of a transition state.
+    pub fn most_imaginary_mode(&self) -> Option<&VibrationalMode> {
+        self.imaginary_modes()
+            .into_iter()
+            .max_by(|a, b| a.frequency_cm.partial_cmp(&b.frequency_cm).unwrap())
+    }
+}
+
+impl fmt::Display for Vibrations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.directory)?;
+        writeln!(
+            f,
+            "{:>6} {:>14} {:>12} {:>12}",
+            "mode", "freq (cm-1)", "freq (THz)", "E (meV)"
+        )?;
+        for (i, mode) in self.modes.iter().enumerate() {
+            let suffix = if mode.imaginary { "i" } else { " " };
+            writeln!(
+                f,
+                "{:>6} {:>13.3}{} {:>12.4} {:>12.4}",
+                i + 1,
+                mode.frequency_cm,
+                suffix,
+                mode.frequency_thz,
+                mode.energy_mev
+            )?;
+        }
+        let imaginary = self.imaginary_modes();
+        writeln!(f, "Imaginary modes: {}", imaginary.len())?;
+        for mode in imaginary {
+            writeln!(f, "  {:.3}i cm-1", mode.frequency_cm)?;
+        }
+        writeln!(f, "Zero-point energy: {:.6} eV", self.zero_point_energy)
+    }
+}
+
+mod tests {
+    #[test]
+    fn test_vibrations() {
+        use super::*;
+        let contents = " Eigenvectors and eigenvalues of the dynamical matrix
+ ----------------------------------------------------
+
+
+   1 f  =   60.000000 THz   376.991118 2PiTHz 2001.384540 cm-1   248.140350 meV
+             X         Y         Z           dx          dy          dz
+      0.000000  0.000000  0.000000     0.000000    0.000000    0.707107
+      0.000000  0.000000  1.150000     0.000000    0.000000   -0.707107
+
+   2 f  =   10.000000 THz    62.831853 2PiTHz  333.564090 cm-1    41.356725 meV
+             X         Y         Z           dx          dy          dz
+      0.000000  0.000000  0.000000     0.800000    0.000000    0.000000
+      0.000000  0.000000  1.150000    -0.600000    0.000000    0.000000
+
+   3 f/i=    2.000000 THz    12.566371 2PiTHz   66.712818 cm-1     8.271345 meV
+             X         Y         Z           dx          dy          dz
+      0.000000  0.000000  0.000000     0.000000    0.707107    0.000000
+      0.000000  0.000000  1.150000     0.000000    0.707107    0.000000
+
+   4 f/i=   15.000000 THz    94.247780 2PiTHz  500.346135 cm-1    62.035087 meV
+             X         Y         Z           dx          dy          dz
+      0.000000  0.000000  0.000000     0.000000    0.600000    0.000000
+      0.000000  0.000000  1.150000     0.000000   -0.800000    0.000000
+
+ Eigenvectors after division by SQRT(mass)
+";
+        assert!(Vibrations::new("empty", &Outcar::parse("")).is_err());
+        let vibrations = Vibrations::new("freq", &Outcar::parse(contents)).unwrap();
+        assert_eq!(vibrations.modes.len(), 4);
+        assert_eq!(vibrations.modes[0].frequency_thz, 60.0);
+        assert_eq!(vibrations.modes[1].frequency_cm, 333.56409);
+        assert_eq!(vibrations.modes[1].eigenvector, vec![[0.8, 0.0, 0.0], [-0.6, 0.0, 0.0]]);
+        assert_eq!(vibrations.imaginary_modes().len(), 2);
+        // Only real modes contribute to the zero-point energy.
+        assert!((vibrations.zero_point_energy - (248.14035 + 41.356725) / 2000.0).abs() < 1e-12);
+
+        let mode = vibrations.most_imaginary_mode().unwrap();
+        assert_eq!(mode.frequency_cm, 500.346135);
+        assert_eq!(mode.eigenvector, vec![[0.0, 0.6, 0.0], [0.0, -0.8, 0.0]]);
+    }
+}
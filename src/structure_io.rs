@@ -0,0 +1,453 @@
+use crate::structure_util::{atom_species, cart_to_frac};
+use eyre::Result;
+use std::collections::HashMap;
+use std::path::Path;
+use vasp_files::poscar::Poscar;
+
+/// File formats of input structures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StructureFormat {
+    /// POSCAR, CONTCAR or *.vasp
+    Poscar,
+    Cif,
+    /// XYZ or extended XYZ. Only extended XYZ has the lattice.
+    Xyz,
+}
+
+impl StructureFormat {
+    /// Detects format from the file extension. Files without known extension are regarded as POSCAR.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> StructureFormat {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("cif") => StructureFormat::Cif,
+            Some("xyz") | Some("extxyz") => StructureFormat::Xyz,
+            _ => StructureFormat::Poscar,
+        }
+    }
+}
+
+/// Reads structure in any supported format as POSCAR.
+pub fn read_structure<P: AsRef<Path>>(path: P) -> Result<Poscar> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path).map_err(|_| eyre::eyre!("Could not read {}", path.display()))?;
+    let comment = path.file_stem().unwrap().to_str().unwrap();
+    match StructureFormat::from_path(path) {
+        StructureFormat::Poscar => Poscar::from_str(&contents),
+        StructureFormat::Cif => parse_cif(&contents, comment),
+        StructureFormat::Xyz => parse_extxyz(&contents, comment),
+    }
+}
+
+/// Builds POSCAR from atoms. Consecutive atoms of the same element are grouped.
+pub fn poscar_from_atoms(
+    comment: &str,
+    lattice: [[f64; 3]; 3],
+    species: &[String],
+    frac_coords: Vec<[f64; 3]>,
+) -> Poscar {
+    let mut groups: Vec<String> = vec![];
+    let mut num_atoms: Vec<usize> = vec![];
+    for element in species {
+        match groups.last() {
+            Some(last) if last == element => *num_atoms.last_mut().unwrap() += 1,
+            _ => {
+                groups.push(element.clone());
+                num_atoms.push(1);
+            }
+        }
+    }
+    Poscar {
+        comment: comment.to_string(),
+        scale: 1.0,
+        lattice,
+        species: groups,
+        num_atoms,
+        frac_coords,
+        selective_dynamics: None,
+    }
+}
+
+/// Sorts atoms by element, in the order of `species_order` if given, or in the order of appearance.
+pub fn sort_species(poscar: &Poscar, species_order: Option<&[String]>) -> Result<Poscar> {
+    let species = atom_species(poscar);
+    let mut order: Vec<String> = match species_order {
+        Some(order) => order.to_vec(),
+        None => vec![],
+    };
+    for element in &species {
+        if !order.contains(element) {
+            if species_order.is_some() {
+                return Err(eyre::eyre!("Element {} is missing in species_order", element));
+            }
+            order.push(element.clone());
+        }
+    }
+    let rank = |element: &String| order.iter().position(|e| e == element).unwrap();
+    let mut indices: Vec<usize> = (0..species.len()).collect();
+    indices.sort_by_key(|&i| rank(&species[i]));
+
+    let sorted_species: Vec<String> = indices.iter().map(|&i| species[i].clone()).collect();
+    let frac_coords = indices.iter().map(|&i| poscar.frac_coords[i]).collect();
+    let mut sorted = poscar_from_atoms(&poscar.comment, poscar.lattice, &sorted_species, frac_coords);
+    sorted.scale = poscar.scale;
+    sorted.selective_dynamics = poscar
+        .selective_dynamics
+        .as_ref()
+        .map(|flags| indices.iter().map(|&i| flags[i]).collect());
+    Ok(sorted)
+}
+
+/// Lattice vectors from lattice parameters, with a along x and b in the xy plane.
+pub fn lattice_from_parameters(a: f64, b: f64, c: f64, alpha: f64, beta: f64, gamma: f64) -> [[f64; 3]; 3] {
+    let (alpha, beta, gamma) = (alpha.to_radians(), beta.to_radians(), gamma.to_radians());
+    let cx = c * beta.cos();
+    let cy = c * (alpha.cos() - beta.cos() * gamma.cos()) / gamma.sin();
+    let cz = (c * c - cx * cx - cy * cy).sqrt();
+    let lattice = [[a, 0.0, 0.0], [b * gamma.cos(), b * gamma.sin(), 0.0], [cx, cy, cz]];
+    // Remove round-off of right angles.
+    lattice.map(|v| v.map(|x| if x.abs() < 1e-10 { 0.0 } else { x }))
+}
+
+/// Parses a CIF number, dropping the standard uncertainty, e.g. `5.431(2)`.
+fn cif_number(value: &str) -> Option<f64> {
+    value.split('(').next()?.parse().ok()
+}
+
+/// Splits a CIF data line into values, keeping quoted strings together.
+fn cif_values(line: &str) -> Vec<String> {
+    let mut values = vec![];
+    let mut chars = line.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '\'' || c == '"' {
+            chars.next();
+            let value: String = chars.by_ref().take_while(|&x| x != c).collect();
+            values.push(value);
+        } else {
+            let mut value = String::new();
+            while let Some(&x) = chars.peek() {
+                if x.is_whitespace() {
+                    break;
+                }
+                value.push(x);
+                chars.next();
+            }
+            values.push(value);
+        }
+    }
+    values
+}
+
+/// Symmetry operation `x' = R x + t` parsed from CIF, e.g. `-x+1/2, y, z+1/2`.
+fn parse_symmetry_operation(operation: &str) -> Result<([[f64; 3]; 3], [f64; 3])> {
+    let mut rotation = [[0.0; 3]; 3];
+    let mut translation = [0.0; 3];
+    let components: Vec<&str> = operation.split(',').collect();
+    if components.len() != 3 {
+        return Err(eyre::eyre!("Invalid symmetry operation '{}'", operation));
+    }
+    for (i, component) in components.iter().enumerate() {
+        let component: String = component.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut sign = 1.0;
+        let mut number = String::new();
+        let flush_number = |number: &mut String, sign: f64, translation: &mut f64| -> Result<()> {
+            if number.is_empty() {
+                return Ok(());
+            }
+            let value = match number.split_once('/') {
+                Some((n, d)) => n.parse::<f64>()? / d.parse::<f64>()?,
+                None => number.parse::<f64>()?,
+            };
+            *translation += sign * value;
+            number.clear();
+            Ok(())
+        };
+        for c in component.to_ascii_lowercase().chars() {
+            match c {
+                '+' | '-' => {
+                    flush_number(&mut number, sign, &mut translation[i])?;
+                    sign = if c == '-' { -1.0 } else { 1.0 };
+                }
+                'x' | 'y' | 'z' => {
+                    rotation[i][(c as u8 - b'x') as usize] += sign;
+                    sign = 1.0;
+                }
+                _ => number.push(c),
+            }
+        }
+        flush_number(&mut number, sign, &mut translation[i])?;
+    }
+    Ok((rotation, translation))
+}
+
+/// Parses the first data block of CIF, generating all atoms in the cell from the symmetry operations.
+pub fn parse_cif(contents: &str, comment: &str) -> Result<Poscar> {
+    let mut tags: HashMap<String, String> = HashMap::new();
+    // Each loop is the list of its tags and the rows of values.
+    let mut loops: Vec<(Vec<String>, Vec<Vec<String>>)> = vec![];
+    let mut lines = contents.lines().map(|l| l.trim()).peekable();
+    while let Some(line) = lines.next() {
+        if line.starts_with("data_") && !tags.is_empty() {
+            break;
+        }
+        if line == "loop_" {
+            let mut loop_tags = vec![];
+            while let Some(tag) = lines.peek().filter(|l| l.starts_with('_')) {
+                loop_tags.push(tag.split_whitespace().next().unwrap().to_ascii_lowercase());
+                lines.next();
+            }
+            let mut values = vec![];
+            while let Some(row) = lines.peek() {
+                if row.starts_with('_') || row.starts_with("loop_") || row.starts_with("data_") {
+                    break;
+                }
+                if !row.is_empty() && !row.starts_with('#') {
+                    values.extend(cif_values(row));
+                }
+                lines.next();
+            }
+            let rows = values.chunks(loop_tags.len().max(1)).map(|c| c.to_vec()).collect();
+            loops.push((loop_tags, rows));
+        } else if line.starts_with('_') {
+            let values = cif_values(line);
+            if values.len() >= 2 {
+                tags.insert(values[0].to_ascii_lowercase(), values[1].clone());
+            }
+        }
+    }
+
+    let parameter = |name: &str| -> Result<f64> {
+        tags.get(name)
+            .and_then(|v| cif_number(v))
+            .ok_or_else(|| eyre::eyre!("{} not found in CIF", name))
+    };
+    let lattice = lattice_from_parameters(
+        parameter("_cell_length_a")?,
+        parameter("_cell_length_b")?,
+        parameter("_cell_length_c")?,
+        parameter("_cell_angle_alpha")?,
+        parameter("_cell_angle_beta")?,
+        parameter("_cell_angle_gamma")?,
+    );
+
+    let find_loop = |tag: &str| loops.iter().find(|(loop_tags, _)| loop_tags.iter().any(|t| t == tag));
+    let mut operations = vec![];
+    for tag in ["_symmetry_equiv_pos_as_xyz", "_space_group_symop_operation_xyz"] {
+        if let Some((loop_tags, rows)) = find_loop(tag) {
+            let column = loop_tags.iter().position(|t| t == tag).unwrap();
+            for row in rows {
+                operations.push(parse_symmetry_operation(&row[column])?);
+            }
+            break;
+        }
+    }
+    if operations.is_empty() {
+        operations.push(parse_symmetry_operation("x,y,z")?);
+    }
+
+    let (site_tags, site_rows) =
+        find_loop("_atom_site_fract_x").ok_or_else(|| eyre::eyre!("Fractional coordinates not found in CIF"))?;
+    let column = |tag: &str| site_tags.iter().position(|t| t == tag);
+    let element_column = column("_atom_site_type_symbol")
+        .or_else(|| column("_atom_site_label"))
+        .ok_or_else(|| eyre::eyre!("Atom types not found in CIF"))?;
+    let coord_column = |tag: &str| column(tag).ok_or_else(|| eyre::eyre!("{} not found in CIF", tag));
+    let coord_columns = [
+        coord_column("_atom_site_fract_x")?,
+        coord_column("_atom_site_fract_y")?,
+        coord_column("_atom_site_fract_z")?,
+    ];
+
+    let mut species: Vec<String> = vec![];
+    let mut frac_coords: Vec<[f64; 3]> = vec![];
+    for row in site_rows {
+        if row.len() != site_tags.len() {
+            return Err(eyre::eyre!("Incomplete atom site in CIF: {}", row.join(" ")));
+        }
+        // Labels and type symbols look like Fe1 or Fe3+.
+        let element: String = row[element_column]
+            .chars()
+            .take_while(|c| c.is_ascii_alphabetic())
+            .collect();
+        let mut site = [0.0; 3];
+        for (k, &c) in coord_columns.iter().enumerate() {
+            site[k] = cif_number(&row[c]).ok_or_else(|| eyre::eyre!("Invalid coordinate in CIF: {}", row[c]))?;
+        }
+        for (rotation, translation) in &operations {
+            let mut frac = [0.0; 3];
+            for i in 0..3 {
+                let x = rotation[i][0] * site[0] + rotation[i][1] * site[1] + rotation[i][2] * site[2] + translation[i];
+                frac[i] = x - x.floor();
+            }
+            let duplicate = frac_coords.iter().any(|existing| {
+                (0..3).all(|i| {
+                    let d = (existing[i] - frac[i]).abs();
+                    d.min(1.0 - d) < 1e-4
+                })
+            });
+            if !duplicate {
+                species.push(element.clone());
+                frac_coords.push(frac);
+            }
+        }
+    }
+    Ok(poscar_from_atoms(comment, lattice, &species, frac_coords))
+}
+
+/// Parses the first frame of extended XYZ, which has `Lattice` in the comment line.
+pub fn parse_extxyz(contents: &str, comment: &str) -> Result<Poscar> {
+    let mut lines = contents.lines();
+    let num_atoms: usize = lines
+        .next()
+        .and_then(|l| l.trim().parse().ok())
+        .ok_or_else(|| eyre::eyre!("Invalid XYZ: the first line should be the number of atoms"))?;
+    let comment_line = lines.next().unwrap_or("");
+
+    // key=value pairs, where values may be quoted.
+    let mut info: HashMap<String, String> = HashMap::new();
+    let mut rest = comment_line.trim();
+    while let Some((key, after)) = rest.split_once('=') {
+        let key = key.split_whitespace().last().unwrap_or("").to_string();
+        let after = after.trim_start();
+        let (value, remaining) = match after.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => after.split_once(char::is_whitespace).unwrap_or((after, "")),
+        };
+        info.insert(key.to_ascii_lowercase(), value.to_string());
+        rest = remaining.trim();
+    }
+
+    let lattice_values: Vec<f64> = info
+        .get("lattice")
+        .ok_or_else(|| eyre::eyre!("Lattice not found in XYZ. Use extended XYZ with Lattice=\"...\""))?
+        .split_whitespace()
+        .map(|v| v.parse())
+        .collect::<Result<_, _>>()?;
+    if lattice_values.len() != 9 {
+        return Err(eyre::eyre!("Lattice of extended XYZ should have 9 values"));
+    }
+    let lattice = [
+        [lattice_values[0], lattice_values[1], lattice_values[2]],
+        [lattice_values[3], lattice_values[4], lattice_values[5]],
+        [lattice_values[6], lattice_values[7], lattice_values[8]],
+    ];
+
+    // Columns of species and positions from Properties, e.g. species:S:1:pos:R:3:forces:R:3
+    let (mut species_column, mut pos_column) = (0, 1);
+    if let Some(properties) = info.get("properties") {
+        let fields: Vec<&str> = properties.split(':').collect();
+        let mut column = 0;
+        for property in fields.chunks(3) {
+            if property.len() != 3 {
+                break;
+            }
+            match property[0].to_ascii_lowercase().as_str() {
+                "species" => species_column = column,
+                "pos" => pos_column = column,
+                _ => (),
+            }
+            column += property[2].parse::<usize>()?;
+        }
+    }
+
+    let mut species = vec![];
+    let mut cartesian = vec![];
+    for _ in 0..num_atoms {
+        let line = lines
+            .next()
+            .ok_or_else(|| eyre::eyre!("XYZ has fewer atoms than {}", num_atoms))?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < pos_column + 3 {
+            return Err(eyre::eyre!("Invalid XYZ line: {}", line));
+        }
+        species.push(fields[species_column].to_string());
+        let mut position = [0.0; 3];
+        for (k, p) in position.iter_mut().enumerate() {
+            *p = fields[pos_column + k].parse()?;
+        }
+        cartesian.push(position);
+    }
+    let frac_coords = cartesian
+        .iter()
+        .map(|position| cart_to_frac(position, &lattice))
+        .collect();
+    Ok(poscar_from_atoms(comment, lattice, &species, frac_coords))
+}
+
+mod tests {
+    #[test]
+    fn test_parse_cif() {
+        use super::*;
+        // Zincblende ZnS, with only the centering operations of F-43m for brevity.
+        let contents = "data_ZnS
+_cell_length_a 5.4093(3)
+_cell_length_b 5.4093(3)
+_cell_length_c 5.4093(3)
+_cell_angle_alpha 90
+_cell_angle_beta 90
+_cell_angle_gamma 90.000(0)
+loop_
+_symmetry_equiv_pos_site_id
+_symmetry_equiv_pos_as_xyz
+1 'x, y, z'
+2 'x, y+1/2, z+1/2'
+3 'x+1/2, y, z+1/2'
+4 'x+1/2, y+1/2, z'
+loop_
+_atom_site_label
+_atom_site_type_symbol
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+_atom_site_occupancy
+Zn1 Zn2+ 0 0 0 1
+S1 S2- 0.25(1) 0.25(1) 0.25(1) 1
+";
+        let poscar = parse_cif(contents, "ZnS").unwrap();
+        assert_eq!(poscar.species, vec!["Zn", "S"]);
+        assert_eq!(poscar.num_atoms, vec![4, 4]);
+        assert_eq!(poscar.lattice[0], [5.4093, 0.0, 0.0]);
+        assert_eq!(poscar.lattice[2], [0.0, 0.0, 5.4093]);
+        assert_eq!(poscar.frac_coords[1], [0.0, 0.5, 0.5]);
+        assert_eq!(poscar.frac_coords[7], [0.75, 0.75, 0.25]);
+
+        let missing_y = contents.replace("_atom_site_fract_y\n", "");
+        assert!(parse_cif(&missing_y, "ZnS").is_err());
+    }
+
+    #[test]
+    fn test_parse_extxyz() {
+        use super::*;
+        let contents = "2
+Lattice=\"4.0 0.0 0.0 0.0 4.0 0.0 0.0 0.0 8.0\" Properties=species:S:1:pos:R:3:forces:R:3 energy=-10.0 pbc=\"T T T\"
+Na 0.0 0.0 0.0 0.1 0.0 0.0
+Cl 2.0 2.0 4.0 -0.1 0.0 0.0
+3
+Lattice=\"4.0 0.0 0.0 0.0 4.0 0.0 0.0 0.0 8.0\" Properties=species:S:1:pos:R:3:forces:R:3
+Na 0.0 0.0 0.0 0.0 0.0 0.0
+Cl 2.0 2.0 4.0 0.0 0.0 0.0
+Cl 2.0 2.0 2.0 0.0 0.0 0.0
+";
+        let poscar = parse_extxyz(contents, "NaCl").unwrap();
+        assert_eq!(poscar.species, vec!["Na", "Cl"]);
+        assert_eq!(poscar.num_atoms, vec![1, 1]);
+        assert_eq!(poscar.lattice[2], [0.0, 0.0, 8.0]);
+        assert_eq!(poscar.frac_coords[1], [0.5, 0.5, 0.5]);
+
+        // Positions after other properties.
+        let reordered = "1
+Lattice=\"4.0 0.0 0.0 0.0 4.0 0.0 0.0 0.0 4.0\" Properties=id:I:1:species:S:1:pos:R:3
+7 Fe 1.0 2.0 3.0
+";
+        let poscar = parse_extxyz(reordered, "Fe").unwrap();
+        assert_eq!(poscar.species, vec!["Fe"]);
+        assert_eq!(poscar.frac_coords[0], [0.25, 0.5, 0.75]);
+
+        assert!(parse_extxyz("1\nplain xyz\nFe 0.0 0.0 0.0\n", "Fe").is_err());
+    }
+}
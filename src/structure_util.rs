@@ -0,0 +1,671 @@
+use eyre::Result;
+use vasp_files::poscar::Poscar;
+
+/// Converts fractional coordinates to Cartesian coordinates.
+pub fn frac_to_cart(frac: &[f64; 3], lattice: &[[f64; 3]; 3]) -> [f64; 3] {
+    let mut cart = [0.0; 3];
+    for (i, c) in cart.iter_mut().enumerate() {
+        *c = frac[0] * lattice[0][i] + frac[1] * lattice[1][i] + frac[2] * lattice[2][i];
+    }
+    cart
+}
+
+/// Returns the lattice vectors multiplied by the scale factor.
+pub fn scaled_lattice(poscar: &Poscar) -> [[f64; 3]; 3] {
+    let mut lattice = poscar.lattice;
+    for row in lattice.iter_mut() {
+        for x in row.iter_mut() {
+            *x *= poscar.scale;
+        }
+    }
+    lattice
+}
+
+/// Returns Cartesian coordinates of all atoms in Angstrom.
+pub fn cartesian_coords(poscar: &Poscar) -> Vec<[f64; 3]> {
+    let lattice = scaled_lattice(poscar);
+    poscar
+        .frac_coords
+        .iter()
+        .map(|frac| frac_to_cart(frac, &lattice))
+        .collect()
+}
+
+/// Returns the element of each atom, in POSCAR order.
+pub fn atom_species(poscar: &Poscar) -> Vec<String> {
+    poscar
+        .species
+        .iter()
+        .zip(poscar.num_atoms.iter())
+        .flat_map(|(species, &n)| vec![species.clone(); n])
+        .collect()
+}
+
+/// Volume of the cell spanned by `lattice`.
+pub fn volume(lattice: &[[f64; 3]; 3]) -> f64 {
+    let [a, b, c] = lattice;
+    let cross = [
+        b[1] * c[2] - b[2] * c[1],
+        b[2] * c[0] - b[0] * c[2],
+        b[0] * c[1] - b[1] * c[0],
+    ];
+    (a[0] * cross[0] + a[1] * cross[1] + a[2] * cross[2]).abs()
+}
+
+/// Converts Cartesian coordinates to fractional coordinates.
+pub fn cart_to_frac(cart: &[f64; 3], lattice: &[[f64; 3]; 3]) -> [f64; 3] {
+    let [a, b, c] = lattice;
+    let det =
+        a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0]) + a[2] * (b[0] * c[1] - b[1] * c[0]);
+    // Rows of the inverse of the transposed lattice matrix.
+    let inverse = [
+        [
+            (b[1] * c[2] - b[2] * c[1]) / det,
+            (b[2] * c[0] - b[0] * c[2]) / det,
+            (b[0] * c[1] - b[1] * c[0]) / det,
+        ],
+        [
+            (c[1] * a[2] - c[2] * a[1]) / det,
+            (c[2] * a[0] - c[0] * a[2]) / det,
+            (c[0] * a[1] - c[1] * a[0]) / det,
+        ],
+        [
+            (a[1] * b[2] - a[2] * b[1]) / det,
+            (a[2] * b[0] - a[0] * b[2]) / det,
+            (a[0] * b[1] - a[1] * b[0]) / det,
+        ],
+    ];
+    let mut frac = [0.0; 3];
+    for (i, f) in frac.iter_mut().enumerate() {
+        *f = inverse[i][0] * cart[0] + inverse[i][1] * cart[1] + inverse[i][2] * cart[2];
+    }
+    frac
+}
+
+/// Wraps fractional displacement to the nearest periodic image.
+pub fn minimum_image(frac: [f64; 3]) -> [f64; 3] {
+    frac.map(|x| x - x.round())
+}
+
+/// Distance between two atoms in fractional coordinates, under minimum image convention.
+pub fn distance(frac_a: &[f64; 3], frac_b: &[f64; 3], lattice: &[[f64; 3]; 3]) -> f64 {
+    let d = minimum_image([frac_b[0] - frac_a[0], frac_b[1] - frac_a[1], frac_b[2] - frac_a[2]]);
+    let cart = frac_to_cart(&d, lattice);
+    (cart[0].powi(2) + cart[1].powi(2) + cart[2].powi(2)).sqrt()
+}
+
+/// Checks that the initial and final structures of NEB have the same atoms in the same order.
+pub fn check_atom_ordering(initial: &Poscar, final_: &Poscar) -> Result<()> {
+    if initial.species != final_.species || initial.num_atoms != final_.num_atoms {
+        return Err(eyre::eyre!(
+            "Initial and final structures have different atoms: {:?} {:?} and {:?} {:?}",
+            initial.species,
+            initial.num_atoms,
+            final_.species,
+            final_.num_atoms
+        ));
+    }
+    let lattice = scaled_lattice(initial);
+    let species = atom_species(initial);
+    for (i, frac_i) in initial.frac_coords.iter().enumerate() {
+        let displacement = distance(frac_i, &final_.frac_coords[i], &lattice);
+        // Another atom of the same element much closer than its counterpart means the atoms are reordered.
+        let closer = final_
+            .frac_coords
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| j != i && species[j] == species[i])
+            .map(|(j, frac_j)| (j, distance(frac_i, frac_j, &lattice)))
+            .find(|&(_, d)| d < 0.5 * displacement && displacement > 0.5);
+        if let Some((j, d)) = closer {
+            return Err(eyre::eyre!(
+                "Atom {} ({}) moves {:.2} A between initial and final structures, but atom {} of final structure is {:.2} A away. Check the atom ordering.",
+                i + 1,
+                species[i],
+                displacement,
+                j + 1,
+                d
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Linearly interpolates `num_images` images between the initial and final structures.
+/// Atoms move to the nearest periodic image of their final positions.
+pub fn interpolate_linear(initial: &Poscar, final_: &Poscar, num_images: usize) -> Vec<Poscar> {
+    (1..=num_images)
+        .map(|n| {
+            let t = n as f64 / (num_images + 1) as f64;
+            let mut image = initial.clone();
+            image.comment = format!("{} (image {:02})", initial.comment.trim(), n);
+            for (i, row) in image.lattice.iter_mut().enumerate() {
+                for (j, x) in row.iter_mut().enumerate() {
+                    *x += t * (final_.lattice[i][j] - initial.lattice[i][j]);
+                }
+            }
+            for (frac, frac_final) in image.frac_coords.iter_mut().zip(&final_.frac_coords) {
+                let d = minimum_image([
+                    frac_final[0] - frac[0],
+                    frac_final[1] - frac[1],
+                    frac_final[2] - frac[2],
+                ]);
+                for k in 0..3 {
+                    frac[k] += t * d[k];
+                }
+            }
+            image
+        })
+        .collect()
+}
+
+/// Interpolates images with the image dependent pair potential (IDPP) method
+/// (Smidstrup et al., J. Chem. Phys. 140, 214106 (2014)), starting from linear interpolation.
+/// Pairwise distances of each image are optimized towards linearly interpolated distances.
+pub fn interpolate_idpp(initial: &Poscar, final_: &Poscar, num_images: usize) -> Vec<Poscar> {
+    let num_atoms = initial.frac_coords.len();
+    let pair_distances = |poscar: &Poscar| -> Vec<f64> {
+        let lattice = scaled_lattice(poscar);
+        let mut distances = vec![0.0; num_atoms * num_atoms];
+        for i in 0..num_atoms {
+            for j in i + 1..num_atoms {
+                let d = distance(&poscar.frac_coords[i], &poscar.frac_coords[j], &lattice);
+                distances[i * num_atoms + j] = d;
+                distances[j * num_atoms + i] = d;
+            }
+        }
+        distances
+    };
+    let initial_distances = pair_distances(initial);
+    let final_distances = pair_distances(final_);
+
+    let mut images = interpolate_linear(initial, final_, num_images);
+    for (n, image) in images.iter_mut().enumerate() {
+        let t = (n + 1) as f64 / (num_images + 1) as f64;
+        let targets: Vec<f64> = initial_distances
+            .iter()
+            .zip(&final_distances)
+            .map(|(d_i, d_f)| d_i + t * (d_f - d_i))
+            .collect();
+        let lattice = scaled_lattice(image);
+        let mut positions = cartesian_coords(image);
+        let fixed: Vec<bool> = match &initial.selective_dynamics {
+            Some(flags) => flags.iter().map(|f| !f.iter().any(|&x| x)).collect(),
+            None => vec![false; num_atoms],
+        };
+
+        // Objective S = sum_{i<j} (d_target - d)^2 / d^4, and its negative gradient.
+        let objective = |positions: &[[f64; 3]]| -> (f64, Vec<[f64; 3]>) {
+            let mut value = 0.0;
+            let mut forces = vec![[0.0; 3]; num_atoms];
+            for i in 0..num_atoms {
+                for j in i + 1..num_atoms {
+                    let diff = [
+                        positions[i][0] - positions[j][0],
+                        positions[i][1] - positions[j][1],
+                        positions[i][2] - positions[j][2],
+                    ];
+                    let frac = minimum_image(cart_to_frac(&diff, &lattice));
+                    let r = frac_to_cart(&frac, &lattice);
+                    let d = (r[0].powi(2) + r[1].powi(2) + r[2].powi(2)).sqrt();
+                    if d < 1e-8 {
+                        continue;
+                    }
+                    let delta = targets[i * num_atoms + j] - d;
+                    value += delta.powi(2) / d.powi(4);
+                    let d_value = -2.0 * delta / d.powi(4) - 4.0 * delta.powi(2) / d.powi(5);
+                    for k in 0..3 {
+                        let f = -d_value * r[k] / d;
+                        forces[i][k] += f;
+                        forces[j][k] -= f;
+                    }
+                }
+            }
+            for (force, &fixed) in forces.iter_mut().zip(&fixed) {
+                if fixed {
+                    *force = [0.0; 3];
+                }
+            }
+            (value, forces)
+        };
+
+        // Steepest descent with adaptive step size.
+        let (mut value, mut forces) = objective(&positions);
+        let mut step: f64 = 0.1;
+        for _ in 0..1000 {
+            let max_force = forces
+                .iter()
+                .map(|f| (f[0].powi(2) + f[1].powi(2) + f[2].powi(2)).sqrt())
+                .fold(0.0, f64::max);
+            if max_force < 1e-4 || step < 1e-10 {
+                break;
+            }
+            // Limit the largest displacement to 0.1 Angstrom.
+            let scale = step.min(0.1 / max_force);
+            let trial: Vec<[f64; 3]> = positions
+                .iter()
+                .zip(&forces)
+                .map(|(p, f)| [p[0] + scale * f[0], p[1] + scale * f[1], p[2] + scale * f[2]])
+                .collect();
+            let (trial_value, trial_forces) = objective(&trial);
+            if trial_value < value {
+                positions = trial;
+                value = trial_value;
+                forces = trial_forces;
+                step *= 1.2;
+            } else {
+                step *= 0.5;
+            }
+        }
+        image.frac_coords = positions.iter().map(|p| cart_to_frac(p, &lattice)).collect();
+    }
+    images
+}
+
+/// Criteria to select atoms. An atom is selected if it matches any of the criteria.
+#[derive(Debug, Clone, Default)]
+pub struct AtomSelection {
+    /// 0-based indices of atoms.
+    pub indices: Vec<usize>,
+    pub elements: Vec<String>,
+    /// Select atoms with z coordinate above this value.
+    pub above: Option<f64>,
+    /// Select atoms with z coordinate below this value.
+    pub below: Option<f64>,
+    /// Whether `above` and `below` are fractional coordinates instead of Cartesian (Angstrom).
+    pub fractional: bool,
+    /// Select atoms in this number of layers from the bottom.
+    pub bottom_layers: Option<usize>,
+    /// Atoms within this distance (Angstrom) along z belong to the same layer.
+    pub layer_tolerance: f64,
+}
+
+impl AtomSelection {
+    /// Returns whether each atom of `poscar` is selected. Indices beyond the atoms of `poscar` are errors.
+    pub fn select(&self, poscar: &Poscar) -> Result<Vec<bool>> {
+        let num_atoms = poscar.frac_coords.len();
+        if let Some(&i) = self.indices.iter().find(|&&i| i >= num_atoms) {
+            return Err(eyre::eyre!(
+                "Invalid atom index {}. POSCAR has {} atoms",
+                i + 1,
+                num_atoms
+            ));
+        }
+        let species = atom_species(poscar);
+        let cartesian = cartesian_coords(poscar);
+        let heights: Vec<f64> = match self.fractional {
+            true => poscar.frac_coords.iter().map(|frac| frac[2]).collect(),
+            false => cartesian.iter().map(|position| position[2]).collect(),
+        };
+        let layers = layer_indices(&cartesian, self.layer_tolerance);
+        Ok((0..heights.len())
+            .map(|i| {
+                self.indices.contains(&i)
+                    || self.elements.contains(&species[i])
+                    || self.above.is_some_and(|z| heights[i] > z)
+                    || self.below.is_some_and(|z| heights[i] < z)
+                    || self.bottom_layers.is_some_and(|n| layers[i] < n)
+            })
+            .collect())
+    }
+}
+
+/// Assigns each atom to a layer along z, counted from the bottom.
+/// A new layer starts where the gap between consecutive heights exceeds `tolerance`.
+pub fn layer_indices(positions: &[[f64; 3]], tolerance: f64) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..positions.len()).collect();
+    order.sort_by(|&a, &b| positions[a][2].partial_cmp(&positions[b][2]).unwrap());
+    let mut layers = vec![0; positions.len()];
+    let mut layer = 0;
+    for (k, &i) in order.iter().enumerate() {
+        if k > 0 && positions[i][2] - positions[order[k - 1]][2] > tolerance {
+            layer += 1;
+        }
+        layers[i] = layer;
+    }
+    layers
+}
+
+/// Parses 1-based index ranges like "1-4, 7" into 0-based indices.
+pub fn parse_index_ranges(ranges: &str) -> Result<Vec<usize>> {
+    let mut indices = vec![];
+    for range in ranges.split(',').map(|r| r.trim()).filter(|r| !r.is_empty()) {
+        let parse = |i: &str| -> Result<usize> {
+            match i.trim().parse::<usize>() {
+                Ok(i) if i > 0 => Ok(i - 1),
+                _ => Err(eyre::eyre!("Invalid atom index '{}' in '{}'", i.trim(), ranges)),
+            }
+        };
+        match range.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    return Err(eyre::eyre!("Invalid atom index range '{}' in '{}'", range, ranges));
+                }
+                indices.extend(start..=end)
+            }
+            None => indices.push(parse(range)?),
+        }
+    }
+    Ok(indices)
+}
+
+/// Space group operation acting on fractional coordinates, `x' = rotation * x + translation`.
+#[derive(Debug, Clone)]
+pub struct SymmetryOperation {
+    pub rotation: [[i32; 3]; 3],
+    pub translation: [f64; 3],
+}
+
+impl SymmetryOperation {
+    pub fn apply(&self, frac: &[f64; 3]) -> [f64; 3] {
+        let mut image = self.translation;
+        for (x, row) in image.iter_mut().zip(&self.rotation) {
+            *x += row[0] as f64 * frac[0] + row[1] as f64 * frac[1] + row[2] as f64 * frac[2];
+        }
+        image
+    }
+}
+
+fn determinant(m: &[[i32; 3]; 3]) -> i32 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1]) - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+/// Finds space group operations of the structure, matching atoms of the same element within `symprec` (Angstrom).
+/// Rotations are searched among matrices with entries -1, 0 and 1, which covers the point group of reduced cells.
+pub fn symmetry_operations(poscar: &Poscar, symprec: f64) -> Vec<SymmetryOperation> {
+    let lattice = scaled_lattice(poscar);
+    let species = atom_species(poscar);
+    let metric: Vec<Vec<f64>> = (0..3)
+        .map(|i| {
+            (0..3)
+                .map(|j| (0..3).map(|k| lattice[i][k] * lattice[j][k]).sum())
+                .collect()
+        })
+        .collect();
+    let max_length = (0..3).map(|i| metric[i][i].sqrt()).fold(0.0, f64::max);
+
+    // Rotations preserving the metric, R^T G R = G.
+    let mut rotations = vec![];
+    for n in 0..3_i32.pow(9) {
+        let mut rotation = [[0; 3]; 3];
+        for (k, entry) in rotation.iter_mut().flatten().enumerate() {
+            *entry = (n / 3_i32.pow(k as u32)) % 3 - 1;
+        }
+        if determinant(&rotation).abs() != 1 {
+            continue;
+        }
+        let preserves_metric = (0..3).all(|i| {
+            (0..3).all(|j| {
+                let rotated: f64 = (0..3)
+                    .flat_map(|k| (0..3).map(move |l| (k, l)))
+                    .map(|(k, l)| rotation[k][i] as f64 * metric[k][l] * rotation[l][j] as f64)
+                    .sum();
+                (rotated - metric[i][j]).abs() < 2.0 * symprec * max_length
+            })
+        });
+        if preserves_metric {
+            rotations.push(rotation);
+        }
+    }
+
+    // Translations mapping an atom of the least common element onto the atoms of the same element.
+    let reference = (0..species.len())
+        .min_by_key(|&i| species.iter().filter(|s| **s == species[i]).count())
+        .unwrap();
+    let mut operations = vec![];
+    for rotation in rotations {
+        for j in (0..species.len()).filter(|&j| species[j] == species[reference]) {
+            let rotated = SymmetryOperation {
+                rotation,
+                translation: [0.0; 3],
+            }
+            .apply(&poscar.frac_coords[reference]);
+            let frac_j = &poscar.frac_coords[j];
+            let operation = SymmetryOperation {
+                rotation,
+                translation: [0, 1, 2].map(|k| (frac_j[k] - rotated[k]).rem_euclid(1.0)),
+            };
+            if symmetry_image_indices(poscar, &operation, symprec).is_some() {
+                operations.push(operation);
+            }
+        }
+    }
+    operations
+}
+
+/// Index of the atom each atom is mapped onto by `operation`, or `None` if it is not a symmetry of the structure.
+pub fn symmetry_image_indices(poscar: &Poscar, operation: &SymmetryOperation, symprec: f64) -> Option<Vec<usize>> {
+    let lattice = scaled_lattice(poscar);
+    let species = atom_species(poscar);
+    poscar
+        .frac_coords
+        .iter()
+        .enumerate()
+        .map(|(i, frac)| {
+            let image = operation.apply(frac);
+            (0..species.len())
+                .find(|&j| species[j] == species[i] && distance(&image, &poscar.frac_coords[j], &lattice) < symprec)
+        })
+        .collect()
+}
+
+/// Builds a supercell repeating the cell `multiplicity` times along each lattice vector.
+/// Atoms are ordered as phonopy does: images of each atom are consecutive, with the first lattice vector running fastest.
+pub fn make_supercell(poscar: &Poscar, multiplicity: [usize; 3]) -> Poscar {
+    let [m0, m1, m2] = multiplicity;
+    let lattice_points: Vec<[usize; 3]> = (0..m2)
+        .flat_map(|z| (0..m1).flat_map(move |y| (0..m0).map(move |x| [x, y, z])))
+        .collect();
+    let mut supercell = poscar.clone();
+    for (row, &m) in supercell.lattice.iter_mut().zip(&multiplicity) {
+        for x in row.iter_mut() {
+            *x *= m as f64;
+        }
+    }
+    supercell.num_atoms = poscar.num_atoms.iter().map(|n| n * lattice_points.len()).collect();
+    supercell.frac_coords = poscar
+        .frac_coords
+        .iter()
+        .flat_map(|frac| {
+            lattice_points
+                .iter()
+                .map(move |point| [0, 1, 2].map(|k| (frac[k] + point[k] as f64) / multiplicity[k] as f64))
+        })
+        .collect();
+    supercell.selective_dynamics = None;
+    supercell
+}
+
+/// Deforms the cell by the strain in Voigt notation (xx, yy, zz, yz, xz, xy), with engineering shear strains.
+/// Fractional coordinates are kept.
+pub fn apply_strain(poscar: &Poscar, strain: &[f64; 6]) -> Poscar {
+    let [e1, e2, e3, e4, e5, e6] = *strain;
+    let deformation = [
+        [1.0 + e1, e6 / 2.0, e5 / 2.0],
+        [e6 / 2.0, 1.0 + e2, e4 / 2.0],
+        [e5 / 2.0, e4 / 2.0, 1.0 + e3],
+    ];
+    let mut strained = poscar.clone();
+    for (row, vector) in strained.lattice.iter_mut().zip(&poscar.lattice) {
+        *row = [0, 1, 2].map(|j| (0..3).map(|k| vector[k] * deformation[k][j]).sum());
+    }
+    strained
+}
+
+mod tests {
+    #[test]
+    fn test_symmetry_operations() {
+        use super::*;
+        let poscar = Poscar {
+            comment: String::from("Si"),
+            scale: 5.43,
+            lattice: [[0.0, 0.5, 0.5], [0.5, 0.0, 0.5], [0.5, 0.5, 0.0]],
+            species: vec![String::from("Si")],
+            num_atoms: vec![2],
+            frac_coords: vec![[0.0, 0.0, 0.0], [0.25, 0.25, 0.25]],
+            selective_dynamics: None,
+        };
+        // Fd-3m has 48 operations in the primitive cell of diamond.
+        let operations = symmetry_operations(&poscar, 1e-3);
+        assert_eq!(operations.len(), 48);
+        for operation in &operations {
+            assert!(symmetry_image_indices(&poscar, operation, 1e-3).is_some());
+        }
+        // Half of them exchange the two atoms.
+        let exchanging = operations
+            .iter()
+            .filter(|op| symmetry_image_indices(&poscar, op, 1e-3).unwrap()[0] == 1)
+            .count();
+        assert_eq!(exchanging, 24);
+    }
+
+    #[test]
+    fn test_make_supercell() {
+        use super::*;
+        let poscar = Poscar {
+            comment: String::from("Si"),
+            scale: 5.43,
+            lattice: [[0.0, 0.5, 0.5], [0.5, 0.0, 0.5], [0.5, 0.5, 0.0]],
+            species: vec![String::from("Si")],
+            num_atoms: vec![2],
+            frac_coords: vec![[0.0, 0.0, 0.0], [0.25, 0.25, 0.25]],
+            selective_dynamics: None,
+        };
+        let supercell = make_supercell(&poscar, [2, 1, 3]);
+        assert_eq!(supercell.num_atoms, vec![12]);
+        assert_eq!(supercell.lattice[0], [0.0, 1.0, 1.0]);
+        assert_eq!(supercell.lattice[2], [1.5, 1.5, 0.0]);
+        // Images of each atom are consecutive, the first lattice vector running fastest.
+        assert_eq!(supercell.frac_coords[0], [0.0, 0.0, 0.0]);
+        assert_eq!(supercell.frac_coords[1], [0.5, 0.0, 0.0]);
+        assert_eq!(supercell.frac_coords[2], [0.0, 0.0, 1.0 / 3.0]);
+        assert_eq!(supercell.frac_coords[6], [0.125, 0.25, 0.25 / 3.0]);
+        assert_eq!(supercell.frac_coords[7], [0.625, 0.25, 0.25 / 3.0]);
+    }
+
+    #[test]
+    fn test_interpolate_linear() {
+        use super::*;
+        let cube = |a: f64| [[a, 0.0, 0.0], [0.0, 10.0, 0.0], [0.0, 0.0, 10.0]];
+        let initial = Poscar {
+            comment: String::from("CO"),
+            scale: 1.0,
+            lattice: cube(10.0),
+            species: vec![String::from("C"), String::from("O")],
+            num_atoms: vec![1, 1],
+            frac_coords: vec![[0.9, 0.5, 0.5], [0.5, 0.5, 0.5]],
+            selective_dynamics: None,
+        };
+        let mut final_ = initial.clone();
+        final_.lattice = cube(12.0);
+        final_.frac_coords = vec![[0.1, 0.5, 0.5], [0.5, 0.7, 0.5]];
+        let images = interpolate_linear(&initial, &final_, 3);
+        assert_eq!(images.len(), 3);
+        assert_eq!(images[0].lattice[0], [10.5, 0.0, 0.0]);
+        assert_eq!(images[2].lattice[0], [11.5, 0.0, 0.0]);
+        // The first atom moves across the cell boundary to the nearest image of its final position.
+        let x: Vec<f64> = images.iter().map(|image| image.frac_coords[0][0]).collect();
+        for (x, expected) in x.iter().zip([0.95, 1.0, 1.05]) {
+            assert!((x - expected).abs() < 1e-12);
+        }
+        assert!((images[1].frac_coords[1][1] - 0.6).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_interpolate_idpp() {
+        use super::*;
+        // Rotation of a bond by 90 degrees around the fixed first atom.
+        let lattice = [[10.0, 0.0, 0.0], [0.0, 10.0, 0.0], [0.0, 0.0, 10.0]];
+        let mut initial = Poscar {
+            comment: String::from("CO"),
+            scale: 1.0,
+            lattice,
+            species: vec![String::from("C"), String::from("O")],
+            num_atoms: vec![1, 1],
+            frac_coords: vec![[0.5, 0.5, 0.5], [0.6, 0.5, 0.5]],
+            selective_dynamics: None,
+        };
+        let mut final_ = initial.clone();
+        final_.frac_coords = vec![[0.5, 0.5, 0.5], [0.5, 0.6, 0.5]];
+        initial.selective_dynamics = Some(vec![[false; 3], [true; 3]]);
+        let bond = |image: &Poscar| distance(&image.frac_coords[0], &image.frac_coords[1], &lattice);
+
+        // Linear interpolation shortens the bond, which IDPP keeps at its length.
+        let linear = interpolate_linear(&initial, &final_, 1);
+        assert!((bond(&linear[0]) - 0.5_f64.sqrt()).abs() < 1e-9);
+        let images = interpolate_idpp(&initial, &final_, 1);
+        assert_eq!(images.len(), 1);
+        assert!((bond(&images[0]) - 1.0).abs() < 1e-3);
+        assert_eq!(images[0].frac_coords[0], [0.5, 0.5, 0.5]);
+        // The atom moves along the bisector.
+        let [x, y, _] = images[0].frac_coords[1];
+        assert!((x - y).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_parse_index_ranges() {
+        use super::*;
+        assert_eq!(parse_index_ranges("1-4, 7").unwrap(), vec![0, 1, 2, 3, 6]);
+        assert_eq!(parse_index_ranges(" 3 ,").unwrap(), vec![2]);
+        assert_eq!(parse_index_ranges("2-2").unwrap(), vec![1]);
+        assert!(parse_index_ranges("4-1").is_err());
+        assert!(parse_index_ranges("0-2").is_err());
+        assert!(parse_index_ranges("1-a").is_err());
+    }
+
+    #[test]
+    fn test_atom_selection() {
+        use super::*;
+        // Slab of two Pt layers, the lower one slightly rumpled, with O on top.
+        let poscar = Poscar {
+            comment: String::from("Pt slab"),
+            scale: 1.0,
+            lattice: [[5.0, 0.0, 0.0], [0.0, 5.0, 0.0], [0.0, 0.0, 10.0]],
+            species: vec![String::from("Pt"), String::from("O")],
+            num_atoms: vec![3, 1],
+            frac_coords: vec![[0.0, 0.0, 0.1], [0.5, 0.5, 0.12], [0.0, 0.5, 0.3], [0.0, 0.5, 0.45]],
+            selective_dynamics: None,
+        };
+        assert_eq!(layer_indices(&cartesian_coords(&poscar), 0.5), vec![0, 0, 1, 2]);
+        assert_eq!(layer_indices(&cartesian_coords(&poscar), 0.1), vec![0, 1, 2, 3]);
+
+        let select = |selection: AtomSelection| selection.select(&poscar).unwrap();
+        let selection = AtomSelection {
+            layer_tolerance: 0.5,
+            ..Default::default()
+        };
+        let indices = AtomSelection {
+            indices: vec![1, 3],
+            ..selection.clone()
+        };
+        assert_eq!(select(indices), vec![false, true, false, true]);
+        let elements = AtomSelection {
+            elements: vec![String::from("O")],
+            ..selection.clone()
+        };
+        assert_eq!(select(elements), vec![false, false, false, true]);
+        let below = AtomSelection {
+            below: Some(2.0),
+            ..selection.clone()
+        };
+        assert_eq!(select(below), vec![true, true, false, false]);
+        let above = AtomSelection {
+            above: Some(0.2),
+            fractional: true,
+            ..selection.clone()
+        };
+        assert_eq!(select(above), vec![false, false, true, true]);
+        let bottom_layers = AtomSelection {
+            bottom_layers: Some(2),
+            ..selection.clone()
+        };
+        assert_eq!(select(bottom_layers), vec![true, true, true, false]);
+        let out_of_range = AtomSelection {
+            indices: vec![4],
+            ..selection
+        };
+        assert!(out_of_range.select(&poscar).is_err());
+    }
+}
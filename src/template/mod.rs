@@ -4,16 +4,49 @@ use serde::Serialize;
 pub use tera::Tera;
 
 pub static JOB_SCRIPT: &str = include_str!("templates/job_script.sh");
+pub static JOB_ARRAY_SCRIPT: &str = include_str!("templates/job_array.sh");
 pub static INCAR_TEMPLATES: phf::Map<&'static str, (&'static str, &'static str)> = phf_map! {
     "INCAR_relax" => (include_str!("templates/INCAR_relax"), "Standard ionic relaxation"),
     "INCAR_singlepoint" => (include_str!("templates/INCAR_singlepoint"), "Single point calculation"),
     "INCAR_bader" => (include_str!("templates/INCAR_bader"), "Bader analysis"),
     "INCAR_aimd" => (include_str!("templates/INCAR_aimd"), "AIMD calculation (NVT)"),
+    "INCAR_dos" => (include_str!("templates/INCAR_dos"), "Density of states (non-SCF on a denser mesh after SCF)"),
+    "INCAR_freq" => (include_str!("templates/INCAR_freq"), "Vibrational frequencies (finite differences)"),
+    "INCAR_phonon" => (include_str!("templates/INCAR_phonon"), "Forces of displaced supercells for phonopy"),
+    "INCAR_eos" => (include_str!("templates/INCAR_eos"), "Equation of state (cells at fixed volumes)"),
+    "INCAR_elastic" => (include_str!("templates/INCAR_elastic"), "Elastic constants (strained cells)"),
+    "INCAR_dimer" => (include_str!("templates/INCAR_dimer"), "Dimer method for saddle point search (requires VTST)"),
+    "INCAR_hse" => (include_str!("templates/INCAR_hse"), "HSE06 hybrid functional (after PBE pre-convergence)"),
+    "INCAR_pbe0" => (include_str!("templates/INCAR_pbe0"), "PBE0 hybrid functional (after PBE pre-convergence)"),
+    "INCAR_scan" => (include_str!("templates/INCAR_scan"), "SCAN meta-GGA (after PBE pre-convergence)"),
+    "INCAR_r2scan" => (include_str!("templates/INCAR_r2scan"), "r2SCAN meta-GGA (after PBE pre-convergence, VASP >= 6.2)"),
+    "INCAR_neb" => (include_str!("templates/INCAR_neb"), "Climbing image NEB (requires VTST)"),
+    "INCAR_mlff_train" => (include_str!("templates/INCAR_mlff_train"), "On-the-fly training of ML force field (VASP >= 6.4)"),
+    "INCAR_mlff_refit" => (include_str!("templates/INCAR_mlff_refit"), "Refit ML force field from ML_AB for production (VASP >= 6.4)"),
+    "INCAR_mlff_run" => (include_str!("templates/INCAR_mlff_run"), "MD with ML force field in ML_FF (VASP >= 6.4)"),
 };
 pub static KPOINTS: &str = include_str!("templates/KPOINTS");
 pub static VASP_TOML: &str = include_str!("templates/Vasp.toml.template");
+/// Templates other than INCAR: (name in `TEMPLATES`, source, description).
+pub static OTHER_TEMPLATES: [(&str, &str, &str); 4] = [
+    ("KPOINTS", KPOINTS, "Automatic k-points mesh"),
+    ("job_script.sh", JOB_SCRIPT, "SLURM job script"),
+    ("job_array.sh", JOB_ARRAY_SCRIPT, "SLURM job array script"),
+    ("Vasp.toml", VASP_TOML, "Job configuration written by init"),
+];
 
-#[derive(Serialize)]
+/// Source of the template by its name, e.g. "INCAR_relax" or "KPOINTS".
+pub fn template_source(name: &str) -> Option<&'static str> {
+    match INCAR_TEMPLATES.get(name) {
+        Some((source, _)) => Some(source),
+        None => OTHER_TEMPLATES
+            .iter()
+            .find(|(other, _, _)| *other == name)
+            .map(|(_, source, _)| *source),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct IncarTag {
     pub name: String,
     pub value: String,
@@ -23,6 +56,7 @@ lazy_static! {
     pub static ref TEMPLATES: Tera = {
         let mut tera = Tera::default();
         tera.add_raw_template("job_script.sh", JOB_SCRIPT).unwrap();
+        tera.add_raw_template("job_array.sh", JOB_ARRAY_SCRIPT).unwrap();
         tera.add_raw_template("KPOINTS", KPOINTS).unwrap();
         tera.add_raw_template("Vasp.toml", VASP_TOML).unwrap();
         for name in INCAR_TEMPLATES.keys() {
@@ -0,0 +1,48 @@
+#!/bin/bash
+#SBATCH -e stderr-%A_%a.log
+#SBATCH -o stdout-%A_%a.log
+#SBATCH -p {{ partition }}
+#SBATCH -N {{ num_nodes }}
+#SBATCH -n {{ num_tasks }}
+#SBATCH --array=0-{{ last_index }}{% if throttle %}%{{ throttle }}{% endif %}
+
+module purge
+module add compiler/2022.1.0
+module add mkl/2022.1.0
+module add mpi/2021.6.0
+
+VASP_VERSION={{ vasp_version }}
+VASP={{ bin }}
+{% if vasp_load %}{{ vasp_load }}
+{% endif %}VASP_BIN="{{ vasp_executable }}"
+
+JOB_DIR=$(sed -n "$((SLURM_ARRAY_TASK_ID + 1))p" {{ dir_list }})
+cd "$JOB_DIR" || exit 1
+if [ ! -f INCAR ]; then
+    # Tasks with sub-jobs (phonon, eos, elastic) run the sub-directories with INCAR one after another.
+    for SUB_DIR in */; do
+        if [ -f "$SUB_DIR/INCAR" ]; then
+            (cd "$SUB_DIR" && mpirun -np $SLURM_NTASKS $VASP_BIN)
+        fi
+    done
+    exit 0
+fi
+{% if pre_step -%}
+# {{ pre_step.description }}
+cp INCAR INCAR.main
+cp INCAR.{{ pre_step.name }} INCAR
+{% if pre_step.kpoints %}cp KPOINTS KPOINTS.main
+cp KPOINTS.{{ pre_step.name }} KPOINTS
+{% endif %}mpirun -np $SLURM_NTASKS $VASP_BIN
+for f in OUTCAR OSZICAR vasprun.xml; do cp $f $f.{{ pre_step.name }}; done
+mv INCAR.main INCAR
+{% if pre_step.kpoints %}mv KPOINTS.main KPOINTS
+{% endif %}
+{% endif -%}
+mpirun -np $SLURM_NTASKS $VASP_BIN
+{% if bader %}
+# Bader analysis with core charge reference
+{{ vasp_manager }} chgsum AECCAR0 AECCAR2 -o CHGCAR_sum
+{{ bader }} CHGCAR -ref CHGCAR_sum
+{{ vasp_manager }} bader
+{% endif -%}
@@ -12,6 +12,31 @@ module add mpi/2021.6.0
 
 VASP_VERSION={{ vasp_version }}
 VASP={{ bin }}
-VASP_BIN="$HOME/.local/VASP/bin/$VASP_VERSION/$VASP"
+{% if vasp_load %}{{ vasp_load }}
+{% endif %}VASP_BIN="{{ vasp_executable }}"
 
+{% if sub_dirs -%}
+for JOB_DIR in {{ sub_dirs }}; do
+    (cd "$JOB_DIR" && mpirun -np $SLURM_NTASKS $VASP_BIN)
+done
+{% else -%}
+{% if pre_step -%}
+# {{ pre_step.description }}
+cp INCAR INCAR.main
+cp INCAR.{{ pre_step.name }} INCAR
+{% if pre_step.kpoints %}cp KPOINTS KPOINTS.main
+cp KPOINTS.{{ pre_step.name }} KPOINTS
+{% endif %}mpirun -np $SLURM_NTASKS $VASP_BIN
+for f in OUTCAR OSZICAR vasprun.xml; do cp $f $f.{{ pre_step.name }}; done
+mv INCAR.main INCAR
+{% if pre_step.kpoints %}mv KPOINTS.main KPOINTS
+{% endif %}
+{% endif -%}
 mpirun -np $SLURM_NTASKS $VASP_BIN
+{% if bader %}
+# Bader analysis with core charge reference
+{{ vasp_manager }} chgsum AECCAR0 AECCAR2 -o CHGCAR_sum
+{{ bader }} CHGCAR -ref CHGCAR_sum
+{{ vasp_manager }} bader
+{% endif -%}
+{% endif -%}
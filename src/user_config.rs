@@ -0,0 +1,56 @@
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Environment variable overriding the path of the user config.
+pub const USER_CONFIG_ENV: &str = "VASP_MANAGER_CONFIG";
+/// Path of the user config, relative to the home directory.
+pub const USER_CONFIG_FILE: &str = ".config/vasp_manager/config.toml";
+
+/// A VASP binary in the registry of the user config: either the absolute path of the binary,
+/// or a command loading it (e.g. `module load vasp/6.4.2`) after which `executable` is on PATH.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum VaspBinEntry {
+    Path(String),
+    Module {
+        load: String,
+        /// Name of the binary after loading, the name of the entry by default.
+        executable: Option<String>,
+    },
+}
+
+/// User config in `~/.config/vasp_manager/config.toml`, shared by all jobs.
+///
+/// ```toml
+/// [vasp."6.4.2"]
+/// vasp_std = "/opt/vasp/6.4.2/bin/vasp_std"
+/// vasp_gam = { load = "module load vasp/6.4.2" }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserConfig {
+    /// Registry of VASP binaries by version and binary name.
+    #[serde(default)]
+    pub vasp: BTreeMap<String, BTreeMap<String, VaspBinEntry>>,
+}
+
+impl UserConfig {
+    /// Path of the user config, given by `VASP_MANAGER_CONFIG` or `~/.config/vasp_manager/config.toml`.
+    pub fn path() -> Option<PathBuf> {
+        match std::env::var(USER_CONFIG_ENV) {
+            Ok(path) => Some(PathBuf::from(path)),
+            Err(_) => home::home_dir().map(|home_dir| home_dir.join(USER_CONFIG_FILE)),
+        }
+    }
+
+    /// Reads the user config, which is empty if the file does not exist.
+    pub fn load() -> Result<UserConfig> {
+        let path = match UserConfig::path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(UserConfig::default()),
+        };
+        let contents = std::fs::read_to_string(&path)?;
+        toml::from_str(&contents).map_err(|e| eyre::eyre!("Could not parse {}: {}", path.display(), e))
+    }
+}